@@ -0,0 +1,331 @@
+//! Headless command-line front end for the tool engine.
+//!
+//! ```text
+//! lazycat <domain> <action> [--payload JSON|-] [--<field> VALUE|-]... [--raw] [--compact]
+//! lazycat list [domain]
+//! ```
+//!
+//! The payload is built from `--payload` (a JSON object, or `-` for stdin) and
+//! then per-field flags. Field flags accept camelCase, snake_case or kebab-case
+//! names and are typed from the action's spec; `-` reads the value from stdin.
+//! With no payload arguments and piped stdin, stdin is read as the JSON payload.
+
+use lazycat_lib::tools::{self, registry::ActionSpec, ToolError, ToolResult};
+use serde_json::{Map, Value};
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const USAGE: &str = "usage:
+  lazycat <domain> <action> [--payload JSON|-] [--<field> VALUE|-]... [--raw] [--compact]
+  lazycat list [domain]
+
+options:
+  --payload JSON   payload object; `-` reads it from stdin
+  --<field> VALUE  set one payload field; `-` reads the value from stdin
+  --raw            print string results without JSON quoting
+  --compact        print JSON on a single line
+  -h, --help       show this help
+
+environment:
+  LAZYCAT_RESOURCES_DIR  folder holding manuals/, regex-library/ and hotkey-library/;
+                         by default the app's bundled resources next to this executable
+
+exit status: 0 success, 1 tool failure, 2 usage or payload error";
+
+#[derive(Debug, Default, PartialEq)]
+struct Args {
+    domain: String,
+    action: String,
+    payload: Option<String>,
+    fields: Vec<(String, Option<String>)>,
+    raw: bool,
+    compact: bool,
+}
+
+enum Command {
+    Help,
+    List { domain: Option<String>, compact: bool },
+    Run(Args),
+}
+
+fn parse_args(argv: &[String]) -> Result<Command, String> {
+    let mut positional = Vec::new();
+    let mut args = Args::default();
+    let mut iter = argv.iter().peekable();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--raw" => args.raw = true,
+            "--compact" => args.compact = true,
+            "--payload" => {
+                let value = iter.next().ok_or("--payload needs a value")?;
+                args.payload = Some(value.clone());
+            }
+            flag if flag.starts_with("--") && flag.len() > 2 => {
+                let name = &flag[2..];
+                let (name, value) = match name.split_once('=') {
+                    Some((n, v)) => (n.to_string(), Some(v.to_string())),
+                    // A flag followed by another flag (or nothing) is a bare boolean switch.
+                    None => match iter.peek() {
+                        Some(next) if !next.starts_with("--") => (name.to_string(), iter.next().cloned()),
+                        _ => (name.to_string(), None),
+                    },
+                };
+                args.fields.push((name, value));
+            }
+            _ => positional.push(arg.clone()),
+        }
+    }
+    match positional.as_slice() {
+        [] => Ok(Command::Help),
+        [cmd, rest @ ..] if cmd == "list" && rest.len() <= 1 => Ok(Command::List { domain: rest.first().cloned(), compact: args.compact }),
+        [domain, action] => {
+            args.domain = domain.clone();
+            args.action = action.clone();
+            Ok(Command::Run(args))
+        }
+        _ => Err(format!("expected <domain> <action>, got: {}", positional.join(" "))),
+    }
+}
+
+/// `source-path`, `source_path` and `sourcePath` all name the same field.
+fn normalize_name(name: &str) -> String {
+    name.chars().filter(|c| *c != '-' && *c != '_').flat_map(char::to_lowercase).collect()
+}
+
+fn typed_value(kind: &str, name: &str, raw: Option<String>) -> ToolResult {
+    let Some(raw) = raw else {
+        return match kind {
+            "boolean" | "" => Ok(Value::Bool(true)),
+            _ => Err(ToolError::invalid_field(name, format!("--{name} needs a value"))),
+        };
+    };
+    let bad = |e: String| ToolError::invalid_field(name, format!("--{name}: {e}"));
+    match kind {
+        "integer" => raw.parse::<i64>().map(Value::from).map_err(|e| bad(e.to_string())),
+        "number" => raw.parse::<f64>().map(Value::from).map_err(|e| bad(e.to_string())),
+        "boolean" => raw.parse::<bool>().map(Value::Bool).map_err(|e| bad(e.to_string())),
+        "array" | "object" => serde_json::from_str(&raw).map_err(|e| ToolError::json(&format!("--{name}"), &e)),
+        // Text that is not JSON stays a string, so `--input hello` still works.
+        "json" => Ok(serde_json::from_str(&raw).unwrap_or(Value::String(raw))),
+        _ => Ok(Value::String(raw)),
+    }
+}
+
+fn read_stdin() -> ToolResult<String> {
+    let mut buf = String::new();
+    io::stdin()
+        .read_to_string(&mut buf)
+        .map_err(|e| ToolError::io("read stdin failed", e))?;
+    Ok(buf)
+}
+
+fn build_payload(args: &Args, spec: &ActionSpec) -> ToolResult {
+    let mut stdin_used = false;
+    let mut take_stdin = |what: &str| -> ToolResult<String> {
+        if stdin_used {
+            return Err(ToolError::invalid(format!("stdin is already used, cannot read {what} from it")));
+        }
+        stdin_used = true;
+        read_stdin()
+    };
+
+    let mut payload = match args.payload.as_deref() {
+        Some("-") => take_stdin("--payload")?,
+        Some(json) => json.to_string(),
+        None if args.fields.is_empty() && !io::stdin().is_terminal() => take_stdin("the payload")?,
+        None => String::new(),
+    };
+    if payload.trim().is_empty() {
+        payload = "{}".to_string();
+    }
+    let mut object = match serde_json::from_str::<Value>(&payload) {
+        Ok(Value::Object(map)) => map,
+        Ok(_) => return Err(ToolError::invalid_field("payload", "payload must be a JSON object")),
+        Err(e) => return Err(ToolError::json("invalid payload", &e)),
+    };
+
+    for (flag, value) in &args.fields {
+        let field = spec
+            .payload
+            .iter()
+            .find(|f| normalize_name(f.name) == normalize_name(flag));
+        let (name, kind) = match field {
+            Some(f) => (f.name.to_string(), f.kind),
+            None => (flag.clone(), ""),
+        };
+        let value = match value.as_deref() {
+            Some("-") => Some(take_stdin(&format!("--{flag}"))?),
+            _ => value.clone(),
+        };
+        object.insert(name.clone(), typed_value(kind, &name, value)?);
+    }
+    Ok(Value::Object(object))
+}
+
+fn resolve_action(domain: &str, action: &str) -> ToolResult<&'static ActionSpec> {
+    tools::registry::find(tools::DOMAINS, domain)
+        .and_then(|d| d.action(action))
+        .ok_or_else(|| ToolError::unsupported(domain, action))
+}
+
+/// `LAZYCAT_RESOURCES_DIR`, else the first resource folder found relative to
+/// the executable, where the app bundle puts it on each platform (beside the
+/// exe on Windows, `../Resources` on macOS, `../lib/Lazycat` on Linux). Dev
+/// builds fall back to the repo copy. Never relative to the working directory.
+fn resources_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("LAZYCAT_RESOURCES_DIR") {
+        return PathBuf::from(dir);
+    }
+    let exe_dir = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.canonicalize().ok())
+        .and_then(|exe| exe.parent().map(Path::to_path_buf));
+    let mut candidates: Vec<PathBuf> = exe_dir
+        .iter()
+        .flat_map(|dir| [dir.clone(), dir.join("../Resources"), dir.join("../lib/Lazycat")])
+        .collect();
+    candidates.push(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../../resources"));
+    candidates
+        .into_iter()
+        .find(|dir| dir.join("regex-library").is_dir())
+        .or(exe_dir)
+        .unwrap_or_default()
+}
+
+fn init_resources() {
+    let dir = resources_dir();
+    let _ = tools::regex::REGEX_TEMPLATES_DIR.set(dir.join("regex-library"));
+    let _ = tools::hotkey::HOTKEY_MAPPINGS_DIR.set(dir.join("hotkey-library"));
+    let _ = tools::manuals::MANUALS_DIR.set(dir.join("manuals"));
+}
+
+fn print(value: &Value, raw: bool, compact: bool) -> io::Result<()> {
+    let mut out = io::stdout().lock();
+    match value {
+        Value::String(s) if raw => {
+            out.write_all(s.as_bytes())?;
+            if !s.ends_with('\n') {
+                out.write_all(b"\n")?;
+            }
+        }
+        _ if compact => writeln!(out, "{value}")?,
+        _ => writeln!(out, "{}", serde_json::to_string_pretty(value).unwrap_or_default())?,
+    }
+    out.flush()
+}
+
+fn report_error(err: &ToolError) -> ExitCode {
+    let mut body = Map::new();
+    body.insert("code".into(), err.code().into());
+    body.insert("message".into(), err.message().into());
+    if let Some(details) = err.details() {
+        body.insert("details".into(), details);
+    }
+    eprintln!("{}", Value::Object(body));
+    match err {
+        ToolError::InvalidPayload { .. } | ToolError::UnsupportedAction { .. } => ExitCode::from(2),
+        _ => ExitCode::from(1),
+    }
+}
+
+fn main() -> ExitCode {
+    let argv: Vec<String> = std::env::args().skip(1).collect();
+    let command = match parse_args(&argv) {
+        Ok(command) => command,
+        Err(message) => {
+            eprintln!("{message}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    init_resources();
+
+    let (result, raw, compact) = match command {
+        Command::Help => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Command::List { domain, compact } => (tools::describe_tools(domain.as_deref()), false, compact),
+        Command::Run(args) => {
+            let result = resolve_action(&args.domain, &args.action)
+                .and_then(|spec| build_payload(&args, spec))
+                .and_then(|payload| tools::execute_tool(&args.domain, &args.action, &payload));
+            (result, args.raw, args.compact)
+        }
+    };
+    match result {
+        Ok(value) => match print(&value, raw, compact) {
+            Ok(()) => ExitCode::SUCCESS,
+            // A closed pipe (`lazycat ... | head`) is not a tool failure.
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+            Err(e) => report_error(&ToolError::io("write stdout failed", e)),
+        },
+        Err(err) => report_error(&err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argv(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn parse_run_with_fields_and_switches() {
+        let Ok(Command::Run(args)) = parse_args(&argv("encode base64_encode --input hello --raw --flag"))
+        else {
+            panic!("expected run command");
+        };
+        assert_eq!(args.domain, "encode");
+        assert_eq!(args.action, "base64_encode");
+        assert!(args.raw);
+        assert_eq!(
+            args.fields,
+            vec![("input".to_string(), Some("hello".to_string())), ("flag".to_string(), None)]
+        );
+    }
+
+    #[test]
+    fn fields_are_typed_from_spec() {
+        let spec = resolve_action("file", "split").expect("spec");
+        let args = Args {
+            payload: Some(r#"{"outputDir":"out"}"#.to_string()),
+            fields: vec![
+                ("source-path".to_string(), Some("a.bin".to_string())),
+                ("chunk_size_mb".to_string(), Some("5".to_string())),
+            ],
+            ..Args::default()
+        };
+        let payload = build_payload(&args, spec).expect("payload");
+        assert_eq!(payload["sourcePath"], "a.bin");
+        assert_eq!(payload["chunkSizeMb"], 5);
+        assert_eq!(payload["outputDir"], "out");
+    }
+
+    #[test]
+    fn json_fields_take_any_value() {
+        let spec = resolve_action("pipeline", "run").expect("spec");
+        let args = |input: &str| Args {
+            payload: Some("{}".to_string()),
+            fields: vec![("input".to_string(), Some(input.to_string()))],
+            ..Args::default()
+        };
+        assert_eq!(build_payload(&args(r#"{"a":1}"#), spec).expect("payload")["input"], serde_json::json!({ "a": 1 }));
+        assert_eq!(build_payload(&args("hello"), spec).expect("payload")["input"], "hello");
+    }
+
+    #[test]
+    fn bad_typed_field_is_invalid_payload() {
+        let spec = resolve_action("file", "split").expect("spec");
+        let args = Args {
+            payload: Some("{}".to_string()),
+            fields: vec![("chunkSizeMb".to_string(), Some("lots".to_string()))],
+            ..Args::default()
+        };
+        let err = build_payload(&args, spec).expect_err("should fail");
+        assert_eq!(err.code(), "INVALID_PAYLOAD");
+    }
+}
@@ -0,0 +1,276 @@
+//! Minimal HTTP/1.1 request parsing and response writing for the loopback
+//! servers, and the bounded worker pool they accept connections on.
+
+use std::io::{self, BufRead, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+pub const MAX_HEADER_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub method: String,
+    /// Percent-decoded path without the query string.
+    pub path: String,
+    pub query: Option<String>,
+    pub version: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// HTTP/1.1 keeps the connection unless told to close; HTTP/1.0 only on request.
+    pub fn keep_alive(&self) -> bool {
+        let connection = self.header("connection").unwrap_or_default().to_ascii_lowercase();
+        if self.version == "HTTP/1.0" {
+            connection.contains("keep-alive")
+        } else {
+            !connection.contains("close")
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ReadError {
+    /// The peer closed (or timed out) before sending a request line.
+    Closed,
+    /// Malformed or oversized request; answer with this status and close.
+    Bad(u16, String),
+}
+
+fn read_line<R: BufRead>(reader: &mut R, budget: &mut usize) -> Result<Option<String>, ReadError> {
+    let mut line = Vec::new();
+    let n = reader
+        .by_ref()
+        .take(*budget as u64 + 1)
+        .read_until(b'\n', &mut line)
+        .map_err(|_| ReadError::Closed)?;
+    if n == 0 {
+        return Ok(None);
+    }
+    if n > *budget {
+        return Err(ReadError::Bad(431, "request headers too large".into()));
+    }
+    *budget -= n;
+    while matches!(line.last(), Some(b'\n' | b'\r')) {
+        line.pop();
+    }
+    Ok(Some(String::from_utf8_lossy(&line).into_owned()))
+}
+
+/// Read one request. `max_body` caps `Content-Length`.
+pub fn read_request<R: BufRead>(reader: &mut R, max_body: usize) -> Result<Request, ReadError> {
+    let mut budget = MAX_HEADER_BYTES;
+    // Tolerate stray blank lines between pipelined requests.
+    let request_line = loop {
+        match read_line(reader, &mut budget)? {
+            None => return Err(ReadError::Closed),
+            Some(line) if line.is_empty() => continue,
+            Some(line) => break line,
+        }
+    };
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target), version) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(ReadError::Bad(400, "malformed request line".into()));
+    };
+    if !target.starts_with('/') {
+        return Err(ReadError::Bad(400, "malformed request target".into()));
+    }
+    let (raw_path, query) = match target.split_once('?') {
+        Some((p, q)) => (p, Some(q.to_string())),
+        None => (target, None),
+    };
+    let path = urlencoding::decode(raw_path)
+        .map(|p| p.into_owned())
+        .unwrap_or_else(|_| raw_path.to_string());
+
+    let mut headers = Vec::new();
+    loop {
+        match read_line(reader, &mut budget)? {
+            None => return Err(ReadError::Bad(400, "incomplete request headers".into())),
+            Some(line) if line.is_empty() => break,
+            Some(line) => {
+                if let Some((k, v)) = line.split_once(':') {
+                    headers.push((k.trim().to_string(), v.trim().to_string()));
+                }
+            }
+        }
+    }
+
+    let mut request = Request {
+        method: method.to_string(),
+        path,
+        query,
+        version: version.unwrap_or("HTTP/1.0").to_string(),
+        headers,
+        body: Vec::new(),
+    };
+    let length = match request.header("content-length") {
+        Some(v) => v
+            .parse::<usize>()
+            .map_err(|_| ReadError::Bad(400, "invalid Content-Length".into()))?,
+        None => 0,
+    };
+    if length > max_body {
+        return Err(ReadError::Bad(413, "request body too large".into()));
+    }
+    request.body.resize(length, 0);
+    reader
+        .read_exact(&mut request.body)
+        .map_err(|_| ReadError::Bad(400, "incomplete request body".into()))?;
+    Ok(request)
+}
+
+pub fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        204 => "No Content",
+        206 => "Partial Content",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        416 => "Range Not Satisfiable",
+        431 => "Request Header Fields Too Large",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "Unknown",
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status: u16) -> Self {
+        Response { status, headers: Vec::new(), body: Vec::new() }
+    }
+
+    pub fn header(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.headers.push((name.to_string(), value.into()));
+        self
+    }
+
+    pub fn body(mut self, content_type: &str, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self.header("Content-Type", content_type)
+    }
+
+    pub fn get_header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Write status line, headers (plus Content-Length and Connection) and,
+    /// unless `head_only`, the body.
+    pub fn write_to<W: Write>(&self, out: &mut W, head_only: bool, keep_alive: bool) -> io::Result<()> {
+        let mut head = format!("HTTP/1.1 {} {}\r\n", self.status, reason(self.status));
+        for (k, v) in &self.headers {
+            head.push_str(&format!("{k}: {v}\r\n"));
+        }
+        if self.get_header("content-length").is_none() {
+            head.push_str(&format!("Content-Length: {}\r\n", self.body.len()));
+        }
+        head.push_str(if keep_alive { "Connection: keep-alive\r\n\r\n" } else { "Connection: close\r\n\r\n" });
+        out.write_all(head.as_bytes())?;
+        if !head_only && self.status != 304 {
+            out.write_all(&self.body)?;
+        }
+        out.flush()
+    }
+}
+
+/// Accepts connections until `stop` returns true (checked after each
+/// accept), handing them to `workers` threads running `handle`. Up to
+/// `queue_depth` connections wait for a worker; beyond that they get a 503.
+/// Blocks the calling thread; workers exit once the queue drains.
+pub fn serve<S, H>(listener: TcpListener, workers: usize, queue_depth: usize, stop: S, handle: H)
+where
+    S: Fn() -> bool,
+    H: Fn(TcpStream) + Send + Sync + 'static,
+{
+    let (tx, rx) = mpsc::sync_channel::<TcpStream>(queue_depth);
+    let rx = Arc::new(Mutex::new(rx));
+    let handle = Arc::new(handle);
+    for _ in 0..workers {
+        let (rx, handle) = (rx.clone(), handle.clone());
+        thread::spawn(move || loop {
+            let stream = match rx.lock().unwrap_or_else(|e| e.into_inner()).recv() {
+                Ok(stream) => stream,
+                Err(_) => return,
+            };
+            handle(stream);
+        });
+    }
+    for stream in listener.incoming() {
+        if stop() {
+            return;
+        }
+        let Ok(stream) = stream else { continue };
+        if let Err(TrySendError::Full(mut stream)) = tx.try_send(stream) {
+            let _ = Response::new(503)
+                .header("Retry-After", "1")
+                .write_to(&mut stream, false, false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufReader;
+    use std::net::TcpStream;
+
+    #[test]
+    fn parses_pipelined_requests_with_query_and_body() {
+        let raw = b"GET /docs/a%20b.html?x=1 HTTP/1.1\r\nHost: localhost\r\n\r\n\
+                    POST /rpc HTTP/1.1\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}";
+        let mut reader = BufReader::new(&raw[..]);
+        let first = read_request(&mut reader, 1024).expect("first");
+        assert_eq!(first.path, "/docs/a b.html");
+        assert_eq!(first.query.as_deref(), Some("x=1"));
+        assert!(first.keep_alive());
+        let second = read_request(&mut reader, 1024).expect("second");
+        assert_eq!(second.body, b"{}");
+        assert!(!second.keep_alive());
+        assert!(matches!(read_request(&mut reader, 1024), Err(ReadError::Closed)));
+    }
+
+    #[test]
+    fn serve_answers_503_when_the_queue_is_full() {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let addr = listener.local_addr().expect("addr");
+        // No workers: the first connection waits in the queue, the second overflows it.
+        thread::spawn(move || serve(listener, 0, 1, || false, |_| {}));
+        let _queued = TcpStream::connect(addr).expect("connect");
+        let mut rejected = TcpStream::connect(addr).expect("connect");
+        let mut head = String::new();
+        BufReader::new(&mut rejected).read_line(&mut head).expect("read");
+        assert_eq!(head.trim_end(), "HTTP/1.1 503 Service Unavailable");
+    }
+
+    #[test]
+    fn rejects_oversized_body() {
+        let raw = b"POST / HTTP/1.1\r\nContent-Length: 10\r\n\r\n0123456789";
+        let err = read_request(&mut BufReader::new(&raw[..]), 4).expect_err("too large");
+        assert!(matches!(err, ReadError::Bad(413, _)));
+    }
+}
@@ -1 +1,4 @@
-// Reserved for shared Rust logic across tests and commands.
+// Shared Rust logic for the desktop app and the headless `lazycat` CLI.
+pub mod http;
+pub mod manual_server;
+pub mod tools;
@@ -1,191 +1,31 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-mod tools;
-
 use tauri::{
     menu::{Menu, MenuItem},
     tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
-    Manager, WindowEvent,
+    Emitter, Manager, WindowEvent,
 };
 use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
 
-use serde::{Deserialize, Serialize};
-use serde_json::Value;
-use std::collections::HashMap;
-use std::fs;
-use std::io::{Read, Write};
-use std::net::{TcpListener, TcpStream};
-use std::path::{Path, PathBuf};
+use lazycat_lib::{manual_server, tools};
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Instant;
 
 use tools::hotkey::HOTKEY_MAPPINGS_DIR;
-use tools::manuals::MANUAL_SERVERS;
+use tools::manuals::{MANUALS_DIR, MANUAL_SERVER_PORT};
+use tools::protocol::{ToolRequest, ToolResponse};
 use tools::regex::REGEX_TEMPLATES_DIR;
 
-fn start_manual_server(root_dir: PathBuf) -> u16 {
-    let listener = TcpListener::bind("127.0.0.1:0").expect("bind manual server");
-    let port = listener.local_addr().unwrap().port();
-    std::thread::spawn(move || {
-        for stream in listener.incoming().flatten() {
-            let dir = root_dir.clone();
-            std::thread::spawn(move || handle_manual_request(stream, &dir));
-        }
-    });
-    port
-}
-
-fn handle_manual_request(mut stream: TcpStream, root_dir: &Path) {
-    let mut buf = [0u8; 4096];
-    let n = match stream.read(&mut buf) {
-        Ok(n) if n > 0 => n,
-        _ => return,
-    };
-    let request = String::from_utf8_lossy(&buf[..n]);
-    let path = request
-        .lines()
-        .next()
-        .and_then(|line| line.split_whitespace().nth(1))
-        .unwrap_or("/");
-    // 解码 URL 编码的路径 (%xx)
-    let decoded_path = urlencoding::decode(path).unwrap_or_else(|_| path.into());
-    let rel = decoded_path.trim_start_matches('/');
-    let file_path = root_dir.join(rel);
-    // 安全检查：防止路径穿越
-    if !file_path.starts_with(root_dir) {
-        let resp = "HTTP/1.1 403 Forbidden\r\nContent-Length: 9\r\n\r\nForbidden";
-        let _ = stream.write_all(resp.as_bytes());
-        return;
-    }
-    // 如果是目录，尝试 index.html；如果文件不存在且无扩展名，尝试加 .html
-    let file_path = if file_path.is_dir() {
-        file_path.join("index.html")
-    } else if !file_path.exists() && file_path.extension().is_none() {
-        let with_html = file_path.with_extension("html");
-        if with_html.exists() {
-            with_html
-        } else {
-            // 也尝试作为目录 + index.html（无扩展名的无文件情况）
-            file_path.join("index.html")
-        }
-    } else {
-        file_path
-    };
-    // VitePress lean.js fallback: 请求 foo.js 但磁盘只有 foo.lean.js
-    let file_path = if !file_path.exists() {
-        if let Some(ext) = file_path.extension().and_then(|e| e.to_str()) {
-            if ext == "js" {
-                let lean = file_path.with_extension("lean.js");
-                if lean.exists() { lean } else { file_path }
-            } else { file_path }
-        } else { file_path }
-    } else { file_path };
-
-    match fs::read(&file_path) {
-        Ok(body) => {
-            let mime = match file_path.extension().and_then(|e| e.to_str()) {
-                Some("html") | Some("htm") => "text/html; charset=utf-8",
-                Some("css")  => "text/css",
-                Some("js") | Some("mjs") => "application/javascript",
-                Some("json") => "application/json",
-                Some("png")  => "image/png",
-                Some("jpg") | Some("jpeg") => "image/jpeg",
-                Some("gif")  => "image/gif",
-                Some("svg")  => "image/svg+xml",
-                Some("woff") => "font/woff",
-                Some("woff2")=> "font/woff2",
-                Some("ttf")  => "font/ttf",
-                Some("ico")  => "image/x-icon",
-                Some("xml")  => "application/xml",
-                Some("txt")  => "text/plain; charset=utf-8",
-                Some("wasm") => "application/wasm",
-                None             => {
-                    // 无扩展名：检测 body 是否以 HTML doctype 开头（跳过可能的 UTF-8 BOM）
-                    let content = if body.starts_with(&[0xEF, 0xBB, 0xBF]) { &body[3..] } else { &body[..] };
-                    if content.starts_with(b"<!DOCTYPE") || content.starts_with(b"<!doctype") || content.starts_with(b"<html") {
-                        "text/html; charset=utf-8"
-                    } else {
-                        "application/octet-stream"
-                    }
-                }
-                Some(_)          => "application/octet-stream",
-            };
-            let header = format!(
-                "HTTP/1.1 200 OK\r\nContent-Type: {mime}\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\n\r\n",
-                body.len()
-            );
-            let _ = stream.write_all(header.as_bytes());
-            let _ = stream.write_all(&body);
-        }
-        Err(_) => {
-            let resp = "HTTP/1.1 404 Not Found\r\nContent-Length: 9\r\n\r\nNot Found";
-            let _ = stream.write_all(resp.as_bytes());
-        }
-    }
-}
-
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "snake_case")]
-struct ToolRequest {
-    request_id: String,
-    domain: String,
-    action: String,
-    payload: Value,
-}
-
-#[derive(Debug, Serialize)]
-#[serde(rename_all = "snake_case")]
-struct ToolError {
-    code: String,
-    message: String,
-    details: Option<Value>,
-}
-
-#[derive(Debug, Serialize)]
-#[serde(rename_all = "snake_case")]
-struct ToolMeta {
-    duration_ms: u128,
-    warnings: Option<Vec<String>>,
-}
-
-#[derive(Debug, Serialize)]
-#[serde(rename_all = "snake_case")]
-struct ToolResponse {
-    request_id: String,
-    ok: bool,
-    data: Option<Value>,
-    error: Option<ToolError>,
-    meta: ToolMeta,
+#[tauri::command]
+fn tool_execute(request: ToolRequest) -> ToolResponse {
+    tools::protocol::execute_request(request)
 }
 
+/// Same envelope as `tool_execute`, so errors keep their code and details.
 #[tauri::command]
-fn tool_execute(request: ToolRequest) -> ToolResponse {
-    let start = Instant::now();
-    match tools::execute_tool(&request.domain, &request.action, &request.payload) {
-        Ok(data) => ToolResponse {
-            request_id: request.request_id,
-            ok: true,
-            data: Some(data),
-            error: None,
-            meta: ToolMeta {
-                duration_ms: start.elapsed().as_millis(),
-                warnings: None,
-            },
-        },
-        Err(message) => ToolResponse {
-            request_id: request.request_id,
-            ok: false,
-            data: None,
-            error: Some(ToolError {
-                code: "TOOL_EXECUTION_FAILED".to_string(),
-                message,
-                details: None,
-            }),
-            meta: ToolMeta {
-                duration_ms: start.elapsed().as_millis(),
-                warnings: None,
-            },
-        },
-    }
+fn tool_describe(domain: Option<String>) -> ToolResponse {
+    tools::protocol::respond(String::new(), tools::describe_tools(domain.as_deref()), Instant::now())
 }
 
 #[tauri::command]
@@ -227,6 +67,12 @@ fn main() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_global_shortcut::Builder::new().build())
         .setup(|app| {
+            // 工具事件（后台任务进度等）转发为 Tauri 事件
+            let event_handle = app.handle().clone();
+            tools::events::set_sink(Box::new(move |event, payload| {
+                let _ = event_handle.emit(event, payload.clone());
+            }));
+
             // 启动离线文档 HTTP 服务器
             // 打包后从 resource_dir/manuals 读取；开发模式下 fallback 到源码目录
             let manuals_dir = {
@@ -241,19 +87,38 @@ fn main() {
                 }
             };
             if manuals_dir.exists() {
-                let mut ports = HashMap::new();
-                if let Ok(entries) = fs::read_dir(&manuals_dir) {
-                    for entry in entries.flatten() {
-                        let path = entry.path();
-                        if path.is_dir() {
-                            if let Some(id) = path.file_name().and_then(|n| n.to_str()) {
-                                let port = start_manual_server(path.clone());
-                                ports.insert(id.to_string(), port);
-                            }
-                        }
-                    }
+                let _ = MANUALS_DIR.set(manuals_dir);
+            }
+            match manual_server::start(Arc::new(tools::manuals::sites)) {
+                Ok(port) => {
+                    let _ = MANUAL_SERVER_PORT.set(port);
                 }
-                let _ = MANUAL_SERVERS.set(ports);
+                Err(e) => eprintln!("manual server not started: {e}"),
+            }
+            // 后台增量更新手册全文索引（进度通过 jobs 事件上报）
+            let index_job = serde_json::json!({ "domain": "manuals", "action": "index" });
+            if let Err(e) = tools::jobs::execute("start", &index_job) {
+                eprintln!("manual index job not started: {}", e.message());
+            }
+
+            // 启动时执行一次数据库迁移；加密数据库在解锁后首次访问时执行
+            if let Err(e) = tools::helpers::db_conn() {
+                eprintln!("database not opened at startup: {e}");
+            }
+
+            // 按计划备份数据库（设置 backup.enabled / backup.schedule / backup.retention）
+            if let Err(e) = tools::backup::start_scheduler() {
+                eprintln!("backup scheduler not started: {}", e.message());
+            }
+
+            // 按计划刷新 hosts 订阅配置
+            if let Err(e) = tools::hosts_subscription::start_scheduler() {
+                eprintln!("hosts subscription scheduler not started: {}", e.message());
+            }
+
+            // 本地 HTTP API（设置 api.enabled 开启）
+            if let Err(e) = tools::api::init() {
+                eprintln!("local api server not started: {e}");
             }
 
             // 初始化正则模板目录
@@ -330,6 +195,7 @@ fn main() {
         })
         .invoke_handler(tauri::generate_handler![
             tool_execute,
+            tool_describe,
             register_hotkey,
             unregister_hotkey
         ])
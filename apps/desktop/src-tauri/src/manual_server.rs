@@ -0,0 +1,606 @@
+//! Static file server for the offline manuals (VitePress / MDN bundles).
+//!
+//! One loopback port serves every manual under `/manual/<id>/...`. The
+//! bundles are built for the site root, so absolute asset and router paths
+//! (`/assets/app.js`, `/zh-CN/...`) are mapped back to a manual through the
+//! `Referer` header, or by probing the manuals when exactly one has the file.
+//!
+//! `route` / `serve_file` are pure request → response functions so they can
+//! be tested without sockets; `start` wires them to a bounded worker pool.
+
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::http::{self, ReadError, Request, Response};
+use crate::tools::manual_site::Site;
+
+/// Idle time before a keep-alive connection is dropped. Kept short because
+/// an idle connection holds a worker.
+const KEEP_ALIVE_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_REQUESTS_PER_CONNECTION: usize = 200;
+const WORKERS: usize = 8;
+/// Accepted connections waiting for a worker; beyond this we answer 503.
+const QUEUE_DEPTH: usize = 64;
+/// Text assets smaller than this are sent uncompressed.
+const MIN_COMPRESS_BYTES: usize = 1024;
+/// Upper bound for the in-memory cache of compressed assets.
+const COMPRESSED_CACHE_BYTES: usize = 64 * 1024 * 1024;
+/// URL prefix under which each manual is mounted.
+pub const MOUNT_PREFIX: &str = "/manual/";
+/// Webview origins allowed to read manual responses cross-origin
+/// (packaged app on macOS/Linux, on Windows, and the Vite dev server).
+pub const ALLOWED_ORIGINS: &[&str] = &[
+    "tauri://localhost",
+    "http://tauri.localhost",
+    "https://tauri.localhost",
+    "http://localhost:5173",
+];
+
+/// Manual id → site (folder or zip archive).
+pub type Sites = BTreeMap<String, Site>;
+
+/// Source of the mounted manuals, asked once per request so that installed
+/// and removed packages show up without a restart.
+pub type SiteProvider = Arc<dyn Fn() -> Sites + Send + Sync>;
+
+/// Bind one loopback port serving the manuals from `sites` and return the port.
+pub fn start(sites: SiteProvider) -> io::Result<u16> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let port = listener.local_addr()?.port();
+    thread::spawn(move || {
+        http::serve(listener, WORKERS, QUEUE_DEPTH, || false, move |stream| handle_connection(stream, &sites))
+    });
+    Ok(port)
+}
+
+fn handle_connection(stream: TcpStream, sites: &SiteProvider) {
+    let _ = stream.set_read_timeout(Some(KEEP_ALIVE_TIMEOUT));
+    let _ = stream.set_nodelay(true);
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    let mut reader = BufReader::new(stream);
+    for served in 1..=MAX_REQUESTS_PER_CONNECTION {
+        let request = match http::read_request(&mut reader, 0) {
+            Ok(request) => request,
+            Err(ReadError::Closed) => return,
+            Err(ReadError::Bad(status, message)) => {
+                let _ = Response::new(status)
+                    .body("text/plain; charset=utf-8", message)
+                    .write_to(&mut writer, false, false);
+                return;
+            }
+        };
+        let keep_alive = request.keep_alive() && served < MAX_REQUESTS_PER_CONNECTION;
+        let response = route(&sites(), &request);
+        let head_only = request.method == "HEAD";
+        if response.write_to(&mut writer, head_only, keep_alive).is_err() || !keep_alive {
+            return;
+        }
+    }
+}
+
+/// Split `/manual/<id>/rest` into `(id, "/rest")`.
+fn split_mount(path: &str) -> Option<(&str, &str)> {
+    let rest = path.strip_prefix(MOUNT_PREFIX)?;
+    match rest.find('/') {
+        Some(i) => Some((&rest[..i], &rest[i..])),
+        None => Some((rest, "")),
+    }
+}
+
+/// Manual that an unprefixed path belongs to: the one named in the
+/// `Referer`, else the only manual that has the file.
+fn infer_site<'a>(sites: &'a Sites, request: &Request) -> Option<&'a str> {
+    let from_referer = request
+        .header("referer")
+        .and_then(|r| r.split_once("://"))
+        .and_then(|(_, rest)| rest.find('/').map(|i| &rest[i..]))
+        .and_then(split_mount)
+        .and_then(|(id, _)| sites.get_key_value(id))
+        .map(|(id, _)| id.as_str());
+    if from_referer.is_some() {
+        return from_referer;
+    }
+    let mut hits = sites
+        .iter()
+        .filter(|(_, site)| matches!(resolve_path(site, &request.path), Ok(Some(_))));
+    match (hits.next(), hits.next()) {
+        (Some((id, _)), None) => Some(id.as_str()),
+        _ => None,
+    }
+}
+
+fn with_cors(response: Response, request: &Request) -> Response {
+    let response = response.header("Vary", "Origin");
+    match request.header("origin") {
+        Some(origin) if ALLOWED_ORIGINS.contains(&origin) => {
+            response.header("Access-Control-Allow-Origin", origin)
+        }
+        _ => response,
+    }
+}
+
+fn redirect(location: String) -> Response {
+    Response::new(302).header("Location", location)
+}
+
+/// Route a request to the right manual and serve it.
+pub fn route(sites: &Sites, request: &Request) -> Response {
+    let query = request.query.as_deref().map(|q| format!("?{q}")).unwrap_or_default();
+    let response = match split_mount(&request.path) {
+        Some((id, "")) if sites.contains_key(id) => redirect(format!("{MOUNT_PREFIX}{id}/{query}")),
+        Some((id, rest)) => match sites.get(id) {
+            Some(site) => serve_file(site, &Request { path: rest.to_string(), ..request.clone() }),
+            None => not_found_page(),
+        },
+        None => match infer_site(sites, request) {
+            // Pages move under the mount so that their own links carry the
+            // prefix (and the Referer) from then on; assets are served as is.
+            Some(id) if is_document_request(request) => {
+                redirect(format!("{MOUNT_PREFIX}{id}{}{query}", request.path))
+            }
+            Some(id) => serve_file(&sites[id], request),
+            None => not_found_page(),
+        },
+    };
+    with_cors(response, request)
+}
+
+fn is_document_request(request: &Request) -> bool {
+    matches!(request.method.as_str(), "GET" | "HEAD")
+        && request.header("sec-fetch-dest").map_or_else(
+            || request.header("accept").is_some_and(|a| a.starts_with("text/html")),
+            |dest| dest == "document" || dest == "iframe",
+        )
+}
+
+fn mime_for(path: &Path, body: &[u8]) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase()).as_deref() {
+        Some("html") | Some("htm") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") | Some("mjs") => "application/javascript; charset=utf-8",
+        Some("json") | Some("map") => "application/json",
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("svg") => "image/svg+xml",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        Some("ttf") => "font/ttf",
+        Some("ico") => "image/x-icon",
+        Some("xml") => "application/xml",
+        Some("txt") | Some("md") => "text/plain; charset=utf-8",
+        Some("wasm") => "application/wasm",
+        Some(_) => "application/octet-stream",
+        None => {
+            // 无扩展名：检测 body 是否以 HTML doctype 开头（跳过可能的 UTF-8 BOM）
+            let content = body.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(body);
+            let head = &content[..content.len().min(16)];
+            if head.to_ascii_lowercase().starts_with(b"<!doctype") || head.starts_with(b"<html") {
+                "text/html; charset=utf-8"
+            } else {
+                "application/octet-stream"
+            }
+        }
+    }
+}
+
+fn is_compressible(mime: &str) -> bool {
+    mime.starts_with("text/")
+        || mime.starts_with("application/javascript")
+        || mime.starts_with("application/json")
+        || mime.starts_with("application/xml")
+        || mime.starts_with("image/svg+xml")
+        || mime.starts_with("application/wasm")
+}
+
+/// Map a request path to a file of `site`, with the VitePress conventions:
+/// directories serve `index.html`, extension-less paths try `.html`, and
+/// `foo.js` falls back to `foo.lean.js`. `Err(403)` for traversal attempts.
+pub fn resolve_path(site: &Site, request_path: &str) -> Result<Option<String>, u16> {
+    let mut parts = Vec::new();
+    for component in Path::new(request_path.trim_start_matches('/')).components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy()),
+            Component::CurDir => {}
+            _ => return Err(403),
+        }
+    }
+    let rel = parts.join("/");
+    let index = if rel.is_empty() { "index.html".to_string() } else { format!("{rel}/index.html") };
+    let extension = Path::new(&rel).extension();
+    let candidates = if site.is_dir(&rel) {
+        vec![index]
+    } else if extension.is_none() {
+        vec![rel.clone(), format!("{rel}.html"), index]
+    } else if extension.is_some_and(|e| e == "js") {
+        vec![rel.clone(), format!("{}.lean.js", &rel[..rel.len() - 3])]
+    } else {
+        vec![rel]
+    };
+    Ok(candidates.into_iter().find(|key| site.is_file(key)))
+}
+
+fn http_date(time: SystemTime) -> String {
+    let dt: DateTime<Utc> = time.into();
+    dt.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+fn parse_http_date(value: &str) -> Option<i64> {
+    DateTime::parse_from_rfc2822(value.trim()).ok().map(|d| d.timestamp())
+}
+
+fn unix_secs(time: SystemTime) -> i64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+fn etag_for(len: u64, modified: SystemTime) -> String {
+    let nanos = modified
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    format!("\"{len:x}-{nanos:x}\"")
+}
+
+/// `If-None-Match` uses weak comparison, so `W/"x"` matches `"x"`.
+fn etag_matches(header: &str, etag: &str) -> bool {
+    header
+        .split(',')
+        .map(|t| t.trim().trim_start_matches("W/"))
+        .any(|t| t == "*" || t == etag)
+}
+
+/// Single `bytes=` range resolved against `len`; `Some(Err(()))` when unsatisfiable.
+/// Multi-range requests are answered with the full body.
+fn parse_range(header: &str, len: u64) -> Option<Result<(u64, u64), ()>> {
+    let spec = header.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = (start.trim(), end.trim());
+    let range = if start.is_empty() {
+        let suffix: u64 = end.parse().ok()?;
+        if suffix == 0 || len == 0 {
+            return Some(Err(()));
+        }
+        (len.saturating_sub(suffix), len - 1)
+    } else {
+        let start: u64 = start.parse().ok()?;
+        let end = if end.is_empty() { len.saturating_sub(1) } else { end.parse::<u64>().ok()?.min(len.saturating_sub(1)) };
+        if start >= len || end < start {
+            return Some(Err(()));
+        }
+        (start, end)
+    };
+    Some(Ok(range))
+}
+
+fn accepts(accept_encoding: &str, coding: &str) -> bool {
+    accept_encoding.split(',').any(|part| {
+        let mut it = part.split(';');
+        let name = it.next().unwrap_or_default().trim();
+        let disabled = it.any(|p| matches!(p.trim(), "q=0" | "q=0.0" | "q=0.00" | "q=0.000"));
+        name.eq_ignore_ascii_case(coding) && !disabled
+    })
+}
+
+type CacheKey = (PathBuf, String, &'static str);
+
+struct CompressedCache {
+    entries: HashMap<CacheKey, Arc<Vec<u8>>>,
+    bytes: usize,
+}
+
+fn compressed_cache() -> &'static Mutex<CompressedCache> {
+    static CACHE: OnceLock<Mutex<CompressedCache>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(CompressedCache { entries: HashMap::new(), bytes: 0 }))
+}
+
+fn compress(coding: &'static str, data: &[u8]) -> io::Result<Vec<u8>> {
+    match coding {
+        "br" => {
+            let mut out = Vec::new();
+            {
+                let mut writer = brotli::CompressorWriter::new(&mut out, 4096, 5, 22);
+                writer.write_all(data)?;
+            }
+            Ok(out)
+        }
+        _ => {
+            let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(data)?;
+            encoder.finish()
+        }
+    }
+}
+
+/// Compressed body for `path` at version `etag`, cached until the budget is hit.
+fn compressed_body(path: &Path, etag: &str, coding: &'static str, data: &[u8]) -> io::Result<Arc<Vec<u8>>> {
+    let key = (path.to_path_buf(), etag.to_string(), coding);
+    if let Some(hit) = compressed_cache().lock().unwrap_or_else(|e| e.into_inner()).entries.get(&key) {
+        return Ok(hit.clone());
+    }
+    let body = Arc::new(compress(coding, data)?);
+    let mut cache = compressed_cache().lock().unwrap_or_else(|e| e.into_inner());
+    if cache.bytes + body.len() > COMPRESSED_CACHE_BYTES {
+        cache.entries.clear();
+        cache.bytes = 0;
+    }
+    cache.bytes += body.len();
+    cache.entries.insert(key, body.clone());
+    Ok(body)
+}
+
+fn not_found_page() -> Response {
+    Response::new(404).body(
+        "text/html; charset=utf-8",
+        "<!doctype html><html><head><meta charset=\"utf-8\"><title>404</title></head>\
+         <body><h1>404</h1><p>页面不存在 / Page not found</p></body></html>",
+    )
+}
+
+fn not_found(site: &Site) -> Response {
+    // VitePress builds ship a themed 404.html at the site root.
+    match site.read("404.html") {
+        Ok(body) => Response::new(404).body("text/html; charset=utf-8", body),
+        Err(_) => not_found_page(),
+    }
+}
+
+/// Answer one GET/HEAD request for a file of `site`.
+pub fn serve_file(site: &Site, request: &Request) -> Response {
+    if request.method != "GET" && request.method != "HEAD" {
+        return Response::new(405).header("Allow", "GET, HEAD");
+    }
+    let key = match resolve_path(site, &request.path) {
+        Ok(Some(key)) => key,
+        Ok(None) => return not_found(site),
+        Err(status) => return Response::new(status).body("text/plain; charset=utf-8", "Forbidden"),
+    };
+    let Some((len, modified)) = site.stat(&key) else {
+        return not_found(site);
+    };
+    let file_path = Path::new(&key);
+    let etag = etag_for(len, modified);
+    let last_modified = http_date(modified);
+    let is_html = file_path.extension().is_some_and(|e| e == "html" || e == "htm");
+    let cache_control = if is_html { "no-cache" } else { "public, max-age=3600" };
+
+    // Sniffing the type of extension-less files needs the first bytes.
+    let sniff = if file_path.extension().is_none() { site.read_range(&key, 0, 16).unwrap_or_default() } else { Vec::new() };
+    let mime = mime_for(file_path, &sniff);
+
+    // Negotiated up front: each encoding is a distinct representation with
+    // its own tag, and revalidation has to compare against that one.
+    let accept_encoding = request.header("accept-encoding").unwrap_or_default();
+    let coding = if is_compressible(mime) && len >= MIN_COMPRESS_BYTES as u64 {
+        if accepts(accept_encoding, "br") {
+            Some("br")
+        } else if accepts(accept_encoding, "gzip") {
+            Some("gzip")
+        } else {
+            None
+        }
+    } else {
+        None
+    };
+    let coded_etag = match coding {
+        Some(coding) => format!("{}-{coding}\"", etag.trim_end_matches('"')),
+        None => etag.clone(),
+    };
+
+    let fresh = match request.header("if-none-match") {
+        Some(tags) => etag_matches(tags, &coded_etag),
+        None => request
+            .header("if-modified-since")
+            .and_then(parse_http_date)
+            .is_some_and(|since| unix_secs(modified) <= since),
+    };
+    let base = |status: u16, tag: &str| {
+        Response::new(status)
+            .header("ETag", tag.to_string())
+            .header("Last-Modified", last_modified.clone())
+            .header("Cache-Control", cache_control)
+            .header("Accept-Ranges", "bytes")
+            .header("Vary", "Accept-Encoding")
+    };
+    if fresh {
+        return base(304, &coded_etag);
+    }
+
+    // Ranges are served from the identity representation.
+    let range = request
+        .header("range")
+        .filter(|_| request.header("if-range").is_none_or(|v| v.trim() == etag))
+        .and_then(|h| parse_range(h, len));
+    match range {
+        Some(Err(())) => {
+            return Response::new(416)
+                .header("Content-Range", format!("bytes */{len}"))
+                .header("Accept-Ranges", "bytes");
+        }
+        Some(Ok((start, end))) => {
+            let body = if request.method == "HEAD" {
+                Vec::new()
+            } else {
+                match site.read_range(&key, start, end - start + 1) {
+                    Ok(body) => body,
+                    Err(_) => return not_found(site),
+                }
+            };
+            let response = base(206, &etag)
+                .header("Content-Range", format!("bytes {start}-{end}/{len}"))
+                .header("Content-Type", mime);
+            return if request.method == "HEAD" {
+                response.header("Content-Length", (end - start + 1).to_string())
+            } else {
+                Response { body, ..response }
+            };
+        }
+        None => {}
+    }
+
+    let Ok(data) = site.read(&key) else {
+        return not_found(site);
+    };
+    if let Some(coding) = coding {
+        if let Ok(body) = compressed_body(&site.location().join(&key), &etag, coding, &data) {
+            return base(200, &coded_etag).header("Content-Encoding", coding).body(mime, body.as_slice());
+        }
+    }
+    base(200, &etag).body(mime, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::Read;
+
+    fn site_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lazycat-manual-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("guide")).expect("mkdir");
+        fs::write(dir.join("index.html"), "<!doctype html><p>home</p>").expect("write");
+        fs::write(dir.join("guide/intro.html"), "<p>intro</p>").expect("write");
+        fs::write(dir.join("app.lean.js"), "x".repeat(4096)).expect("write");
+        fs::write(dir.join("data.bin"), (0u8..=99).collect::<Vec<_>>()).expect("write");
+        dir
+    }
+
+    fn get(path: &str, headers: &[(&str, &str)]) -> Request {
+        Request {
+            method: "GET".into(),
+            path: path.into(),
+            query: None,
+            version: "HTTP/1.1".into(),
+            headers: headers.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            body: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn resolves_vitepress_paths_and_blocks_traversal() {
+        let root = Site::Dir(site_dir());
+        assert_eq!(serve_file(&root, &get("/", &[])).status, 200);
+        assert_eq!(serve_file(&root, &get("/guide/intro", &[])).body, b"<p>intro</p>");
+        assert_eq!(serve_file(&root, &get("/app.js", &[])).status, 200);
+        assert_eq!(serve_file(&root, &get("/missing.css", &[])).status, 404);
+        assert_eq!(serve_file(&root, &get("/../secret", &[])).status, 403);
+    }
+
+    #[test]
+    fn conditional_requests_return_304() {
+        let root = Site::Dir(site_dir());
+        let first = serve_file(&root, &get("/data.bin", &[]));
+        let etag = first.get_header("etag").expect("etag").to_string();
+        let again = serve_file(&root, &get("/data.bin", &[("If-None-Match", &etag)]));
+        assert_eq!(again.status, 304);
+        let since = first.get_header("last-modified").expect("last-modified").to_string();
+        assert_eq!(serve_file(&root, &get("/data.bin", &[("If-Modified-Since", &since)])).status, 304);
+        assert_eq!(serve_file(&root, &get("/data.bin", &[("If-Modified-Since", &http_date(SystemTime::UNIX_EPOCH))])).status, 200);
+    }
+
+    #[test]
+    fn range_requests() {
+        let root = Site::Dir(site_dir());
+        let part = serve_file(&root, &get("/data.bin", &[("Range", "bytes=10-19")]));
+        assert_eq!(part.status, 206);
+        assert_eq!(part.body, (10u8..20).collect::<Vec<_>>());
+        assert_eq!(part.get_header("content-range"), Some("bytes 10-19/100"));
+        let tail = serve_file(&root, &get("/data.bin", &[("Range", "bytes=-5")]));
+        assert_eq!(tail.body, (95u8..100).collect::<Vec<_>>());
+        assert_eq!(serve_file(&root, &get("/data.bin", &[("Range", "bytes=200-")])).status, 416);
+    }
+
+    #[test]
+    fn text_assets_are_compressed_when_accepted() {
+        let root = Site::Dir(site_dir());
+        let gz = serve_file(&root, &get("/app.js", &[("Accept-Encoding", "gzip")]));
+        assert_eq!(gz.get_header("content-encoding"), Some("gzip"));
+        let mut plain = String::new();
+        flate2::read::GzDecoder::new(gz.body.as_slice()).read_to_string(&mut plain).expect("gunzip");
+        assert_eq!(plain.len(), 4096);
+        let br = serve_file(&root, &get("/app.js", &[("Accept-Encoding", "gzip, br")]));
+        assert_eq!(br.get_header("content-encoding"), Some("br"));
+        let identity = serve_file(&root, &get("/app.js", &[]));
+        assert!(identity.get_header("content-encoding").is_none());
+    }
+
+    #[test]
+    fn compressed_assets_revalidate_against_their_own_tag() {
+        let root = Site::Dir(site_dir());
+        let gzip = [("Accept-Encoding", "gzip")];
+        let tag = serve_file(&root, &get("/app.js", &gzip)).get_header("etag").expect("etag").to_string();
+        assert!(tag.ends_with("-gzip\""));
+        let again = serve_file(&root, &get("/app.js", &[("Accept-Encoding", "gzip"), ("If-None-Match", &tag)]));
+        assert_eq!(again.status, 304);
+        assert_eq!(again.get_header("etag"), Some(tag.as_str()));
+        // A cached gzip body is not a valid answer for a client that now wants identity.
+        assert_eq!(serve_file(&root, &get("/app.js", &[("If-None-Match", &tag)])).status, 200);
+    }
+
+    #[test]
+    fn routes_by_mount_referer_and_probe() {
+        let (vue, mdn) = (site_dir(), site_dir());
+        fs::write(mdn.join("only-mdn.css"), "body{}").expect("write");
+        let sites: Sites = [("vue3".to_string(), Site::Dir(vue)), ("mdn-js".to_string(), Site::Dir(mdn))].into();
+
+        let page = route(&sites, &get("/manual/vue3/guide/intro", &[("Origin", "tauri://localhost")]));
+        assert_eq!(page.body, b"<p>intro</p>");
+        assert_eq!(page.get_header("access-control-allow-origin"), Some("tauri://localhost"));
+        let foreign = route(&sites, &get("/manual/vue3/", &[("Origin", "http://evil.test")]));
+        assert!(foreign.get_header("access-control-allow-origin").is_none());
+
+        assert_eq!(route(&sites, &get("/manual/vue3", &[])).get_header("location"), Some("/manual/vue3/"));
+        assert_eq!(route(&sites, &get("/manual/nope/", &[])).status, 404);
+
+        let referer = [("Referer", "http://127.0.0.1:1/manual/mdn-js/guide/intro")];
+        assert_eq!(route(&sites, &get("/data.bin", &referer)).status, 200);
+        let html = [("Referer", "http://127.0.0.1:1/manual/mdn-js/"), ("Sec-Fetch-Dest", "iframe")];
+        assert_eq!(
+            route(&sites, &get("/guide/intro", &html)).get_header("location"),
+            Some("/manual/mdn-js/guide/intro")
+        );
+        assert_eq!(route(&sites, &get("/only-mdn.css", &[])).status, 200);
+        // Present in both manuals and no Referer: ambiguous.
+        assert_eq!(route(&sites, &get("/data.bin", &[])).status, 404);
+    }
+
+    #[test]
+    fn serves_from_zip_archive_with_same_fallbacks() {
+        let path = std::env::temp_dir().join(format!("lazycat-manual-{}.zip", uuid::Uuid::new_v4()));
+        {
+            let mut writer = zip::ZipWriter::new(fs::File::create(&path).expect("create"));
+            let options = zip::write::SimpleFileOptions::default();
+            for (name, body) in [
+                ("manual.json", "{}"),
+                ("index.html", "<!doctype html>home"),
+                ("guide/intro.html", "<p>intro</p>"),
+                ("app.lean.js", "console.log(1)"),
+            ] {
+                writer.start_file(name, options).expect("start");
+                writer.write_all(body.as_bytes()).expect("write");
+            }
+            writer.finish().expect("finish");
+        }
+        let site = Site::open(&path).expect("open");
+        assert_eq!(serve_file(&site, &get("/", &[])).body, b"<!doctype html>home");
+        assert_eq!(serve_file(&site, &get("/guide/intro", &[])).body, b"<p>intro</p>");
+        assert_eq!(serve_file(&site, &get("/app.js", &[])).body, b"console.log(1)");
+        let part = serve_file(&site, &get("/guide/intro.html", &[("Range", "bytes=3-7")]));
+        assert_eq!(part.body, b"intro");
+        assert_eq!(serve_file(&site, &get("/guide/missing", &[])).status, 404);
+        let _ = fs::remove_file(path);
+    }
+}
@@ -0,0 +1,569 @@
+//! Opt-in loopback HTTP API over the tool registry.
+//!
+//! Endpoints (all except `/v1/health` need `Authorization: Bearer <token>`):
+//! - `GET  /v1/health`         liveness probe
+//! - `GET  /v1/tools`          catalog, same as `tool_describe`
+//! - `POST /v1/tools/execute`  body `ToolRequest`, returns `ToolResponse`
+//! - `POST /rpc`               JSON-RPC 2.0, `method` is `"<domain>.<action>"`
+//!
+//! The token lives in `<data dir>/api-token`; `api.enabled` / `api.port`
+//! settings decide whether and where the server listens.
+
+use serde_json::{json, Value};
+use std::cell::Cell;
+use std::fs;
+use std::io::BufReader;
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::http::{self, ReadError, Request, Response};
+
+use super::error::{ToolError, ToolResult};
+use super::helpers::{db_conn, get_data_dir};
+use super::protocol::{self, ToolRequest};
+use super::{pipeline, workspace};
+use super::registry::{ActionSpec, DomainSpec, FieldSpec};
+use super::settings::{read_setting, store_setting};
+
+/// Settings keys owned by the API server; their schema hook re-applies the config.
+pub const SETTINGS_PREFIX: &str = "api.";
+pub const KEY_ENABLED: &str = "api.enabled";
+pub const KEY_PORT: &str = "api.port";
+pub const DEFAULT_PORT: u16 = 17800;
+
+const TOKEN_FILE: &str = "api-token";
+const MAX_BODY_BYTES: usize = 16 * 1024 * 1024;
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+const WORKERS: usize = 4;
+/// Accepted connections waiting for a worker; beyond this we answer 503.
+const QUEUE_DEPTH: usize = 32;
+
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "api",
+    description: "Local HTTP / JSON-RPC API for editors and scripts",
+    execute,
+    actions: &[
+        ActionSpec::new("status", "Whether the API is enabled and listening", &[], "object: { enabled, running, port, url, tokenPath }"),
+        ActionSpec::new(
+            "configure",
+            "Change api.enabled / api.port and apply immediately",
+            &[
+                FieldSpec::optional("enabled", "boolean", "start or stop the API"),
+                FieldSpec::optional("port", "integer", "loopback port, default 17800"),
+            ],
+            "object: status",
+        ),
+        ActionSpec::new("token", "Read the access token, creating it on first use", &[], "object: { token, tokenPath }"),
+        ActionSpec::new("rotate_token", "Replace the access token", &[], "object: { token, tokenPath }"),
+    ],
+};
+
+pub fn execute(action: &str, payload: &Value) -> ToolResult {
+    match action {
+        "status" => status(),
+        "configure" => configure(payload),
+        "token" => token_info(false),
+        "rotate_token" => token_info(true),
+        _ => Err(ToolError::unsupported("api", action)),
+    }
+}
+
+struct RunningServer {
+    port: u16,
+    stop: Arc<AtomicBool>,
+}
+
+static SERVER: OnceLock<Mutex<Option<RunningServer>>> = OnceLock::new();
+/// Set by the desktop app; other hosts (the CLI) only store the settings.
+static HOSTED: AtomicBool = AtomicBool::new(false);
+
+fn server() -> MutexGuard<'static, Option<RunningServer>> {
+    SERVER
+        .get_or_init(|| Mutex::new(None))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+struct ApiSettings {
+    enabled: bool,
+    port: u16,
+}
+
+fn load_settings() -> Result<ApiSettings, String> {
+    let enabled = read_setting(KEY_ENABLED)?.is_some_and(|v| v == "true" || v == "1");
+    let port = read_setting(KEY_PORT)?
+        .and_then(|v| v.trim().parse::<u16>().ok())
+        .filter(|p| *p != 0)
+        .unwrap_or(DEFAULT_PORT);
+    Ok(ApiSettings { enabled, port })
+}
+
+/// Make this process the API host and start the server if it is enabled.
+pub fn init() -> ToolResult {
+    HOSTED.store(true, Ordering::Relaxed);
+    apply_settings()
+}
+
+/// Start, stop or restart the server so it matches the stored settings.
+/// Called whenever an `api.*` setting changes; a no-op outside the desktop app.
+pub fn apply_settings() -> ToolResult {
+    if !HOSTED.load(Ordering::Relaxed) {
+        return status();
+    }
+    let settings = load_settings()?;
+    let mut current = server();
+    let wanted = settings.enabled.then_some(settings.port);
+    if current.as_ref().map(|s| s.port) != wanted {
+        if let Some(running) = current.take() {
+            stop_server(running);
+        }
+        if let Some(port) = wanted {
+            *current = Some(start_server(port)?);
+        }
+    }
+    drop(current);
+    status()
+}
+
+fn status() -> ToolResult {
+    let settings = load_settings()?;
+    let running = server().as_ref().map(|s| s.port);
+    Ok(json!({
+        "enabled": settings.enabled,
+        "running": running.is_some(),
+        "port": running.unwrap_or(settings.port),
+        "url": running.map(|p| format!("http://127.0.0.1:{p}")),
+        "tokenPath": token_path()?.to_string_lossy(),
+    }))
+}
+
+fn configure(payload: &Value) -> ToolResult {
+    if let Some(port) = payload.get("port").filter(|v| !v.is_null()) {
+        let port = port
+            .as_u64()
+            .filter(|p| (1..=65535).contains(p))
+            .ok_or_else(|| ToolError::invalid_field("port", "port must be 1-65535"))?;
+        store_setting(KEY_PORT, &json!(port))?;
+    }
+    if let Some(enabled) = payload.get("enabled").filter(|v| !v.is_null()) {
+        let enabled = enabled
+            .as_bool()
+            .ok_or_else(|| ToolError::invalid_field("enabled", "enabled must be a boolean"))?;
+        store_setting(KEY_ENABLED, &json!(enabled))?;
+    }
+    apply_settings()
+}
+
+// ── Token ────────────────────────────────────────────────────────────────
+
+fn token_path() -> Result<PathBuf, String> {
+    Ok(get_data_dir()?.join(TOKEN_FILE))
+}
+
+fn generate_token() -> String {
+    let bytes: [u8; 32] = rand::random();
+    hex::encode(bytes)
+}
+
+fn write_token(path: &Path, token: &str) -> ToolResult<()> {
+    fs::write(path, token).map_err(|e| ToolError::io("write api token failed", e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = fs::set_permissions(path, fs::Permissions::from_mode(0o600));
+    }
+    Ok(())
+}
+
+/// Current token, created on first use.
+fn load_token() -> ToolResult<String> {
+    let path = token_path()?;
+    match fs::read_to_string(&path) {
+        Ok(token) if !token.trim().is_empty() => Ok(token.trim().to_string()),
+        _ => {
+            let token = generate_token();
+            write_token(&path, &token)?;
+            Ok(token)
+        }
+    }
+}
+
+fn token_info(rotate: bool) -> ToolResult {
+    let path = token_path()?;
+    let token = if rotate {
+        let token = generate_token();
+        write_token(&path, &token)?;
+        token
+    } else {
+        load_token()?
+    };
+    Ok(json!({ "token": token, "tokenPath": path.to_string_lossy() }))
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// ── Server ───────────────────────────────────────────────────────────────
+
+fn start_server(port: u16) -> ToolResult<RunningServer> {
+    // Make sure a token exists before anyone can connect.
+    load_token()?;
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, port)))
+        .map_err(|e| ToolError::io(&format!("bind 127.0.0.1:{port} failed"), e))?;
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_flag = stop.clone();
+    thread::Builder::new()
+        .name("api-server".into())
+        .spawn(move || {
+            let stopped = || stop_flag.load(Ordering::Relaxed);
+            http::serve(listener, WORKERS, QUEUE_DEPTH, stopped, move |stream| handle_connection(stream, port));
+        })
+        .map_err(|e| format!("spawn api server failed: {e}"))?;
+    Ok(RunningServer { port, stop })
+}
+
+fn stop_server(server: RunningServer) {
+    server.stop.store(true, Ordering::Relaxed);
+    // Wake the blocking accept so the listener thread sees the flag and exits.
+    let _ = TcpStream::connect_timeout(
+        &SocketAddr::from((Ipv4Addr::LOCALHOST, server.port)),
+        Duration::from_millis(500),
+    );
+}
+
+fn write_json(stream: &mut TcpStream, status: u16, body: &Value) {
+    let _ = Response::new(status)
+        .body("application/json; charset=utf-8", body.to_string())
+        .write_to(stream, false, false);
+}
+
+fn error_body(code: &str, message: &str) -> Value {
+    json!({ "error": { "code": code, "message": message } })
+}
+
+/// Only loopback host names are accepted, which blocks DNS-rebinding pages.
+fn is_loopback_host(host: Option<&str>, port: u16) -> bool {
+    let Some(host) = host else {
+        return false;
+    };
+    let name = match host.strip_suffix(&format!(":{port}")) {
+        Some(name) => name,
+        None => host,
+    };
+    matches!(name, "127.0.0.1" | "localhost" | "[::1]")
+}
+
+fn is_authorized(request: &Request) -> bool {
+    let Ok(expected) = load_token() else {
+        return false;
+    };
+    let supplied = request
+        .header("authorization")
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .or_else(|| request.header("x-lazycat-token"))
+        .unwrap_or_default()
+        .trim();
+    constant_time_eq(supplied.as_bytes(), expected.as_bytes())
+}
+
+fn handle_connection(mut stream: TcpStream, port: u16) {
+    let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+    let request = match http::read_request(&mut BufReader::new(&stream), MAX_BODY_BYTES) {
+        Ok(request) => request,
+        Err(ReadError::Closed) => return,
+        Err(ReadError::Bad(status, message)) => {
+            write_json(&mut stream, status, &error_body("BAD_REQUEST", &message));
+            return;
+        }
+    };
+    if !is_loopback_host(request.header("host"), port) {
+        write_json(&mut stream, 403, &error_body("FORBIDDEN", "host not allowed"));
+        return;
+    }
+    let (status, body) = route(&request);
+    write_json(&mut stream, status, &body);
+}
+
+fn route(request: &Request) -> (u16, Value) {
+    if request.method == "GET" && request.path == "/v1/health" {
+        return (200, json!({ "ok": true }));
+    }
+    if !is_authorized(request) {
+        return (401, error_body("UNAUTHORIZED", "missing or invalid api token"));
+    }
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/v1/tools") => match super::describe_tools(None) {
+            Ok(catalog) => (200, catalog),
+            Err(err) => (500, error_body(err.code(), &err.message())),
+        },
+        ("POST", "/v1/tools/execute") => (200, execute_http(&request.body)),
+        ("POST", "/rpc") => (200, execute_rpc(&request.body)),
+        (_, "/v1/tools" | "/v1/tools/execute" | "/rpc") => {
+            (405, error_body("METHOD_NOT_ALLOWED", "method not allowed"))
+        }
+        _ => (404, error_body("NOT_FOUND", "no such endpoint")),
+    }
+}
+
+thread_local! {
+    /// Set while the current thread runs a call made through the API.
+    static SERVING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Whether the current thread runs a call made through the API, including
+/// the job thread of a job it started.
+pub fn serving() -> bool {
+    SERVING.with(Cell::get)
+}
+
+/// Runs `f` with the API policy applied to its pipeline steps or not, see
+/// `check_step`.
+pub fn serve_as<T>(serving: bool, f: impl FnOnce() -> T) -> T {
+    let previous = SERVING.with(|s| s.replace(serving));
+    let out = f();
+    SERVING.with(|s| s.set(previous));
+    out
+}
+
+/// Checks a pipeline step against the API policy once its payload is
+/// rendered, when the call came through the API. Bindings can compute a
+/// path or key the request-level check could not see.
+pub fn check_step(domain: &str, action: &str, payload: &Value) -> ToolResult<()> {
+    if serving() {
+        guard(domain, action, payload)
+    } else {
+        Ok(())
+    }
+}
+
+/// The API cannot manage itself remotely, directly or through a job or
+/// pipeline: no `api` actions (e.g. reading or rotating its own token), no
+/// changes to its settings and no access to its token file. Nested `api`
+/// steps are also refused by `registry::find_step`. `history.replay` is
+/// refused outright: the entry it re-runs was recorded (or imported) outside
+/// this check.
+fn guard(domain: &str, action: &str, payload: &Value) -> ToolResult<()> {
+    if domain == SPEC.name {
+        return Err(ToolError::unsupported(domain, action));
+    }
+    let mut calls = Vec::new();
+    let conn = db_conn().ok();
+    pipeline::reached_calls(conn.as_deref(), domain, action, payload, &mut calls);
+    drop(conn);
+    let token = token_path()?;
+    for (domain, action, payload) in &calls {
+        if domain == SPEC.name {
+            return Err(ToolError::unsupported(domain, action));
+        }
+        if domain == "history" && action == "replay" {
+            return Err(ToolError::PermissionDenied("the local API cannot replay history".into()));
+        }
+        if domain == "settings" && changes_api_settings(action, payload) {
+            return Err(ToolError::PermissionDenied("the local API cannot change its own settings".into()));
+        }
+        if names_token_file(payload, &token) {
+            return Err(ToolError::PermissionDenied("the local API cannot access its token file".into()));
+        }
+    }
+    Ok(())
+}
+
+/// Keys bound from a template (`{{...}}`) are unknown until the step runs,
+/// so they count as API-owned.
+fn changes_api_settings(action: &str, payload: &Value) -> bool {
+    match action {
+        "set" => {
+            let key = payload["key"].as_str().unwrap_or_default();
+            key.starts_with(SETTINGS_PREFIX) || key.contains("{{")
+        }
+        "import" | "import_from_file" => {
+            workspace::parse_sections(&payload["sections"]).map_or(true, |sections| sections.contains(&"settings"))
+        }
+        _ => false,
+    }
+}
+
+/// Whether any string in `payload` is a path to the token file, or a
+/// templated path ending in its name. Other templated paths are checked
+/// again once rendered, see `check_step`.
+fn names_token_file(payload: &Value, token: &Path) -> bool {
+    match payload {
+        Value::String(text) => {
+            let path = Path::new(text);
+            if path.file_name().is_none_or(|name| name != TOKEN_FILE) {
+                return false;
+            }
+            if text.contains("{{") {
+                return true;
+            }
+            let parent = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+            let token_dir = token.parent().and_then(|d| d.canonicalize().ok());
+            parent.canonicalize().ok().is_some_and(|dir| Some(dir) == token_dir)
+        }
+        Value::Array(items) => items.iter().any(|v| names_token_file(v, token)),
+        Value::Object(map) => map.values().any(|v| names_token_file(v, token)),
+        _ => false,
+    }
+}
+
+fn execute_http(body: &[u8]) -> Value {
+    let start = Instant::now();
+    let request: ToolRequest = match serde_json::from_slice(body) {
+        Ok(request) => request,
+        Err(e) => {
+            let response = protocol::respond(String::new(), Err(ToolError::json("invalid ToolRequest", &e)), start);
+            return serde_json::to_value(response).unwrap_or(Value::Null);
+        }
+    };
+    let response = match guard(&request.domain, &request.action, &request.payload) {
+        Ok(()) => serve_as(true, || protocol::execute_request(request)),
+        Err(err) => protocol::respond(request.request_id, Err(err), start),
+    };
+    serde_json::to_value(response).unwrap_or(Value::Null)
+}
+
+fn rpc_error(id: Value, code: i64, message: String, data: Option<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message, "data": data },
+    })
+}
+
+fn execute_rpc(body: &[u8]) -> Value {
+    let call: Value = match serde_json::from_slice(body) {
+        Ok(call) => call,
+        Err(e) => return rpc_error(Value::Null, -32700, format!("parse error: {e}"), None),
+    };
+    let id = call.get("id").cloned().unwrap_or(Value::Null);
+    let Some((domain, action)) = call["method"].as_str().and_then(|m| m.split_once('.')) else {
+        return rpc_error(id, -32600, "method must be \"<domain>.<action>\"".into(), None);
+    };
+    let params = match &call["params"] {
+        Value::Null => json!({}),
+        p => p.clone(),
+    };
+    let outcome = guard(domain, action, &params).and_then(|_| serve_as(true, || super::execute_tool(domain, action, &params)));
+    match outcome {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(err) => {
+            let code = match err {
+                ToolError::UnsupportedAction { .. } => -32601,
+                ToolError::InvalidPayload { .. } => -32602,
+                _ => -32000,
+            };
+            let data = json!({ "code": err.code(), "details": err.details() });
+            rpc_error(id, code, err.message(), Some(data))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loopback_host_check() {
+        assert!(is_loopback_host(Some("127.0.0.1:17800"), 17800));
+        assert!(is_loopback_host(Some("localhost"), 17800));
+        assert!(!is_loopback_host(Some("evil.example:17800"), 17800));
+        assert!(!is_loopback_host(None, 17800));
+    }
+
+    #[test]
+    fn rpc_dispatches_and_maps_errors() {
+        crate::tools::helpers::use_test_data_dir();
+        let ok = execute_rpc(br#"{"jsonrpc":"2.0","id":1,"method":"encode.base64_encode","params":{"input":"hi"}}"#);
+        assert_eq!(ok["result"], "aGk=");
+        let err = execute_rpc(br#"{"jsonrpc":"2.0","id":2,"method":"encode.nope"}"#);
+        assert_eq!(err["error"]["code"], -32601);
+        let err = execute_rpc(br#"{"jsonrpc":"2.0","id":3,"method":"api.token"}"#);
+        assert_eq!(err["error"]["data"]["code"], "UNSUPPORTED_ACTION");
+    }
+
+    #[test]
+    fn nested_calls_cannot_reach_the_api_domain() {
+        crate::tools::helpers::use_test_data_dir();
+        let body = br#"{"request_id":"r2","domain":"jobs","action":"start","payload":{"domain":"api","action":"rotate_token"}}"#;
+        let response = execute_http(body);
+        assert_eq!(response["ok"], false);
+        assert_eq!(response["error"]["code"], "UNSUPPORTED_ACTION");
+        let err = execute_rpc(
+            br#"{"jsonrpc":"2.0","id":4,"method":"pipeline.run","params":{"steps":[{"domain":"api","action":"configure","payloadTemplate":{"enabled":false}}]}}"#,
+        );
+        assert_eq!(err["error"]["data"]["code"], "UNSUPPORTED_ACTION");
+        let nested = json!({ "domain": "pipeline", "action": "run", "payload": { "steps": [{ "domain": "api", "action": "token" }] } });
+        let err = execute_rpc(json!({ "jsonrpc": "2.0", "id": 5, "method": "jobs.start", "params": nested }).to_string().as_bytes());
+        assert_eq!(err["error"]["data"]["code"], "UNSUPPORTED_ACTION");
+        // Started directly, the job runs; its pipeline then refuses the api step.
+        let started = super::super::execute_tool("jobs", "start", &nested).expect("start");
+        let id = json!({ "jobId": started["jobId"] });
+        let status = (0..200)
+            .map(|_| {
+                std::thread::sleep(Duration::from_millis(10));
+                super::super::execute_tool("jobs", "status", &id).expect("status")
+            })
+            .find(|s| s["status"] != "running")
+            .expect("job finished");
+        assert_eq!(status["status"], "failed");
+        assert_eq!(status["error"]["code"], "UNSUPPORTED_ACTION");
+    }
+
+    #[test]
+    fn api_settings_and_token_file_are_off_limits() {
+        let data_dir = crate::tools::helpers::use_test_data_dir();
+        let token = data_dir.join(TOKEN_FILE).to_string_lossy().into_owned();
+        let denied = |domain: &str, action: &str, payload: Value| {
+            guard(domain, action, &payload).expect_err("denied").code() == "PERMISSION_DENIED"
+        };
+        assert!(denied("settings", "set", json!({ "key": KEY_PORT, "value": 9 })));
+        assert!(denied("settings", "import", json!({ "data": "{}" })));
+        assert!(denied("file", "write_text", json!({ "path": token, "content": "x" })));
+        assert!(denied("convert", "csv_read_file", json!({ "path": token })));
+        assert!(denied("jobs", "start", json!({ "domain": "convert", "action": "csv_read_file", "payload": { "path": token } })));
+        let templated = json!({ "steps": [{ "domain": "settings", "action": "set", "payloadTemplate": { "key": "{{input}}" } }] });
+        assert!(denied("pipeline", "run", templated));
+
+        assert!(denied("history", "replay", json!({ "id": 1 })));
+        assert!(denied("jobs", "start", json!({ "domain": "history", "action": "replay", "payload": { "id": 1 } })));
+
+        guard("settings", "set", &json!({ "key": "theme", "value": "dark" })).expect("other settings");
+        guard("history", "list", &json!({})).expect("other history actions");
+        guard("settings", "import", &json!({ "data": "{}", "sections": ["snippets"] })).expect("other sections");
+        guard("convert", "csv_read_file", &json!({ "path": data_dir.join("notes.csv") })).expect("other files");
+    }
+
+    #[test]
+    fn rendered_steps_cannot_reach_the_token_file() {
+        let data_dir = crate::tools::helpers::use_test_data_dir();
+        let token = data_dir.join(TOKEN_FILE).to_string_lossy().into_owned();
+        let encoded = super::super::execute_tool("encode", "base64_encode", &json!({ "input": token })).expect("encode");
+        let steps = json!([
+            { "domain": "encode", "action": "base64_decode" },
+            { "domain": "convert", "action": "csv_read_file", "payloadTemplate": { "path": "{{prev}}" } },
+        ]);
+        let params = json!({ "steps": steps, "input": encoded });
+        let call = json!({ "jsonrpc": "2.0", "id": 6, "method": "pipeline.run", "params": params });
+        let err = execute_rpc(call.to_string().as_bytes());
+        assert_eq!(err["error"]["data"]["code"], "PERMISSION_DENIED");
+        assert!(!serving());
+        // The same pipeline is not the API's business when run in the app.
+        let outcome = super::super::execute_tool("pipeline", "run", &params);
+        assert!(outcome.map_or_else(|e| e.code() != "PERMISSION_DENIED", |_| true));
+    }
+
+    #[test]
+    fn http_execute_returns_tool_response() {
+        crate::tools::helpers::use_test_data_dir();
+        let body = br#"{"request_id":"r1","domain":"encode","action":"base64_encode","payload":{"input":"hi"}}"#;
+        let response = execute_http(body);
+        assert_eq!(response["request_id"], "r1");
+        assert_eq!(response["ok"], true);
+        assert_eq!(response["data"], "aGk=");
+    }
+}
@@ -0,0 +1,397 @@
+//! Backups of `lazycat.sqlite` into `<data_dir>/backups`.
+//!
+//! Copies are taken with SQLite's online backup API, so they are consistent
+//! even while other connections write. Restoring goes the same way in the
+//! other direction, into the live database, after the current state has been
+//! saved as a `pre-restore` backup.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, Local};
+use rusqlite::backup::Backup;
+use rusqlite::{Connection, OpenFlags};
+use serde_json::{json, Value};
+
+use super::cron;
+use super::encryption;
+use super::error::{ToolError, ToolResult};
+use super::helpers::{db_conn, db_path, exclusive_db, get_data_dir};
+use super::registry::{ActionSpec, DomainSpec, FieldSpec};
+use super::settings::read_setting;
+
+pub const BACKUP_DIR: &str = "backups";
+
+pub const KIND_AUTO: &str = "auto";
+pub const KIND_MANUAL: &str = "manual";
+pub const KIND_PRE_MIGRATION: &str = "pre-migration";
+pub const KIND_PRE_RESTORE: &str = "pre-restore";
+
+pub const KEY_ENABLED: &str = "backup.enabled";
+pub const KEY_SCHEDULE: &str = "backup.schedule";
+pub const KEY_RETENTION: &str = "backup.retention";
+/// Daily at midnight; missed runs happen at the next start.
+pub const DEFAULT_SCHEDULE: &str = "0 0 0 * * *";
+pub const DEFAULT_RETENTION: usize = 10;
+
+const PAGES_PER_STEP: i32 = 256;
+const STEP_PAUSE: Duration = Duration::from_millis(10);
+const TICK: Duration = Duration::from_secs(60);
+
+static SCHEDULER_STARTED: AtomicBool = AtomicBool::new(false);
+
+const FILENAME: FieldSpec = FieldSpec::required("filename", "string", "backup file name from list");
+
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "backup",
+    description: "Database backups with rotation, integrity checks and restore",
+    execute,
+    actions: &[
+        ActionSpec::new("create", "Back up the database now", &[], "object: { filename, size }"),
+        ActionSpec::new(
+            "list",
+            "List database backups, newest first",
+            &[],
+            "array: [{ filename, kind, size, createdAt }]",
+        ),
+        ActionSpec::new(
+            "check",
+            "Run an integrity check on a backup",
+            &[FILENAME],
+            "object: { filename, ok, messages, schemaVersion }",
+        ),
+        ActionSpec::new(
+            "restore",
+            "Replace the live database with a backup, saving the current state first",
+            &[FILENAME],
+            "object: { ok, restoredFrom, safetyBackup }",
+        ),
+        ActionSpec::new(
+            "status",
+            "Backup settings and directory",
+            &[],
+            "object: { enabled, schedule, retention, dir, lastAutoBackup }",
+        ),
+    ],
+};
+
+pub fn execute(action: &str, payload: &Value) -> ToolResult {
+    match action {
+        "create" => {
+            let conn = db_conn()?;
+            let path = create_from(&conn, KIND_MANUAL)?;
+            Ok(backup_json(&path))
+        }
+        "list" => Ok(Value::Array(list(&backup_dir()?).iter().map(|p| backup_json(p)).collect())),
+        "check" => {
+            let path = resolve(payload)?;
+            let (ok, messages, schema_version) = check(&path)?;
+            Ok(json!({
+                "filename": file_name(&path),
+                "ok": ok,
+                "messages": messages,
+                "schemaVersion": schema_version,
+            }))
+        }
+        "restore" => restore(&resolve(payload)?),
+        "status" => status(),
+        _ => Err(ToolError::unsupported("backup", action)),
+    }
+}
+
+fn backup_dir() -> ToolResult<PathBuf> {
+    let dir = get_data_dir()?.join(BACKUP_DIR);
+    fs::create_dir_all(&dir).map_err(|e| ToolError::io("create backup dir failed", e))?;
+    Ok(dir)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().unwrap_or_default().to_string_lossy().to_string()
+}
+
+/// `lazycat-<YYYYmmdd-HHMMSS>-<kind>[-n].sqlite` -> kind.
+fn kind_of(name: &str) -> Option<&str> {
+    let rest = name.strip_prefix("lazycat-")?.strip_suffix(".sqlite")?;
+    let (stamp, kind) = (rest.get(..15)?, rest.get(16..)?);
+    let stamp_ok = stamp.bytes().enumerate().all(|(i, b)| if i == 8 { b == b'-' } else { b.is_ascii_digit() });
+    if !stamp_ok || rest.as_bytes()[15] != b'-' || kind.is_empty() {
+        return None;
+    }
+    let kind = kind.rsplit_once('-').filter(|(_, n)| n.parse::<u32>().is_ok()).map_or(kind, |(k, _)| k);
+    Some(kind)
+}
+
+fn modified(path: &Path) -> SystemTime {
+    fs::metadata(path).and_then(|m| m.modified()).unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+fn backup_json(path: &Path) -> Value {
+    let name = file_name(path);
+    let created: DateTime<Local> = modified(path).into();
+    json!({
+        "filename": name,
+        "kind": kind_of(&name),
+        "size": fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+        "createdAt": created.format("%Y-%m-%d %H:%M:%S").to_string(),
+    })
+}
+
+/// Backups in `dir`, newest first.
+fn list(dir: &Path) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| kind_of(&file_name(p)).is_some())
+                .collect()
+        })
+        .unwrap_or_default();
+    // Names start with the timestamp, so they sort chronologically.
+    paths.sort_by_key(|p| std::cmp::Reverse(file_name(p)));
+    paths
+}
+
+/// Every backup file, newest first.
+pub fn files() -> ToolResult<Vec<PathBuf>> {
+    Ok(list(&backup_dir()?))
+}
+
+/// A backup name from the payload, confined to the backup directory.
+fn resolve(payload: &Value) -> ToolResult<PathBuf> {
+    let name = payload["filename"].as_str().unwrap_or_default();
+    if kind_of(name).is_none() || name.contains(['/', '\\']) || name.contains("..") {
+        return Err(ToolError::invalid_field("filename", "invalid backup filename"));
+    }
+    let path = backup_dir()?.join(name);
+    if !path.is_file() {
+        return Err(ToolError::not_found(format!("backup not found: {name}")));
+    }
+    Ok(path)
+}
+
+/// Copies `source` into `dest` page by page, yielding between steps so
+/// writers on other connections are not blocked for the whole copy.
+pub fn copy_database(source: &Connection, dest: &mut Connection) -> ToolResult<()> {
+    Backup::new(source, dest)
+        .and_then(|backup| backup.run_to_completion(PAGES_PER_STEP, STEP_PAUSE, None))
+        .map_err(|e| ToolError::Execution(format!("sqlite backup failed: {e}")))
+}
+
+fn backup_to(conn: &Connection, dir: &Path, kind: &str) -> ToolResult<PathBuf> {
+    let stamp = Local::now().format("%Y%m%d-%H%M%S");
+    let mut path = dir.join(format!("lazycat-{stamp}-{kind}.sqlite"));
+    let mut n = 2;
+    while path.exists() {
+        path = dir.join(format!("lazycat-{stamp}-{kind}-{n}.sqlite"));
+        n += 1;
+    }
+    let mut dest = encryption::open(&path).map_err(|e| format!("open backup file failed: {e}"))?;
+    if let Err(e) = copy_database(conn, &mut dest) {
+        drop(dest);
+        let _ = fs::remove_file(&path);
+        return Err(e);
+    }
+    Ok(path)
+}
+
+/// Backs up through an already-open connection and applies retention.
+pub fn create_from(conn: &Connection, kind: &str) -> ToolResult<PathBuf> {
+    let dir = backup_dir()?;
+    let path = backup_to(conn, &dir, kind)?;
+    prune(&dir, retention(conn));
+    Ok(path)
+}
+
+/// Read through `conn` rather than `read_setting`: this also runs from
+/// inside `db_conn` before migrations, where opening another connection
+/// would migrate (and back up) again.
+fn retention(conn: &Connection) -> usize {
+    conn.query_row("SELECT value FROM user_settings WHERE key = ?1", [KEY_RETENTION], |row| {
+        row.get::<_, String>(0)
+    })
+    .ok()
+    .and_then(|v| v.trim().parse().ok())
+    .filter(|n| *n > 0)
+    .unwrap_or(DEFAULT_RETENTION)
+}
+
+/// Keeps the newest `keep` backups of each kind; manual backups are never
+/// removed automatically.
+fn prune(dir: &Path, keep: usize) {
+    let mut seen: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+    for path in list(dir) {
+        let name = file_name(&path);
+        let Some(kind) = kind_of(&name).filter(|k| *k != KIND_MANUAL) else { continue };
+        let count = seen.entry(kind.to_string()).or_default();
+        *count += 1;
+        if *count > keep {
+            let _ = fs::remove_file(&path);
+        }
+    }
+}
+
+/// `(ok, messages, schema version)` of the database at `path`.
+fn check(path: &Path) -> ToolResult<(bool, Vec<String>, i64)> {
+    let conn = encryption::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("open backup failed: {e}"))?;
+    let messages: Vec<String> = conn
+        .prepare("PRAGMA integrity_check")
+        .and_then(|mut stmt| stmt.query_map([], |row| row.get(0))?.collect())
+        .map_err(|e| ToolError::Execution(format!("integrity check failed: {e}")))?;
+    let ok = messages.len() == 1 && messages[0] == "ok";
+    let schema_version: i64 = conn
+        .query_row("SELECT COALESCE(MAX(version), 0) FROM schema_version", [], |row| row.get(0))
+        .unwrap_or(0);
+    Ok((ok, messages, schema_version))
+}
+
+/// Copies `backup` over the database at `live` through the backup API, so
+/// connections that stay open see the restored data on their next read.
+fn restore_into(live: &Path, backup: &Path) -> ToolResult<()> {
+    let source = encryption::open_with_flags(backup, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("open backup failed: {e}"))?;
+    let mut dest = encryption::open(live).map_err(|e| format!("open database failed: {e}"))?;
+    dest.busy_timeout(Duration::from_secs(5)).map_err(|e| format!("set busy timeout failed: {e}"))?;
+    copy_database(&source, &mut dest)
+}
+
+fn restore(backup: &Path) -> ToolResult {
+    let (ok, messages, _) = check(backup)?;
+    if !ok {
+        return Err(ToolError::Execution(format!(
+            "backup failed its integrity check: {}",
+            messages.join("; ")
+        )));
+    }
+    // No other connection may commit between the safety backup and the
+    // restore, or that write would be lost.
+    let exclusive = exclusive_db()?;
+    let live = db_path()?;
+    let safety = create_from(&encryption::open(&live)?, KIND_PRE_RESTORE)?;
+    restore_into(&live, backup)?;
+    drop(exclusive);
+    // An older backup may predate some migrations; reopening runs them.
+    db_conn()?;
+    Ok(json!({
+        "ok": true,
+        "restoredFrom": file_name(backup),
+        "safetyBackup": file_name(&safety),
+    }))
+}
+
+fn enabled() -> bool {
+    !matches!(read_setting(KEY_ENABLED).ok().flatten().as_deref(), Some("false") | Some("0"))
+}
+
+fn schedule_expression() -> String {
+    read_setting(KEY_SCHEDULE)
+        .ok()
+        .flatten()
+        .filter(|v| !v.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_SCHEDULE.to_string())
+}
+
+fn last_auto_backup(dir: &Path) -> Option<PathBuf> {
+    list(dir).into_iter().find(|p| kind_of(&file_name(p)) == Some(KIND_AUTO))
+}
+
+fn status() -> ToolResult {
+    let dir = backup_dir()?;
+    let last = last_auto_backup(&dir).map(|p| backup_json(&p));
+    // Read before taking a connection: each opens its own, and a nested one
+    // would wait behind a pending `exclusive_db`.
+    let (enabled, schedule) = (enabled(), schedule_expression());
+    let retention = retention(&*db_conn()?);
+    Ok(json!({
+        "enabled": enabled,
+        "schedule": schedule,
+        "retention": retention,
+        "dir": dir.to_string_lossy(),
+        "lastAutoBackup": last,
+    }))
+}
+
+fn backup_if_due() -> ToolResult<()> {
+    if !enabled() {
+        return Ok(());
+    }
+    let schedule = cron::schedule(&schedule_expression()).map_err(ToolError::parse)?;
+    let dir = backup_dir()?;
+    let due = match last_auto_backup(&dir) {
+        None => true,
+        Some(last) => {
+            let last: DateTime<Local> = modified(&last).into();
+            schedule.after(&last).next().is_some_and(|next| next <= Local::now())
+        }
+    };
+    if due {
+        let conn = db_conn()?;
+        create_from(&conn, KIND_AUTO)?;
+    }
+    Ok(())
+}
+
+/// Starts the scheduled backup thread; later calls are no-ops.
+pub fn start_scheduler() -> ToolResult<()> {
+    if SCHEDULER_STARTED.swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
+    thread::Builder::new()
+        .name("db-backup".into())
+        .spawn(|| loop {
+            if let Err(e) = backup_if_due() {
+                eprintln!("scheduled backup failed: {}", e.message());
+            }
+            thread::sleep(TICK);
+        })
+        .map_err(|e| format!("spawn backup scheduler failed: {e}"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backs_up_prunes_and_restores() {
+        let dir = std::env::temp_dir().join(format!("lazycat-backup-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).expect("dir");
+        let live = dir.join("live.sqlite");
+        let conn = Connection::open(&live).expect("open");
+        conn.execute_batch("CREATE TABLE t (v TEXT); INSERT INTO t VALUES ('before');").expect("seed");
+
+        let first = backup_to(&conn, &dir, KIND_AUTO).expect("backup");
+        let second = backup_to(&conn, &dir, KIND_AUTO).expect("backup");
+        backup_to(&conn, &dir, KIND_MANUAL).expect("backup");
+        assert_eq!(kind_of(&file_name(&first)), Some(KIND_AUTO));
+        assert_eq!(kind_of(&file_name(&second)), Some(KIND_AUTO));
+        assert!(check(&first).expect("check").0);
+
+        prune(&dir, 1);
+        let kinds: Vec<_> = list(&dir).iter().map(|p| kind_of(&file_name(p)).unwrap().to_string()).collect();
+        assert_eq!(kinds.iter().filter(|k| *k == KIND_AUTO).count(), 1);
+        assert_eq!(kinds.iter().filter(|k| *k == KIND_MANUAL).count(), 1);
+
+        conn.execute("UPDATE t SET v = 'after'", []).expect("update");
+        let kept = list(&dir).into_iter().find(|p| kind_of(&file_name(p)) == Some(KIND_AUTO)).unwrap();
+        restore_into(&live, &kept).expect("restore");
+        let v: String = conn.query_row("SELECT v FROM t", [], |r| r.get(0)).expect("read");
+        assert_eq!(v, "before");
+
+        drop(conn);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn flags_corrupt_backups() {
+        let path = std::env::temp_dir().join(format!("lazycat-{}-auto.sqlite", uuid::Uuid::new_v4()));
+        fs::write(&path, b"definitely not a database").expect("write");
+        // Without the SQLite header it reads as encrypted, and no key opens it.
+        check(&path).expect_err("not a database");
+        let _ = fs::remove_file(path);
+    }
+}
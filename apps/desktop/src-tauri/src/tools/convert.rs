@@ -1,6 +1,9 @@
 use serde_json::{json, Value};
 use std::fs;
 
+use super::error::{ToolError, ToolResult};
+use super::registry::{ActionSpec, DomainSpec, FieldSpec};
+
 fn json_to_xml(root_tag: &str, value: &Value) -> String {
     let root = sanitize_xml_tag(root_tag, "root");
     let mut out = String::new();
@@ -290,11 +293,74 @@ fn parse_java_fields(bean: &str) -> (serde_json::Map<String, Value>, Vec<Value>,
     (map, fields, warnings)
 }
 
-pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
+const INPUT: &[FieldSpec] = &[FieldSpec::required("input", "string", "source document")];
+
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "convert",
+    description: "Convert between JSON, XML, YAML, CSV and Java beans",
+    execute,
+    actions: &[
+        ActionSpec::new(
+            "json_to_xml",
+            "Convert JSON to pretty XML",
+            &[
+                FieldSpec::required("input", "string", "JSON text"),
+                FieldSpec::optional("rootTag", "string", "root element name, default root"),
+            ],
+            "string: XML text",
+        ),
+        ActionSpec::new("xml_to_json", "Convert XML to pretty JSON", INPUT, "string: JSON text"),
+        ActionSpec::new("json_to_yaml", "Convert JSON to YAML", INPUT, "string: YAML text"),
+        ActionSpec::new(
+            "csv_to_json",
+            "Convert CSV rows to a JSON array of objects",
+            &[
+                FieldSpec::required("input", "string", "CSV text"),
+                FieldSpec::optional("delimiter", "string", "single-character delimiter, default ,"),
+                FieldSpec::optional("hasHeader", "boolean", "first row is a header, default true"),
+                FieldSpec::optional("customHeaders", "array", "column names overriding the header row"),
+                FieldSpec::optional("selectedColumns", "array", "zero-based column indexes to keep"),
+            ],
+            "string: JSON text",
+        ),
+        ActionSpec::new(
+            "csv_read_file",
+            "Read a CSV file as UTF-8 or GBK text",
+            &[FieldSpec::required("path", "string", "CSV file path")],
+            "string: file content",
+        ),
+        ActionSpec::new(
+            "java_bean_to_json",
+            "Build a sample JSON object from Java bean fields",
+            &[FieldSpec::required("bean", "string", "Java class source")],
+            "object: { json, fields, warnings }",
+        ),
+        ActionSpec::new(
+            "json_to_js_object",
+            "Render JSON as a JavaScript object literal",
+            &[
+                FieldSpec::required("json", "string", "JSON text"),
+                FieldSpec::optional("quoteStyle", "string", "single | double, default single"),
+            ],
+            "object: { jsObject }",
+        ),
+        ActionSpec::new(
+            "java_bean_to_js_object",
+            "Render Java bean fields as JSON and a JavaScript object literal",
+            &[
+                FieldSpec::required("bean", "string", "Java class source"),
+                FieldSpec::optional("quoteStyle", "string", "single | double, default single"),
+            ],
+            "object: { json, jsObject, fields, warnings }",
+        ),
+    ],
+};
+
+pub fn execute(action: &str, payload: &Value) -> ToolResult {
     match action {
         "json_to_xml" => {
             let input = payload["input"].as_str().unwrap_or_default();
-            let v: Value = serde_json::from_str(input).map_err(|e| format!("invalid json: {e}"))?;
+            let v: Value = serde_json::from_str(input).map_err(|e| ToolError::json("invalid json", &e))?;
             let root_tag = payload["rootTag"]
                 .as_str()
                 .map(str::trim)
@@ -304,12 +370,12 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
         }
         "xml_to_json" => {
             let input = payload["input"].as_str().unwrap_or_default();
-            let v: Value = quick_xml::de::from_str(input).map_err(|e| format!("invalid xml: {e}"))?;
+            let v: Value = quick_xml::de::from_str(input).map_err(|e| ToolError::xml("invalid xml", input, &e))?;
             Ok(json!(serde_json::to_string_pretty(&v).unwrap_or_else(|_| "{}".into())))
         }
         "json_to_yaml" => {
             let input = payload["input"].as_str().unwrap_or_default();
-            let v: Value = serde_json::from_str(input).map_err(|e| format!("invalid json: {e}"))?;
+            let v: Value = serde_json::from_str(input).map_err(|e| ToolError::json("invalid json", &e))?;
             let out = serde_yaml::to_string(&v).map_err(|e| format!("json->yaml failed: {e}"))?;
             Ok(json!(out))
         }
@@ -333,7 +399,7 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
                 custom.clone()
             } else if has_header {
                 rdr.headers()
-                    .map_err(|e| format!("csv read header failed: {e}"))?
+                    .map_err(|e| ToolError::csv("csv read header failed", &e))?
                     .iter()
                     .map(|s| s.to_string())
                     .collect()
@@ -352,7 +418,7 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
 
             let mut rows = Vec::new();
             for rec in rdr.records() {
-                let record = rec.map_err(|e| format!("csv record failed: {e}"))?;
+                let record = rec.map_err(|e| ToolError::csv("csv record failed", &e))?;
                 let mut obj = serde_json::Map::new();
                 for (i, col) in headers.iter().enumerate() {
                     if let Some(ref sel) = selected_columns {
@@ -369,17 +435,17 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
         "csv_read_file" => {
             let path = payload["path"].as_str().unwrap_or_default();
             if path.is_empty() {
-                return Err("file path is empty".into());
+                return Err(ToolError::invalid_field("path", "file path is empty"));
             }
             let bytes = fs::read(path)
-                .map_err(|e| format!("read csv file failed: {e}"))?;
+                .map_err(|e| ToolError::io("read csv file failed", e))?;
             // Try UTF-8 first; fall back to GBK (common on Windows for Chinese text)
             let content = match String::from_utf8(bytes.clone()) {
                 Ok(s) => s,
                 Err(_) => {
                     let (cow, _, had_errors) = encoding_rs::GBK.decode(&bytes);
                     if had_errors {
-                        return Err("文件编码无法识别，请使用 UTF-8 或 GBK 编码的文件".into());
+                        return Err(ToolError::parse("文件编码无法识别，请使用 UTF-8 或 GBK 编码的文件"));
                     }
                     cow.into_owned()
                 }
@@ -389,7 +455,7 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
         "java_bean_to_json" => {
             let bean = payload["bean"].as_str().unwrap_or_default();
             if bean.trim().is_empty() {
-                return Err("bean is empty".into());
+                return Err(ToolError::invalid_field("bean", "bean is empty"));
             }
             let (map, fields, warnings) = parse_java_fields(bean);
             Ok(json!({
@@ -401,12 +467,12 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
         "json_to_js_object" => {
             let json_input = payload["json"].as_str().unwrap_or_default();
             if json_input.trim().is_empty() {
-                return Err("json is empty".into());
+                return Err(ToolError::invalid_field("json", "json is empty"));
             }
             let quote_style = payload["quoteStyle"].as_str().unwrap_or("single");
             let quote = if quote_style.eq_ignore_ascii_case("double") { '"' } else { '\'' };
             let value: Value =
-                serde_json::from_str(json_input).map_err(|e| format!("invalid json: {e}"))?;
+                serde_json::from_str(json_input).map_err(|e| ToolError::json("invalid json", &e))?;
             let body = json_to_js_object_literal(&value, 0, quote);
             Ok(json!({
                 "jsObject": format!("const payload = {body};")
@@ -415,7 +481,7 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
         "java_bean_to_js_object" => {
             let bean = payload["bean"].as_str().unwrap_or_default();
             if bean.trim().is_empty() {
-                return Err("bean is empty".into());
+                return Err(ToolError::invalid_field("bean", "bean is empty"));
             }
             let quote_style = payload["quoteStyle"].as_str().unwrap_or("single");
             let quote = if quote_style.eq_ignore_ascii_case("double") { '"' } else { '\'' };
@@ -429,6 +495,6 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
                 "warnings": warnings
             }))
         }
-        _ => Err(format!("unsupported convert action: {action}")),
+        _ => Err(ToolError::unsupported("convert", action)),
     }
 }
@@ -4,6 +4,9 @@ use cron::Schedule;
 use serde_json::{json, Value};
 use std::str::FromStr;
 
+use super::error::{ToolError, ToolResult};
+use super::registry::{ActionSpec, DomainSpec, FieldSpec};
+
 #[derive(Debug)]
 struct NormalizedCron {
     normalized_expression: String,
@@ -23,7 +26,52 @@ enum PreviewTimezone {
     Iana(Tz),
 }
 
-pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
+const EXPRESSION: &[FieldSpec] = &[FieldSpec::required("expression", "string", "5- or 6-field cron expression")];
+
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "cron",
+    description: "Cron expression builder, parser and previewer",
+    execute,
+    actions: &[
+        ActionSpec::new(
+            "generate",
+            "Join the six fields into an expression",
+            &[
+                FieldSpec::optional("second", "string", "default 0"),
+                FieldSpec::optional("minute", "string", "default *"),
+                FieldSpec::optional("hour", "string", "default *"),
+                FieldSpec::optional("dayOfMonth", "string", "default *"),
+                FieldSpec::optional("month", "string", "default *"),
+                FieldSpec::optional("dayOfWeek", "string", "default *"),
+            ],
+            "string: expression",
+        ),
+        ActionSpec::new(
+            "preview",
+            "Next run times in local time",
+            &[
+                FieldSpec::required("expression", "string", "6-field cron expression"),
+                FieldSpec::optional("count", "integer", "number of runs, default 5"),
+            ],
+            "array: string times",
+        ),
+        ActionSpec::new(
+            "preview_v2",
+            "Next run times in a time zone, with normalization warnings",
+            &[
+                FieldSpec::required("expression", "string", "5- or 6-field cron expression"),
+                FieldSpec::optional("count", "integer", "number of runs, default 8"),
+                FieldSpec::optional("timezone", "string", "local | utc | IANA zone, default local"),
+            ],
+            "object: { normalizedExpression, timezone, items, warnings }",
+        ),
+        ActionSpec::new("normalize", "Normalize to six fields", EXPRESSION, "object: normalized expression and fields"),
+        ActionSpec::new("describe", "Human-readable summary", EXPRESSION, "object: summary and per-field descriptions"),
+        ActionSpec::new("parse", "Validate and split into fields", EXPRESSION, "object: fields and validity"),
+    ],
+};
+
+pub fn execute(action: &str, payload: &Value) -> ToolResult {
     match action {
         "generate" => {
             let second = payload["second"].as_str().unwrap_or("0");
@@ -40,8 +88,8 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
             let expression = payload["expression"].as_str().unwrap_or("0 * * * * *").trim();
             let count = payload["count"].as_u64().unwrap_or(5) as usize;
 
-            let normalized = normalize_expression(expression)?;
-            let schedule = parse_schedule(&normalized.normalized_expression)?;
+            let normalized = normalize_expression(expression).map_err(ToolError::parse)?;
+            let schedule = parse_schedule(&normalized.normalized_expression).map_err(ToolError::parse)?;
 
             let now = Local::now();
             let times: Vec<String> = schedule
@@ -56,8 +104,8 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
             let count = payload["count"].as_u64().unwrap_or(8) as usize;
             let timezone_input = payload["timezone"].as_str().unwrap_or("local");
 
-            let normalized = normalize_expression(expression)?;
-            let schedule = parse_schedule(&normalized.normalized_expression)?;
+            let normalized = normalize_expression(expression).map_err(ToolError::parse)?;
+            let schedule = parse_schedule(&normalized.normalized_expression).map_err(ToolError::parse)?;
             let (timezone, timezone_name, timezone_warning) = parse_timezone(timezone_input);
 
             let items = match timezone {
@@ -82,8 +130,8 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
         }
         "normalize" => {
             let expression = payload["expression"].as_str().unwrap_or("").trim();
-            let normalized = normalize_expression(expression)?;
-            parse_schedule(&normalized.normalized_expression)?;
+            let normalized = normalize_expression(expression).map_err(ToolError::parse)?;
+            parse_schedule(&normalized.normalized_expression).map_err(ToolError::parse)?;
 
             Ok(json!({
                 "ok": true,
@@ -103,8 +151,8 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
         }
         "describe" => {
             let expression = payload["expression"].as_str().unwrap_or("").trim();
-            let normalized = normalize_expression(expression)?;
-            parse_schedule(&normalized.normalized_expression)?;
+            let normalized = normalize_expression(expression).map_err(ToolError::parse)?;
+            parse_schedule(&normalized.normalized_expression).map_err(ToolError::parse)?;
 
             let summary = summarize_expression(&normalized);
             let details = vec![
@@ -125,8 +173,8 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
         }
         "parse" => {
             let expression = payload["expression"].as_str().unwrap_or("").trim();
-            let normalized = normalize_expression(expression)?;
-            parse_schedule(&normalized.normalized_expression)?;
+            let normalized = normalize_expression(expression).map_err(ToolError::parse)?;
+            parse_schedule(&normalized.normalized_expression).map_err(ToolError::parse)?;
 
             Ok(json!({
                 "second": normalized.second,
@@ -139,7 +187,7 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
                 "warnings": normalized.warnings,
             }))
         }
-        _ => Err(format!("unsupported cron action: {action}")),
+        _ => Err(ToolError::unsupported("cron", action)),
     }
 }
 
@@ -182,6 +230,12 @@ fn normalize_expression(expression: &str) -> Result<NormalizedCron, String> {
     })
 }
 
+/// A 5- or 6-field expression as a schedule, for tools that run on a timer.
+pub fn schedule(expression: &str) -> Result<Schedule, String> {
+    let normalized = normalize_expression(expression.trim())?;
+    parse_schedule(&normalized.normalized_expression)
+}
+
 fn parse_schedule(expression: &str) -> Result<Schedule, String> {
     Schedule::from_str(expression).map_err(|e| format!("无效的 Cron 表达式: {e}"))
 }
@@ -4,13 +4,60 @@ use openssl::rsa::{Padding, Rsa};
 use openssl::symm::{decrypt, encrypt, Cipher};
 use serde_json::{json, Value};
 
-pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
+use super::error::{ToolError, ToolResult};
+use super::registry::{ActionSpec, DomainSpec, FieldSpec};
+
+const SYMMETRIC_ENCRYPT: &[FieldSpec] = &[
+    FieldSpec::required("plaintext", "string", "text to encrypt"),
+    FieldSpec::required("key", "string", "raw key bytes as text"),
+    FieldSpec::required("iv", "string", "raw IV bytes as text"),
+    FieldSpec::optional("algorithm", "string", "cipher name"),
+];
+
+const SYMMETRIC_DECRYPT: &[FieldSpec] = &[
+    FieldSpec::required("cipherTextBase64", "string", "base64 ciphertext"),
+    FieldSpec::required("key", "string", "raw key bytes as text"),
+    FieldSpec::required("iv", "string", "raw IV bytes as text"),
+    FieldSpec::optional("algorithm", "string", "cipher name"),
+];
+
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "crypto",
+    description: "RSA, AES and DES encryption",
+    execute,
+    actions: &[
+        ActionSpec::new(
+            "rsa_encrypt",
+            "RSA OAEP encrypt with a PEM public key",
+            &[
+                FieldSpec::required("plaintext", "string", "text to encrypt"),
+                FieldSpec::required("publicKeyPem", "string", "PEM public key"),
+            ],
+            "string: base64 ciphertext",
+        ),
+        ActionSpec::new(
+            "rsa_decrypt",
+            "RSA OAEP decrypt with a PEM private key",
+            &[
+                FieldSpec::required("cipherTextBase64", "string", "base64 ciphertext"),
+                FieldSpec::required("privateKeyPem", "string", "PEM private key"),
+            ],
+            "string: plaintext",
+        ),
+        ActionSpec::new("aes_encrypt", "AES-CBC encrypt (aes-128/192/256-cbc)", SYMMETRIC_ENCRYPT, "string: base64 ciphertext"),
+        ActionSpec::new("aes_decrypt", "AES-CBC decrypt (aes-128/192/256-cbc)", SYMMETRIC_DECRYPT, "string: plaintext"),
+        ActionSpec::new("des_encrypt", "DES / 3DES CBC encrypt (des-cbc, des-ede3-cbc)", SYMMETRIC_ENCRYPT, "string: base64 ciphertext"),
+        ActionSpec::new("des_decrypt", "DES / 3DES CBC decrypt (des-cbc, des-ede3-cbc)", SYMMETRIC_DECRYPT, "string: plaintext"),
+    ],
+};
+
+pub fn execute(action: &str, payload: &Value) -> ToolResult {
     match action {
         "rsa_encrypt" => {
             let plaintext = payload["plaintext"].as_str().unwrap_or_default().as_bytes().to_vec();
             let public_pem = payload["publicKeyPem"].as_str().unwrap_or_default();
             let rsa: Rsa<Public> =
-                Rsa::public_key_from_pem(public_pem.as_bytes()).map_err(|e| format!("invalid public key: {e}"))?;
+                Rsa::public_key_from_pem(public_pem.as_bytes()).map_err(|e| ToolError::invalid_field("publicKeyPem", format!("invalid public key: {e}")))?;
             let mut buf = vec![0; rsa.size() as usize];
             let len = rsa
                 .public_encrypt(&plaintext, &mut buf, Padding::PKCS1_OAEP)
@@ -20,10 +67,10 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
         }
         "rsa_decrypt" => {
             let cipher = payload["cipherTextBase64"].as_str().unwrap_or_default();
-            let data = BASE64.decode(cipher).map_err(|e| format!("invalid base64: {e}"))?;
+            let data = BASE64.decode(cipher).map_err(|e| ToolError::invalid_field("cipherTextBase64", format!("invalid base64: {e}")))?;
             let private_pem = payload["privateKeyPem"].as_str().unwrap_or_default();
             let rsa: Rsa<Private> =
-                Rsa::private_key_from_pem(private_pem.as_bytes()).map_err(|e| format!("invalid private key: {e}"))?;
+                Rsa::private_key_from_pem(private_pem.as_bytes()).map_err(|e| ToolError::invalid_field("privateKeyPem", format!("invalid private key: {e}")))?;
             let mut buf = vec![0; rsa.size() as usize];
             let len = rsa
                 .private_decrypt(&data, &mut buf, Padding::PKCS1_OAEP)
@@ -48,7 +95,7 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
             let cipher_text = payload["cipherTextBase64"].as_str().unwrap_or_default();
             let cipher_data = BASE64
                 .decode(cipher_text)
-                .map_err(|e| format!("invalid base64: {e}"))?;
+                .map_err(|e| ToolError::invalid_field("cipherTextBase64", format!("invalid base64: {e}")))?;
             let key = payload["key"].as_str().unwrap_or_default().as_bytes();
             let iv = payload["iv"].as_str().unwrap_or_default().as_bytes();
             let algorithm = payload["algorithm"].as_str().unwrap_or("aes-256-cbc");
@@ -77,7 +124,7 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
             let cipher_text = payload["cipherTextBase64"].as_str().unwrap_or_default();
             let cipher_data = BASE64
                 .decode(cipher_text)
-                .map_err(|e| format!("invalid base64: {e}"))?;
+                .map_err(|e| ToolError::invalid_field("cipherTextBase64", format!("invalid base64: {e}")))?;
             let key = payload["key"].as_str().unwrap_or_default().as_bytes();
             let iv = payload["iv"].as_str().unwrap_or_default().as_bytes();
             let algorithm = payload["algorithm"].as_str().unwrap_or("des-ede3-cbc");
@@ -89,6 +136,6 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
             let out = decrypt(cipher, key, Some(iv), &cipher_data).map_err(|e| format!("des decrypt failed: {e}"))?;
             Ok(json!(String::from_utf8_lossy(&out).to_string()))
         }
-        _ => Err(format!("unsupported crypto action: {action}")),
+        _ => Err(ToolError::unsupported("crypto", action)),
     }
 }
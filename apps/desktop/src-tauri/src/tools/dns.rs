@@ -7,15 +7,40 @@ use hickory_resolver::config::{ResolverConfig, ResolverOpts, NameServerConfig, P
 use hickory_resolver::TokioAsyncResolver;
 use hickory_resolver::proto::rr::RecordType;
 
-pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
+use super::error::{ToolError, ToolResult};
+use super::jobs::report_progress;
+use super::registry::{ActionSpec, DomainSpec, FieldSpec};
+
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "dns",
+    description: "DNS lookups",
+    execute,
+    actions: &[
+        ActionSpec::new(
+            "resolve",
+            "Query A, AAAA, CNAME, MX, NS, TXT, SOA and SRV records",
+            &[
+                FieldSpec::required("domain", "string", "domain name"),
+                FieldSpec::optional("server", "string", "DNS server IP, default system resolver"),
+            ],
+            "object: { domain, server, records, elapsed_ms }",
+        ),
+        ActionSpec::new("system_dns", "List the system DNS servers", &[], "object: { ipv4, all }"),
+    ],
+};
+
+/// Record types queried by `resolve`, in query order.
+const RECORD_TYPES: [&str; 8] = ["A", "AAAA", "CNAME", "MX", "NS", "TXT", "SOA", "SRV"];
+
+pub fn execute(action: &str, payload: &Value) -> ToolResult {
     match action {
         "resolve" => resolve(payload),
         "system_dns" => system_dns(),
-        _ => Err(format!("unsupported dns action: {action}")),
+        _ => Err(ToolError::unsupported("dns", action)),
     }
 }
 
-fn system_dns() -> Result<Value, String> {
+fn system_dns() -> ToolResult {
     let config = ResolverConfig::default();
     let mut ipv4 = Vec::<String>::new();
     let mut all = Vec::<String>::new();
@@ -37,13 +62,13 @@ fn system_dns() -> Result<Value, String> {
     }))
 }
 
-fn resolve(payload: &Value) -> Result<Value, String> {
+fn resolve(payload: &Value) -> ToolResult {
     let domain = payload["domain"]
         .as_str()
         .unwrap_or("")
         .trim();
     if domain.is_empty() {
-        return Err("domain is required".to_string());
+        return Err(ToolError::invalid_field("domain", "domain is required"));
     }
 
     let server = payload["server"]
@@ -62,7 +87,7 @@ fn resolve(payload: &Value) -> Result<Value, String> {
             TokioAsyncResolver::tokio(ResolverConfig::default(), ResolverOpts::default())
         } else {
             let ip: IpAddr = IpAddr::from_str(&server)
-                .map_err(|e| format!("invalid DNS server address: {e}"))?;
+                .map_err(|e| ToolError::invalid_field("server", format!("invalid DNS server address: {e}")))?;
             let ns = NameServerConfig::new(std::net::SocketAddr::new(ip, 53), Protocol::Udp);
             let mut config = ResolverConfig::new();
             config.add_name_server(ns);
@@ -71,25 +96,27 @@ fn resolve(payload: &Value) -> Result<Value, String> {
 
         let domain_name = format!("{}.", domain.trim_end_matches('.'));
 
-        let a_records = query_a(&resolver, &domain_name).await;
-        let aaaa_records = query_aaaa(&resolver, &domain_name).await;
-        let cname_records = query_cname(&resolver, &domain_name).await;
-        let mx_records = query_mx(&resolver, &domain_name).await;
-        let ns_records = query_ns(&resolver, &domain_name).await;
-        let txt_records = query_txt(&resolver, &domain_name).await;
-        let soa_records = query_soa(&resolver, &domain_name).await;
-        let srv_records = query_srv(&resolver, &domain_name).await;
+        let mut records = serde_json::Map::new();
+        for (idx, record_type) in RECORD_TYPES.iter().enumerate() {
+            report_progress(json!({
+                "step": record_type,
+                "completed": idx,
+                "total": RECORD_TYPES.len(),
+            }))?;
+            let value = match *record_type {
+                "A" => query_a(&resolver, &domain_name).await,
+                "AAAA" => query_aaaa(&resolver, &domain_name).await,
+                "CNAME" => query_cname(&resolver, &domain_name).await,
+                "MX" => query_mx(&resolver, &domain_name).await,
+                "NS" => query_ns(&resolver, &domain_name).await,
+                "TXT" => query_txt(&resolver, &domain_name).await,
+                "SOA" => query_soa(&resolver, &domain_name).await,
+                _ => query_srv(&resolver, &domain_name).await,
+            };
+            records.insert(record_type.to_string(), value);
+        }
 
-        Ok::<Value, String>(json!({
-            "A": a_records,
-            "AAAA": aaaa_records,
-            "CNAME": cname_records,
-            "MX": mx_records,
-            "NS": ns_records,
-            "TXT": txt_records,
-            "SOA": soa_records,
-            "SRV": srv_records,
-        }))
+        Ok::<Value, ToolError>(Value::Object(records))
     })?;
 
     let server_display = if server.is_empty() {
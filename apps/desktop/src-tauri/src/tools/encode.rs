@@ -3,7 +3,40 @@ use image::ImageFormat;
 use qrcode::QrCode;
 use serde_json::{json, Value};
 
-pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
+use super::error::{ToolError, ToolResult};
+use super::registry::{ActionSpec, DomainSpec, FieldSpec};
+
+const INPUT: &[FieldSpec] = &[FieldSpec::required("input", "string", "text to process")];
+
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "encode",
+    description: "Encoding, hashing and QR code helpers",
+    execute,
+    actions: &[
+        ActionSpec::new("base64_encode", "Base64-encode UTF-8 text", INPUT, "string: base64 text"),
+        ActionSpec::new("base64_decode", "Decode base64 into UTF-8 text", INPUT, "string: decoded text"),
+        ActionSpec::new("base64_url_encode", "URL-safe base64 without padding", INPUT, "string: base64url text"),
+        ActionSpec::new("base64_url_decode", "Decode URL-safe base64 without padding", INPUT, "string: decoded text"),
+        ActionSpec::new("url_encode", "Percent-encode text", INPUT, "string: encoded text"),
+        ActionSpec::new("url_decode", "Decode percent-encoded text", INPUT, "string: decoded text"),
+        ActionSpec::new("md5", "MD5 digest", INPUT, "string: lowercase hex digest"),
+        ActionSpec::new("qr_generate", "Render text as a QR code", INPUT, "string: PNG data URL"),
+        ActionSpec::new("sha1", "SHA-1 digest", INPUT, "string: lowercase hex digest"),
+        ActionSpec::new("sha256", "SHA-256 digest", INPUT, "string: lowercase hex digest"),
+        ActionSpec::new("sha512", "SHA-512 digest", INPUT, "string: lowercase hex digest"),
+        ActionSpec::new(
+            "hmac_sha256",
+            "HMAC-SHA256 of input with key",
+            &[
+                FieldSpec::required("input", "string", "message"),
+                FieldSpec::required("key", "string", "secret key"),
+            ],
+            "string: lowercase hex digest",
+        ),
+    ],
+};
+
+pub fn execute(action: &str, payload: &Value) -> ToolResult {
     match action {
         "base64_encode" => {
             let input = payload["input"].as_str().unwrap_or_default();
@@ -13,7 +46,7 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
             let input = payload["input"].as_str().unwrap_or_default();
             let decoded = BASE64
                 .decode(input)
-                .map_err(|e| format!("base64 decode failed: {e}"))?;
+                .map_err(|e| ToolError::parse(format!("base64 decode failed: {e}")))?;
             Ok(json!(String::from_utf8_lossy(&decoded).to_string()))
         }
         "base64_url_encode" => {
@@ -24,7 +57,7 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
             let input = payload["input"].as_str().unwrap_or_default();
             let decoded = BASE64URL
                 .decode(input)
-                .map_err(|e| format!("base64url decode failed: {e}"))?;
+                .map_err(|e| ToolError::parse(format!("base64url decode failed: {e}")))?;
             Ok(json!(String::from_utf8_lossy(&decoded).to_string()))
         }
         "url_encode" => {
@@ -35,7 +68,7 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
             let input = payload["input"].as_str().unwrap_or_default();
             Ok(json!(
                 urlencoding::decode(input)
-                    .map_err(|e| format!("url decode failed: {e}"))?
+                    .map_err(|e| ToolError::parse(format!("url decode failed: {e}")))?
                     .to_string()
             ))
         }
@@ -78,7 +111,7 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
             let input = payload["input"].as_str().unwrap_or_default();
             let key = payload["key"].as_str().unwrap_or_default();
             let pkey = openssl::pkey::PKey::hmac(key.as_bytes())
-                .map_err(|e| format!("hmac key failed: {e}"))?;
+                .map_err(|e| ToolError::parse(format!("hmac key failed: {e}")))?;
             let mut signer = openssl::sign::Signer::new(openssl::hash::MessageDigest::sha256(), &pkey)
                 .map_err(|e| format!("hmac init failed: {e}"))?;
             signer.update(input.as_bytes())
@@ -87,6 +120,6 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
                 .map_err(|e| format!("hmac sign failed: {e}"))?;
             Ok(json!(hex::encode(result)))
         }
-        _ => Err(format!("unsupported encode action: {action}")),
+        _ => Err(ToolError::unsupported("encode", action)),
     }
 }
@@ -0,0 +1,316 @@
+//! Optional at-rest encryption of the database with SQLCipher.
+//!
+//! SQLCipher derives the page key from the master password itself
+//! (PBKDF2-HMAC-SHA512), so nothing but the password needs to be kept, and
+//! only in memory for the running session. Whether a file is encrypted is
+//! read from its header: plaintext SQLite files start with a fixed magic
+//! string, SQLCipher files with a random salt.
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use rusqlite::{params, Connection, OpenFlags};
+use serde_json::{json, Value};
+
+use super::backup;
+use super::error::{ToolError, ToolResult};
+use super::helpers::{close_db, db_conn, db_path, exclusive_db};
+use super::registry::{ActionSpec, DomainSpec, FieldSpec};
+
+/// Lets the CLI and scripts open an encrypted database without a prompt.
+pub const PASSWORD_ENV: &str = "LAZYCAT_MASTER_PASSWORD";
+
+const SQLITE_MAGIC: &[u8; 16] = b"SQLite format 3\0";
+const MIN_PASSWORD_LEN: usize = 8;
+
+static PASSWORD: Mutex<Option<String>> = Mutex::new(None);
+
+const PASSWORD_FIELD: FieldSpec = FieldSpec::required("password", "string", "master password");
+
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "encryption",
+    description: "Master-password encryption of the local database",
+    execute,
+    actions: &[
+        ActionSpec::new("status", "Whether the database is encrypted and unlocked", &[], "object: { encrypted, unlocked }"),
+        ActionSpec::new("unlock", "Unlock the encrypted database for this session", &[PASSWORD_FIELD], "object: { ok }"),
+        ActionSpec::new("lock", "Forget the master password until the next unlock", &[], "object: { ok }"),
+        ActionSpec::new(
+            "enable",
+            "Encrypt the database and its backups with a new master password",
+            &[PASSWORD_FIELD],
+            "object: { ok, backups, skipped }",
+        ),
+        ActionSpec::new(
+            "change_password",
+            "Re-key the database and its backups",
+            &[
+                FieldSpec::required("current", "string", "current master password"),
+                FieldSpec::required("password", "string", "new master password"),
+            ],
+            "object: { ok, backups, skipped }",
+        ),
+        ActionSpec::new(
+            "disable",
+            "Decrypt the database and its backups back to plaintext",
+            &[PASSWORD_FIELD],
+            "object: { ok, backups, skipped }",
+        ),
+    ],
+};
+
+pub fn execute(action: &str, payload: &Value) -> ToolResult {
+    match action {
+        "status" => status(),
+        "unlock" => unlock(required(payload, "password")?),
+        "lock" => {
+            set_password(None);
+            close_db();
+            Ok(json!({ "ok": true }))
+        }
+        "enable" => enable(required(payload, "password")?),
+        "change_password" => change_password(required(payload, "current")?, required(payload, "password")?),
+        "disable" => disable(required(payload, "password")?),
+        _ => Err(ToolError::unsupported("encryption", action)),
+    }
+}
+
+fn required<'a>(payload: &'a Value, field: &str) -> ToolResult<&'a str> {
+    payload[field]
+        .as_str()
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| ToolError::invalid_field(field, format!("{field} is required")))
+}
+
+fn check_strength(password: &str) -> ToolResult<()> {
+    if password.chars().count() < MIN_PASSWORD_LEN {
+        return Err(ToolError::invalid_field(
+            "password",
+            format!("master password must be at least {MIN_PASSWORD_LEN} characters"),
+        ));
+    }
+    Ok(())
+}
+
+/// False for missing files and files shorter than a header: those are new
+/// databases, which SQLite creates on first write.
+pub fn is_encrypted(path: &Path) -> bool {
+    let mut header = [0u8; 16];
+    match File::open(path).and_then(|mut f| f.read_exact(&mut header)) {
+        Ok(()) => &header != SQLITE_MAGIC,
+        Err(_) => false,
+    }
+}
+
+fn password() -> Option<String> {
+    let stored = PASSWORD.lock().ok().and_then(|p| p.clone());
+    stored.or_else(|| std::env::var(PASSWORD_ENV).ok().filter(|p| !p.is_empty()))
+}
+
+fn set_password(password: Option<&str>) {
+    if let Ok(mut guard) = PASSWORD.lock() {
+        *guard = password.map(str::to_string);
+    }
+}
+
+/// Keys `conn` and reads from it, since SQLCipher only checks the key on
+/// first access.
+fn apply_key(conn: &Connection, password: &str) -> Result<(), String> {
+    conn.pragma_update(None, "key", password)
+        .map_err(|e| format!("set database key failed: {e}"))?;
+    conn.query_row("SELECT count(*) FROM sqlite_master", [], |r| r.get::<_, i64>(0))
+        .map_err(|_| "wrong master password".to_string())?;
+    Ok(())
+}
+
+/// Opens a database file, keyed when it is encrypted. While a master
+/// password is set, new files are created encrypted too, so backups follow
+/// the live database.
+pub fn open(path: &Path) -> Result<Connection, String> {
+    open_with_flags(path, OpenFlags::default())
+}
+
+pub fn open_with_flags(path: &Path, flags: OpenFlags) -> Result<Connection, String> {
+    let encrypted = is_encrypted(path);
+    let is_new = fs::metadata(path).map(|m| m.len() == 0).unwrap_or(true);
+    let conn = Connection::open_with_flags(path, flags).map_err(|e| format!("open db failed: {e}"))?;
+    match password() {
+        Some(pw) if encrypted || is_new => apply_key(&conn, &pw)?,
+        None if encrypted => return Err("database is locked: unlock it with the master password".to_string()),
+        _ => {}
+    }
+    Ok(conn)
+}
+
+/// Rewrites the database at `path` under `next` (`None` for plaintext).
+/// `sqlcipher_export` is the only way into or out of plaintext, so it is used
+/// for re-keying as well.
+fn export_as(path: &Path, current: Option<&str>, next: Option<&str>) -> Result<(), String> {
+    let tmp = path.with_extension("sqlite-rekey");
+    let _ = fs::remove_file(&tmp);
+    let result = (|| {
+        let conn = Connection::open(path).map_err(|e| format!("open db failed: {e}"))?;
+        if let Some(pw) = current {
+            apply_key(&conn, pw)?;
+        }
+        // Fold any write-ahead log back in, so no stale -wal file outlives
+        // the swap below.
+        conn.pragma_update_and_check(None, "journal_mode", "DELETE", |_| Ok(()))
+            .map_err(|e| format!("leave WAL mode failed: {e}"))?;
+        conn.execute(
+            "ATTACH DATABASE ?1 AS rekeyed KEY ?2",
+            params![tmp.to_string_lossy(), next.unwrap_or_default()],
+        )
+        .map_err(|e| format!("attach target failed: {e}"))?;
+        conn.query_row("SELECT sqlcipher_export('rekeyed')", [], |_| Ok(()))
+            .map_err(|e| format!("export database failed: {e}"))?;
+        conn.execute("DETACH DATABASE rekeyed", [])
+            .map_err(|e| format!("detach target failed: {e}"))?;
+        Ok(())
+    })();
+    if let Err(e) = result {
+        let _ = fs::remove_file(&tmp);
+        return Err(e);
+    }
+    // Backups are dated by modification time, which must survive re-keying.
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+    fs::rename(&tmp, path).map_err(|e| format!("replace {} failed: {e}", path.display()))?;
+    if let Some(time) = modified {
+        let _ = File::options().write(true).open(path).and_then(|f| f.set_modified(time));
+    }
+    Ok(())
+}
+
+/// Re-keys every backup that `current` opens; the rest (corrupt files, or
+/// ones under an older password) are counted as skipped.
+fn export_backups(current: Option<&str>, next: Option<&str>) -> ToolResult<(usize, usize)> {
+    let (mut done, mut skipped) = (0, 0);
+    let matching: Vec<PathBuf> = backup::files()?
+        .into_iter()
+        .filter(|p| is_encrypted(p) == current.is_some())
+        .collect();
+    for path in matching {
+        match export_as(&path, current, next) {
+            Ok(()) => done += 1,
+            Err(e) => {
+                eprintln!("re-key backup {} failed: {e}", path.display());
+                skipped += 1;
+            }
+        }
+    }
+    Ok((done, skipped))
+}
+
+fn report((backups, skipped): (usize, usize)) -> Value {
+    json!({ "ok": true, "backups": backups, "skipped": skipped })
+}
+
+fn status() -> ToolResult {
+    let path = db_path()?;
+    let encrypted = is_encrypted(&path);
+    let unlocked = !encrypted || open(&path).is_ok();
+    Ok(json!({ "encrypted": encrypted, "unlocked": unlocked }))
+}
+
+fn unlock(password: &str) -> ToolResult {
+    let path = db_path()?;
+    if !is_encrypted(&path) {
+        return Err(ToolError::invalid("the database is not encrypted"));
+    }
+    let conn = Connection::open(&path).map_err(|e| format!("open db failed: {e}"))?;
+    apply_key(&conn, password).map_err(ToolError::PermissionDenied)?;
+    set_password(Some(password));
+    Ok(json!({ "ok": true }))
+}
+
+fn enable(password: &str) -> ToolResult {
+    check_strength(password)?;
+    let path = db_path()?;
+    if is_encrypted(&path) {
+        return Err(ToolError::invalid("the database is already encrypted"));
+    }
+    // Make sure the file exists and is migrated before it is exported.
+    db_conn()?;
+    let _exclusive = exclusive_db()?;
+    export_as(&path, None, Some(password))?;
+    set_password(Some(password));
+    Ok(report(export_backups(None, Some(password))?))
+}
+
+fn change_password(current: &str, password: &str) -> ToolResult {
+    check_strength(password)?;
+    let path = db_path()?;
+    if !is_encrypted(&path) {
+        return Err(ToolError::invalid("the database is not encrypted"));
+    }
+    let conn = Connection::open(&path).map_err(|e| format!("open db failed: {e}"))?;
+    apply_key(&conn, current).map_err(ToolError::PermissionDenied)?;
+    drop(conn);
+    let _exclusive = exclusive_db()?;
+    export_as(&path, Some(current), Some(password))?;
+    set_password(Some(password));
+    Ok(report(export_backups(Some(current), Some(password))?))
+}
+
+fn disable(password: &str) -> ToolResult {
+    let path = db_path()?;
+    if !is_encrypted(&path) {
+        return Err(ToolError::invalid("the database is not encrypted"));
+    }
+    let conn = Connection::open(&path).map_err(|e| format!("open db failed: {e}"))?;
+    apply_key(&conn, password).map_err(ToolError::PermissionDenied)?;
+    drop(conn);
+    let _exclusive = exclusive_db()?;
+    export_as(&path, Some(password), None)?;
+    set_password(None);
+    Ok(report(export_backups(Some(password), None)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypts_rekeys_and_decrypts_in_place() {
+        let path = std::env::temp_dir().join(format!("lazycat-enc-{}.sqlite", uuid::Uuid::new_v4()));
+        let conn = Connection::open(&path).expect("open");
+        conn.execute_batch("CREATE TABLE t (v TEXT); INSERT INTO t VALUES ('secret');").expect("seed");
+        drop(conn);
+        assert!(!is_encrypted(&path));
+
+        export_as(&path, None, Some("first-password")).expect("encrypt");
+        assert!(is_encrypted(&path));
+        assert!(!fs::read(&path).expect("read").windows(6).any(|w| w == b"secret"));
+        let conn = Connection::open(&path).expect("open");
+        assert!(apply_key(&conn, "wrong-password").is_err());
+        drop(conn);
+
+        // Backups of an encrypted database are taken under the same key.
+        let copy = path.with_extension("copy");
+        let source = Connection::open(&path).expect("open");
+        apply_key(&source, "first-password").expect("key");
+        let mut dest = Connection::open(&copy).expect("open copy");
+        apply_key(&dest, "first-password").expect("key copy");
+        rusqlite::backup::Backup::new(&source, &mut dest)
+            .and_then(|b| b.run_to_completion(64, std::time::Duration::ZERO, None))
+            .expect("backup");
+        drop((source, dest));
+        assert!(is_encrypted(&copy));
+        let _ = fs::remove_file(copy);
+
+        export_as(&path, Some("first-password"), Some("second-password")).expect("rekey");
+        let conn = Connection::open(&path).expect("open");
+        apply_key(&conn, "second-password").expect("new key");
+        drop(conn);
+
+        export_as(&path, Some("second-password"), None).expect("decrypt");
+        assert!(!is_encrypted(&path));
+        let conn = Connection::open(&path).expect("open");
+        let v: String = conn.query_row("SELECT v FROM t", [], |r| r.get(0)).expect("read");
+        assert_eq!(v, "secret");
+        drop(conn);
+        let _ = fs::remove_file(path);
+    }
+}
@@ -1,7 +1,17 @@
 use serde_json::{json, Value};
 use std::process::Command;
 
-pub fn execute(action: &str, _payload: &Value) -> Result<Value, String> {
+use super::error::{ToolError, ToolResult};
+use super::registry::{ActionSpec, DomainSpec};
+
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "env",
+    description: "Development environment detection",
+    execute,
+    actions: &[ActionSpec::new("detect", "Detect node and java versions", &[], "object: { node, java }")],
+};
+
+pub fn execute(action: &str, _payload: &Value) -> ToolResult {
     match action {
         "detect" => {
             let node = Command::new("node")
@@ -18,6 +28,6 @@ pub fn execute(action: &str, _payload: &Value) -> Result<Value, String> {
                 .unwrap_or_else(|| "NOT_FOUND".into());
             Ok(json!({"node": node, "java": java}))
         }
-        _ => Err(format!("unsupported env action: {action}")),
+        _ => Err(ToolError::unsupported("env", action)),
     }
 }
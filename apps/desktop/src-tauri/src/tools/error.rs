@@ -0,0 +1,241 @@
+use serde_json::{json, Value};
+use std::fmt;
+use std::io;
+
+/// Result type returned by every tool action.
+pub type ToolResult<T = Value> = Result<T, ToolError>;
+
+/// Failure of a tool action. `code()` is the stable identifier sent to the UI;
+/// the message is for humans and may change between versions.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolError {
+    /// A payload field is missing, empty or has an unusable value.
+    InvalidPayload { message: String, field: Option<String> },
+    /// A referenced file, record or resource does not exist.
+    NotFound(String),
+    /// The OS refused access, or the user cancelled an elevation prompt.
+    PermissionDenied(String),
+    /// User-supplied text (JSON, XML, regex, cron, base64...) could not be parsed.
+    Parse { message: String, line: Option<usize>, column: Option<usize> },
+    UnsupportedAction { domain: String, action: String },
+    /// A background job was cancelled before it finished.
+    Cancelled,
+    /// Other I/O failures.
+    Io(String),
+    /// Anything not classified above.
+    Execution(String),
+}
+
+impl ToolError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ToolError::InvalidPayload { .. } => "INVALID_PAYLOAD",
+            ToolError::NotFound(_) => "NOT_FOUND",
+            ToolError::PermissionDenied(_) => "PERMISSION_DENIED",
+            ToolError::Parse { .. } => "PARSE_ERROR",
+            ToolError::UnsupportedAction { .. } => "UNSUPPORTED_ACTION",
+            ToolError::Cancelled => "CANCELLED",
+            ToolError::Io(_) => "IO_ERROR",
+            ToolError::Execution(_) => "TOOL_EXECUTION_FAILED",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            ToolError::InvalidPayload { message, .. } => message.clone(),
+            ToolError::NotFound(m)
+            | ToolError::PermissionDenied(m)
+            | ToolError::Io(m)
+            | ToolError::Execution(m) => m.clone(),
+            ToolError::Parse { message, .. } => message.clone(),
+            ToolError::UnsupportedAction { domain, action } => {
+                format!("unsupported command: {domain}.{action}")
+            }
+            ToolError::Cancelled => "job cancelled".to_string(),
+        }
+    }
+
+    /// Machine-readable context for the `details` field of the response.
+    pub fn details(&self) -> Option<Value> {
+        match self {
+            ToolError::InvalidPayload { field: Some(field), .. } => Some(json!({ "field": field })),
+            ToolError::Parse { line, column, .. } if line.is_some() || column.is_some() => {
+                Some(json!({ "line": line, "column": column }))
+            }
+            ToolError::UnsupportedAction { domain, action } => {
+                Some(json!({ "domain": domain, "action": action }))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn invalid(message: impl Into<String>) -> Self {
+        ToolError::InvalidPayload { message: message.into(), field: None }
+    }
+
+    /// Invalid payload caused by one named field.
+    pub fn invalid_field(field: &str, message: impl Into<String>) -> Self {
+        ToolError::InvalidPayload { message: message.into(), field: Some(field.to_string()) }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        ToolError::NotFound(message.into())
+    }
+
+    pub fn parse(message: impl Into<String>) -> Self {
+        ToolError::Parse { message: message.into(), line: None, column: None }
+    }
+
+    /// Parse error from serde_json, keeping its 1-based line/column.
+    pub fn json(context: &str, err: &serde_json::Error) -> Self {
+        ToolError::Parse {
+            message: format!("{context}: {err}"),
+            line: Some(err.line()),
+            column: Some(err.column()),
+        }
+    }
+
+    /// Parse error from serde_yaml, keeping its 1-based line/column when known.
+    pub fn yaml(context: &str, err: &serde_yaml::Error) -> Self {
+        let location = err.location();
+        ToolError::Parse {
+            message: format!("{context}: {err}"),
+            line: location.as_ref().map(|l| l.line()),
+            column: location.as_ref().map(|l| l.column()),
+        }
+    }
+
+    /// Parse error from quick-xml's deserializer. It carries no position, so
+    /// `input` is re-read to find where the markup breaks; errors in well-formed
+    /// XML (e.g. an unexpected shape) have none.
+    pub fn xml(context: &str, input: &str, err: &quick_xml::DeError) -> Self {
+        let mut reader = quick_xml::Reader::from_str(input);
+        let offset = loop {
+            match reader.read_event() {
+                Ok(quick_xml::events::Event::Eof) => break None,
+                Ok(_) => {}
+                Err(_) => break Some(reader.error_position() as usize),
+            }
+        };
+        let (line, column) = offset.map(|o| line_column(input, o)).unzip();
+        ToolError::Parse { message: format!("{context}: {err}"), line, column }
+    }
+
+    /// Parse error from the csv reader, keeping the 1-based line of the bad
+    /// record (csv reports no column).
+    pub fn csv(context: &str, err: &csv::Error) -> Self {
+        ToolError::Parse {
+            message: format!("{context}: {err}"),
+            line: err.position().map(|p| p.line() as usize),
+            column: None,
+        }
+    }
+
+    pub fn unsupported(domain: &str, action: &str) -> Self {
+        ToolError::UnsupportedAction { domain: domain.to_string(), action: action.to_string() }
+    }
+
+    /// Same error with `context: ` prepended to its message; code and details are kept.
+    pub fn context(self, context: &str) -> Self {
+        match self {
+            ToolError::InvalidPayload { message, field } => {
+                ToolError::InvalidPayload { message: format!("{context}: {message}"), field }
+            }
+            ToolError::NotFound(m) => ToolError::NotFound(format!("{context}: {m}")),
+            ToolError::PermissionDenied(m) => ToolError::PermissionDenied(format!("{context}: {m}")),
+            ToolError::Parse { message, line, column } => {
+                ToolError::Parse { message: format!("{context}: {message}"), line, column }
+            }
+            ToolError::Io(m) => ToolError::Io(format!("{context}: {m}")),
+            ToolError::Execution(m) => ToolError::Execution(format!("{context}: {m}")),
+            other @ (ToolError::UnsupportedAction { .. } | ToolError::Cancelled) => other,
+        }
+    }
+
+    /// Classify an I/O error by its kind; `context` prefixes the message.
+    pub fn io(context: &str, err: io::Error) -> Self {
+        let message = format!("{context}: {err}");
+        match err.kind() {
+            io::ErrorKind::NotFound => ToolError::NotFound(message),
+            io::ErrorKind::PermissionDenied => ToolError::PermissionDenied(message),
+            _ => ToolError::Io(message),
+        }
+    }
+}
+
+/// 1-based line and column (in characters) of byte `offset` in `text`.
+fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = text.get(..offset.min(text.len())).unwrap_or(text);
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (before.matches('\n').count() + 1, before[line_start..].chars().count() + 1)
+}
+
+impl fmt::Display for ToolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message())
+    }
+}
+
+impl std::error::Error for ToolError {}
+
+// Untyped helper errors (db, formatting) still flow through `?` as generic failures.
+impl From<String> for ToolError {
+    fn from(message: String) -> Self {
+        ToolError::Execution(message)
+    }
+}
+
+impl From<&str> for ToolError {
+    fn from(message: &str) -> Self {
+        ToolError::Execution(message.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_error_keeps_position() {
+        let err = serde_json::from_str::<Value>("{\n  \"a\": }").expect_err("should fail");
+        let tool_err = ToolError::json("invalid json", &err);
+        assert_eq!(tool_err.code(), "PARSE_ERROR");
+        let details = tool_err.details().expect("details");
+        assert_eq!(details["line"], 2);
+        assert!(details["column"].as_u64().unwrap() > 0);
+    }
+
+    #[test]
+    fn xml_csv_and_yaml_errors_keep_position() {
+        let input = "<root>\n  <a>1</a>\n  <b>2</c>\n</root>";
+        let err = quick_xml::de::from_str::<Value>(input).expect_err("should fail");
+        let details = ToolError::xml("invalid xml", input, &err).details().expect("details");
+        assert_eq!(details["line"], 3);
+        assert!(details["column"].as_u64().unwrap() > 1);
+
+        let mut rdr = csv::Reader::from_reader("a,b\n1,2\n3,4,5\n".as_bytes());
+        let err = rdr.records().find_map(Result::err).expect("should fail");
+        let tool_err = ToolError::csv("csv record failed", &err);
+        assert_eq!(tool_err.code(), "PARSE_ERROR");
+        assert_eq!(tool_err.details().expect("details")["line"], 3);
+
+        let err = serde_yaml::from_str::<Value>("a: 1\nb: [2\n").expect_err("should fail");
+        let details = ToolError::yaml("invalid yaml", &err).details().expect("details");
+        assert!(details["line"].as_u64().unwrap() >= 2);
+    }
+
+    #[test]
+    fn io_error_is_classified() {
+        let err = io::Error::new(io::ErrorKind::PermissionDenied, "denied");
+        assert_eq!(ToolError::io("write hosts failed", err).code(), "PERMISSION_DENIED");
+        let err = io::Error::new(io::ErrorKind::NotFound, "missing");
+        assert_eq!(ToolError::io("read failed", err).code(), "NOT_FOUND");
+    }
+
+    #[test]
+    fn plain_string_falls_back_to_execution_failed() {
+        let err: ToolError = "boom".into();
+        assert_eq!(err.code(), "TOOL_EXECUTION_FAILED");
+        assert_eq!(err.details(), None);
+    }
+}
@@ -0,0 +1,19 @@
+use serde_json::Value;
+use std::sync::OnceLock;
+
+/// Receives `(event name, payload)` pairs raised by tools. The desktop app
+/// forwards them as Tauri events; without a sink, events are dropped.
+pub type EventSink = Box<dyn Fn(&str, &Value) + Send + Sync>;
+
+static SINK: OnceLock<EventSink> = OnceLock::new();
+
+/// Install the process-wide event sink. Only the first call takes effect.
+pub fn set_sink(sink: EventSink) {
+    let _ = SINK.set(sink);
+}
+
+pub fn emit(event: &str, payload: &Value) {
+    if let Some(sink) = SINK.get() {
+        sink(event, payload);
+    }
+}
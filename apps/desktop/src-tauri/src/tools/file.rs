@@ -3,43 +3,117 @@ use std::fs::{self, File};
 use std::io::{Read, Write};
 use std::path::PathBuf;
 
-pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
+use super::error::{ToolError, ToolResult};
+use super::jobs::report_progress;
+use super::registry::{ActionSpec, DomainSpec, FieldSpec};
+
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "file",
+    description: "File split, merge and write",
+    execute,
+    actions: &[
+        ActionSpec::new(
+            "split",
+            "Split a file into numbered parts",
+            &[
+                FieldSpec::required("sourcePath", "string", "file to split"),
+                FieldSpec::required("outputDir", "string", "directory for the parts"),
+                FieldSpec::optional("chunkSizeMb", "integer", "part size in MB, default 100"),
+            ],
+            "object: { chunkCount, outputDir, totalBytes }",
+        ),
+        ActionSpec::new(
+            "merge",
+            "Concatenate parts into one file",
+            &[
+                FieldSpec::required("parts", "array", "part file paths in order"),
+                FieldSpec::required("outputPath", "string", "merged file path"),
+            ],
+            "object: { outputPath, totalBytes }",
+        ),
+        ActionSpec::new(
+            "write_text",
+            "Write text to a file",
+            &[
+                FieldSpec::required("path", "string", "target file path"),
+                FieldSpec::required("content", "string", "file content"),
+            ],
+            "object: { path }",
+        ),
+    ],
+};
+
+/// Read/write buffer for split and merge; progress is reported once per buffer.
+const COPY_BUFFER_SIZE: usize = 1024 * 1024;
+
+pub fn execute(action: &str, payload: &Value) -> ToolResult {
     match action {
         "split" => file_split(payload),
         "merge" => file_merge(payload),
         "write_text" => write_text(payload),
-        _ => Err(format!("unsupported file action: {action}")),
+        _ => Err(ToolError::unsupported("file", action)),
     }
 }
 
-fn file_split(payload: &Value) -> Result<Value, String> {
+fn file_split(payload: &Value) -> ToolResult {
     let source_path = PathBuf::from(payload["sourcePath"].as_str().unwrap_or_default());
     let output_dir = PathBuf::from(payload["outputDir"].as_str().unwrap_or_default());
     let chunk_mb = payload["chunkSizeMb"].as_u64().unwrap_or(100) as usize;
     if !source_path.exists() {
-        return Err("source file not found".into());
+        return Err(ToolError::not_found("source file not found"));
     }
     fs::create_dir_all(&output_dir).map_err(|e| format!("create output dir failed: {e}"))?;
-    let metadata = fs::metadata(&source_path).map_err(|e| format!("stat source failed: {e}"))?;
+    let metadata = fs::metadata(&source_path).map_err(|e| ToolError::io("stat source failed", e))?;
     let chunk_size = chunk_mb * 1024 * 1024;
     let total = metadata.len() as usize;
-    let mut reader = File::open(&source_path).map_err(|e| format!("open source failed: {e}"))?;
+    let mut reader = File::open(&source_path).map_err(|e| ToolError::io("open source failed", e))?;
     let mut idx = 0usize;
     let filename = source_path
         .file_name()
         .and_then(|x| x.to_str())
         .ok_or("invalid source filename".to_string())?;
-    loop {
-        let mut buf = vec![0u8; chunk_size];
-        let n = reader.read(&mut buf).map_err(|e| format!("read source failed: {e}"))?;
-        if n == 0 {
-            break;
+    // Stream through a small buffer so multi-GB parts never sit in memory.
+    let mut buf = vec![0u8; COPY_BUFFER_SIZE.min(chunk_size.max(1))];
+    let mut processed = 0usize;
+    let mut parts = Vec::new();
+    let result = (|| -> ToolResult<()> {
+        loop {
+            let part_name = format!("{filename}.part{:04}", idx + 1);
+            let part_path = output_dir.join(&part_name);
+            let mut writer = File::create(&part_path).map_err(|e| ToolError::io("write part failed", e))?;
+            parts.push(part_path.clone());
+            let mut written = 0usize;
+            while written < chunk_size {
+                let want = buf.len().min(chunk_size - written);
+                let n = reader.read(&mut buf[..want]).map_err(|e| format!("read source failed: {e}"))?;
+                if n == 0 {
+                    break;
+                }
+                writer.write_all(&buf[..n]).map_err(|e| format!("write part failed: {e}"))?;
+                written += n;
+                processed += n;
+                report_progress(json!({
+                    "step": "split",
+                    "bytesProcessed": processed,
+                    "totalBytes": total,
+                    "partsWritten": idx,
+                    "currentPart": part_name,
+                }))?;
+            }
+            if written == 0 {
+                drop(writer);
+                let _ = fs::remove_file(&part_path);
+                return Ok(());
+            }
+            idx += 1;
         }
-        buf.truncate(n);
-        let part_name = format!("{filename}.part{:04}", idx + 1);
-        let part_path = output_dir.join(&part_name);
-        fs::write(&part_path, &buf).map_err(|e| format!("write part failed: {e}"))?;
-        idx += 1;
+    })();
+    // A cancelled or failed split leaves no parts behind.
+    if let Err(err) = result {
+        for part in &parts {
+            let _ = fs::remove_file(part);
+        }
+        return Err(err);
     }
     Ok(json!({
       "chunkCount": idx,
@@ -48,23 +122,55 @@ fn file_split(payload: &Value) -> Result<Value, String> {
     }))
 }
 
-fn file_merge(payload: &Value) -> Result<Value, String> {
+fn file_merge(payload: &Value) -> ToolResult {
     let parts = payload["parts"]
         .as_array()
-        .ok_or("parts should be array".to_string())?;
+        .ok_or_else(|| ToolError::invalid_field("parts", "parts should be array"))?;
     let output_path = PathBuf::from(payload["outputPath"].as_str().unwrap_or_default());
     if let Some(parent) = output_path.parent() {
         fs::create_dir_all(parent).map_err(|e| format!("create output parent failed: {e}"))?;
     }
-    let mut writer = File::create(&output_path).map_err(|e| format!("create output failed: {e}"))?;
+    let mut writer = File::create(&output_path).map_err(|e| ToolError::io("create output failed", e))?;
+    let part_paths: Vec<PathBuf> = parts
+        .iter()
+        .map(|p| PathBuf::from(p.as_str().unwrap_or_default()))
+        .collect();
+    let expected: u64 = part_paths
+        .iter()
+        .filter_map(|p| fs::metadata(p).ok())
+        .map(|m| m.len())
+        .sum();
+    let mut buf = vec![0u8; COPY_BUFFER_SIZE];
     let mut total_bytes = 0usize;
-    for p in parts {
-        let part_path = PathBuf::from(p.as_str().unwrap_or_default());
-        let bytes = fs::read(&part_path).map_err(|e| format!("read part failed: {e}"))?;
-        total_bytes += bytes.len();
-        writer
-            .write_all(&bytes)
-            .map_err(|e| format!("write output failed: {e}"))?;
+    let result = (|| -> ToolResult<()> {
+        for (idx, part_path) in part_paths.iter().enumerate() {
+            let mut reader = File::open(part_path).map_err(|e| ToolError::io("read part failed", e))?;
+            loop {
+                let n = reader.read(&mut buf).map_err(|e| format!("read part failed: {e}"))?;
+                if n == 0 {
+                    break;
+                }
+                writer
+                    .write_all(&buf[..n])
+                    .map_err(|e| format!("write output failed: {e}"))?;
+                total_bytes += n;
+                report_progress(json!({
+                    "step": "merge",
+                    "bytesProcessed": total_bytes,
+                    "totalBytes": expected,
+                    "partsMerged": idx,
+                    "totalParts": part_paths.len(),
+                    "currentPart": part_path.to_string_lossy(),
+                }))?;
+            }
+        }
+        Ok(())
+    })();
+    // Don't leave a truncated merge behind on cancel or error.
+    if let Err(err) = result {
+        drop(writer);
+        let _ = fs::remove_file(&output_path);
+        return Err(err);
     }
     Ok(json!({
       "outputPath": output_path.to_string_lossy().to_string(),
@@ -72,7 +178,7 @@ fn file_merge(payload: &Value) -> Result<Value, String> {
     }))
 }
 
-fn write_text(payload: &Value) -> Result<Value, String> {
+fn write_text(payload: &Value) -> ToolResult {
     let path = payload["path"]
         .as_str()
         .ok_or("缺少 path 参数")?;
@@ -1,10 +1,28 @@
 use serde_json::{json, Value};
 
-pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
+use super::error::{ToolError, ToolResult};
+use super::registry::{ActionSpec, DomainSpec, FieldSpec};
+
+const INPUT: &[FieldSpec] = &[FieldSpec::required("input", "string", "source text")];
+
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "format",
+    description: "Code and document formatters",
+    execute,
+    actions: &[
+        ActionSpec::new("json", "Pretty-print JSON", INPUT, "string: formatted text"),
+        ActionSpec::new("xml", "Pass XML through unchanged", INPUT, "string: text"),
+        ActionSpec::new("html", "Pass HTML through unchanged", INPUT, "string: text"),
+        ActionSpec::new("java", "Trim trailing whitespace from Java source", INPUT, "string: text"),
+        ActionSpec::new("sql", "Pass SQL through unchanged", INPUT, "string: text"),
+    ],
+};
+
+pub fn execute(action: &str, payload: &Value) -> ToolResult {
     match action {
         "json" => {
             let input = payload["input"].as_str().unwrap_or_default();
-            let v: Value = serde_json::from_str(input).map_err(|e| format!("invalid json: {e}"))?;
+            let v: Value = serde_json::from_str(input).map_err(|e| ToolError::json("invalid json", &e))?;
             Ok(json!(serde_json::to_string_pretty(&v).unwrap_or_else(|_| input.to_string())))
         }
         "xml" => Ok(json!(payload["input"].as_str().unwrap_or_default().to_string())),
@@ -19,6 +37,6 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
                 .join("\n")
         )),
         "sql" => Ok(json!(payload["input"].as_str().unwrap_or_default().to_string())),
-        _ => Err(format!("unsupported format action: {action}")),
+        _ => Err(ToolError::unsupported("format", action)),
     }
 }
@@ -2,7 +2,32 @@ use rand::Rng;
 use serde_json::{json, Value};
 use uuid::Uuid;
 
-pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
+use super::error::{ToolError, ToolResult};
+use super::registry::{ActionSpec, DomainSpec, FieldSpec};
+
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "gen",
+    description: "Random identifier and password generators",
+    execute,
+    actions: &[
+        ActionSpec::new("uuid", "Random v4 UUID", &[], "string: UUID"),
+        ActionSpec::new("guid", "Random v4 UUID in uppercase braces", &[], "string: GUID"),
+        ActionSpec::new(
+            "password",
+            "Random password from the selected character classes",
+            &[
+                FieldSpec::optional("length", "integer", "password length, default 16"),
+                FieldSpec::optional("uppercase", "boolean", "include A-Z, default true"),
+                FieldSpec::optional("lowercase", "boolean", "include a-z, default true"),
+                FieldSpec::optional("numbers", "boolean", "include 0-9, default true"),
+                FieldSpec::optional("symbols", "boolean", "include punctuation, default false"),
+            ],
+            "string: password",
+        ),
+    ],
+};
+
+pub fn execute(action: &str, payload: &Value) -> ToolResult {
     match action {
         "uuid" => Ok(json!(Uuid::new_v4().to_string())),
         "guid" => Ok(json!(format!("{{{}}}", Uuid::new_v4().to_string().to_uppercase()))),
@@ -26,7 +51,7 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
                 chars.push_str("!@#$%^&*()-_=+[]{};:,.<>?");
             }
             if chars.is_empty() {
-                return Err("password charset is empty".into());
+                return Err(ToolError::invalid("password charset is empty"));
             }
             let mut rng = rand::thread_rng();
             let bytes = chars.as_bytes();
@@ -35,6 +60,6 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
                 .collect::<String>();
             Ok(json!(out))
         }
-        _ => Err(format!("unsupported gen action: {action}")),
+        _ => Err(ToolError::unsupported("gen", action)),
     }
 }
@@ -1,10 +1,17 @@
-use rusqlite::{Connection, params};
+use rusqlite::Connection;
 use serde_json::Value;
 use std::fs;
-use std::path::PathBuf;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
 
 /// Fixed base directory: ~/.lazycat (always exists, never changes)
 pub fn get_base_dir() -> Result<PathBuf, String> {
+    #[cfg(test)]
+    let home = test_home().clone();
+    #[cfg(not(test))]
     let home = dirs::home_dir().ok_or("home dir not found".to_string())?;
     let p = home.join(".lazycat");
     fs::create_dir_all(&p).map_err(|e| format!("create base dir failed: {e}"))?;
@@ -37,209 +44,255 @@ pub fn get_data_dir() -> Result<PathBuf, String> {
     Ok(base)
 }
 
-fn get_schema_version(conn: &Connection) -> i64 {
-    // Check if schema_version table exists
-    let exists: bool = conn
-        .query_row(
-            "SELECT count(*) > 0 FROM sqlite_master WHERE type='table' AND name='schema_version'",
-            [],
-            |row| row.get(0),
-        )
-        .unwrap_or(false);
-    if !exists {
-        return 0;
+/// Copy `src` into `dst` (created if missing), recursing into subdirectories.
+pub fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<(), String> {
+    fs::create_dir_all(dst)
+        .map_err(|e| format!("create dir {:?} failed: {e}", dst))?;
+    let entries = fs::read_dir(src)
+        .map_err(|e| format!("read dir {:?} failed: {e}", src))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("read entry failed: {e}"))?;
+        let file_type = entry.file_type().map_err(|e| format!("get file type failed: {e}"))?;
+        let dst_path = dst.join(entry.file_name());
+        if file_type.is_dir() {
+            copy_dir_recursive(&entry.path(), &dst_path)?;
+        } else {
+            fs::copy(entry.path(), &dst_path)
+                .map_err(|e| format!("copy file {:?} failed: {e}", entry.path()))?;
+        }
     }
-    conn.query_row("SELECT COALESCE(MAX(version), 0) FROM schema_version", [], |row| {
-        row.get(0)
-    })
-    .unwrap_or(0)
+    Ok(())
 }
 
-fn set_schema_version(conn: &Connection, version: i64) -> Result<(), String> {
-    conn.execute_batch("CREATE TABLE IF NOT EXISTS schema_version (version INTEGER NOT NULL);")
-        .map_err(|e| format!("create schema_version table failed: {e}"))?;
-    conn.execute("DELETE FROM schema_version", [])
-        .map_err(|e| format!("clear schema_version failed: {e}"))?;
-    conn.execute("INSERT INTO schema_version (version) VALUES (?1)", params![version])
-        .map_err(|e| format!("set schema_version failed: {e}"))?;
-    Ok(())
+/// The main database file in the current data directory.
+pub fn db_path() -> Result<PathBuf, String> {
+    Ok(get_data_dir()?.join("lazycat.sqlite"))
+}
+
+/// Idle connections kept for reuse; more than this are closed when returned.
+const MAX_IDLE: usize = 4;
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Connections to the database of one data directory. `generation` changes
+/// whenever the pool is closed, so connections checked out before that are
+/// dropped instead of returned.
+struct Pool {
+    path: PathBuf,
+    generation: u64,
+    idle: Vec<Connection>,
 }
 
-fn run_migrations(conn: &Connection) -> Result<(), String> {
-    let current = get_schema_version(conn);
-
-    // Migration 1: hosts_profiles table (already exists via CREATE IF NOT EXISTS)
-    if current < 1 {
-        conn.execute_batch(
-            "CREATE TABLE IF NOT EXISTS hosts_profiles (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL UNIQUE,
-                content TEXT NOT NULL,
-                enabled INTEGER NOT NULL DEFAULT 0,
-                updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
-            );"
-        )
-        .map_err(|e| format!("migration 1 failed: {e}"))?;
-        set_schema_version(conn, 1)?;
+static POOL: Mutex<Option<Pool>> = Mutex::new(None);
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// How long [`exclusive_db`] waits for checked-out connections.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Connections checked out, and whether someone holds [`ExclusiveDb`].
+struct Gate {
+    checked_out: usize,
+    exclusive: bool,
+}
+
+static GATE: Mutex<Gate> = Mutex::new(Gate { checked_out: 0, exclusive: false });
+static GATE_CHANGED: Condvar = Condvar::new();
+
+fn gate() -> MutexGuard<'static, Gate> {
+    GATE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Counts one checked-out connection until dropped.
+struct Permit;
+
+impl Permit {
+    /// Waits while the database is held exclusively.
+    fn acquire() -> Permit {
+        let mut gate = GATE_CHANGED
+            .wait_while(gate(), |g| g.exclusive)
+            .unwrap_or_else(|e| e.into_inner());
+        gate.checked_out += 1;
+        Permit
     }
+}
 
-    // Migration 2: user_settings table
-    if current < 2 {
-        conn.execute_batch(
-            "CREATE TABLE IF NOT EXISTS user_settings (
-                key TEXT PRIMARY KEY,
-                value TEXT NOT NULL,
-                updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
-            );"
-        )
-        .map_err(|e| format!("migration 2 failed: {e}"))?;
-        set_schema_version(conn, 2)?;
+impl Drop for Permit {
+    fn drop(&mut self) {
+        gate().checked_out -= 1;
+        GATE_CHANGED.notify_all();
     }
+}
+
+/// A pooled connection; dereferences to `Connection` and goes back to the
+/// pool when dropped.
+pub struct DbConn {
+    conn: Option<Connection>,
+    generation: u64,
+    _permit: Permit,
+}
 
-    // Migration 3: hosts_profiles add sort_order column
-    if current < 3 {
-        conn.execute_batch(
-            "ALTER TABLE hosts_profiles ADD COLUMN sort_order INTEGER NOT NULL DEFAULT 0;"
-        )
-        .map_err(|e| format!("migration 3 failed: {e}"))?;
-        // Initialize sort_order based on existing id order
-        conn.execute_batch(
-            "UPDATE hosts_profiles SET sort_order = id;"
-        )
-        .map_err(|e| format!("migration 3 init sort_order failed: {e}"))?;
-        set_schema_version(conn, 3)?;
+impl Deref for DbConn {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().expect("connection present until drop")
     }
+}
 
-    // Migration 4: snippet_folders table
-    if current < 4 {
-        conn.execute_batch(
-            "CREATE TABLE IF NOT EXISTS snippet_folders (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL,
-                parent_id INTEGER DEFAULT NULL,
-                sort_order INTEGER NOT NULL DEFAULT 0,
-                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
-                FOREIGN KEY (parent_id) REFERENCES snippet_folders(id) ON DELETE CASCADE
-            );"
-        )
-        .map_err(|e| format!("migration 4 failed: {e}"))?;
-        set_schema_version(conn, 4)?;
+impl DerefMut for DbConn {
+    fn deref_mut(&mut self) -> &mut Connection {
+        self.conn.as_mut().expect("connection present until drop")
     }
+}
 
-    // Migration 5: snippets table
-    if current < 5 {
-        conn.execute_batch(
-            "CREATE TABLE IF NOT EXISTS snippets (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                title TEXT NOT NULL,
-                description TEXT NOT NULL DEFAULT '',
-                folder_id INTEGER DEFAULT NULL,
-                is_favorite INTEGER NOT NULL DEFAULT 0,
-                sort_order INTEGER NOT NULL DEFAULT 0,
-                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
-                updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
-                FOREIGN KEY (folder_id) REFERENCES snippet_folders(id) ON DELETE SET NULL
-            );"
-        )
-        .map_err(|e| format!("migration 5 failed: {e}"))?;
-        set_schema_version(conn, 5)?;
+impl Drop for DbConn {
+    fn drop(&mut self) {
+        let Some(conn) = self.conn.take() else { return };
+        if let Ok(mut pool) = POOL.lock() {
+            if let Some(pool) = pool.as_mut().filter(|p| p.generation == self.generation) {
+                if pool.idle.len() < MAX_IDLE && conn.is_autocommit() {
+                    pool.idle.push(conn);
+                }
+            }
+        }
     }
+}
+
+/// Opens the database keyed (see `encryption::open`) in WAL mode, so readers
+/// don't block the writer, with a busy timeout for concurrent writers.
+fn open_configured(path: &Path) -> Result<Connection, String> {
+    let conn = super::encryption::open(path)?;
+    conn.busy_timeout(BUSY_TIMEOUT)
+        .map_err(|e| format!("set busy timeout failed: {e}"))?;
+    conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))
+        .map_err(|e| format!("enable WAL failed: {e}"))?;
+    conn.pragma_update(None, "synchronous", "NORMAL")
+        .map_err(|e| format!("set synchronous failed: {e}"))?;
+    Ok(conn)
+}
+
+/// A connection to the main database. The first call after startup (or
+/// after [`close_db`]) resolves the data directory and runs migrations;
+/// later calls reuse idle connections.
+pub fn db_conn() -> Result<DbConn, String> {
+    super::migrations::ensure_not_rolled_back()?;
+    let permit = Permit::acquire();
+    let mut pool = POOL.lock().map_err(|_| "database pool poisoned".to_string())?;
+    let (path, generation) = match pool.as_mut() {
+        Some(p) => {
+            if let Some(conn) = p.idle.pop() {
+                return Ok(DbConn { conn: Some(conn), generation: p.generation, _permit: permit });
+            }
+            (p.path.clone(), p.generation)
+        }
+        None => {
+            let path = db_path()?;
+            let conn = open_configured(&path)?;
+            super::migrations::migrate(&conn)?;
+            let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+            *pool = Some(Pool { path, generation, idle: Vec::new() });
+            return Ok(DbConn { conn: Some(conn), generation, _permit: permit });
+        }
+    };
+    // Opening derives the SQLCipher key, which is slow; don't hold the lock.
+    drop(pool);
+    Ok(DbConn { conn: Some(open_configured(&path)?), generation, _permit: permit })
+}
 
-    // Migration 6: snippet_fragments table (multi-tab code)
-    if current < 6 {
-        conn.execute_batch(
-            "CREATE TABLE IF NOT EXISTS snippet_fragments (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                snippet_id INTEGER NOT NULL,
-                label TEXT NOT NULL DEFAULT 'main',
-                language TEXT NOT NULL DEFAULT 'plaintext',
-                code TEXT NOT NULL DEFAULT '',
-                sort_order INTEGER NOT NULL DEFAULT 0,
-                FOREIGN KEY (snippet_id) REFERENCES snippets(id) ON DELETE CASCADE
-            );"
-        )
-        .map_err(|e| format!("migration 6 failed: {e}"))?;
-        set_schema_version(conn, 6)?;
+/// Closes idle connections and forgets the data directory. Call it after
+/// anything that replaces the database file, changes its key or moves the
+/// data directory; connections still checked out are closed when returned.
+pub fn close_db() {
+    GENERATION.fetch_add(1, Ordering::SeqCst);
+    if let Ok(mut pool) = POOL.lock() {
+        *pool = None;
     }
+}
+
+/// Sole use of the database file, from [`exclusive_db`] until dropped.
+pub struct ExclusiveDb(());
 
-    // Migration 7: snippet_tags table
-    if current < 7 {
-        conn.execute_batch(
-            "CREATE TABLE IF NOT EXISTS snippet_tags (
-                snippet_id INTEGER NOT NULL,
-                tag TEXT NOT NULL,
-                PRIMARY KEY (snippet_id, tag),
-                FOREIGN KEY (snippet_id) REFERENCES snippets(id) ON DELETE CASCADE
-            );
-            CREATE INDEX IF NOT EXISTS idx_snippet_tags_tag ON snippet_tags(tag);"
-        )
-        .map_err(|e| format!("migration 7 failed: {e}"))?;
-        set_schema_version(conn, 7)?;
+impl Drop for ExclusiveDb {
+    fn drop(&mut self) {
+        gate().exclusive = false;
+        GATE_CHANGED.notify_all();
     }
+}
 
-    // Migration 8: snippets workspace v2 schema
-    if current < 8 {
-        conn.execute_batch(
-            "CREATE TABLE IF NOT EXISTS snippet_folders_v2 (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL,
-                parent_id INTEGER DEFAULT NULL,
-                sort_order INTEGER NOT NULL DEFAULT 0,
-                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
-                FOREIGN KEY (parent_id) REFERENCES snippet_folders_v2(id) ON DELETE CASCADE
-            );
-            CREATE TABLE IF NOT EXISTS snippet_entries (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                title TEXT NOT NULL,
-                description TEXT NOT NULL DEFAULT '',
-                folder_id INTEGER DEFAULT NULL,
-                is_favorite INTEGER NOT NULL DEFAULT 0,
-                primary_language TEXT NOT NULL DEFAULT 'plaintext',
-                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
-                updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
-                last_used_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
-                use_count INTEGER NOT NULL DEFAULT 0,
-                FOREIGN KEY (folder_id) REFERENCES snippet_folders_v2(id) ON DELETE SET NULL
-            );
-            CREATE INDEX IF NOT EXISTS idx_entries_last_used_at ON snippet_entries(last_used_at DESC);
-            CREATE INDEX IF NOT EXISTS idx_entries_updated_at ON snippet_entries(updated_at DESC);
-            CREATE TABLE IF NOT EXISTS snippet_fragments_v2 (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                entry_id INTEGER NOT NULL,
-                label TEXT NOT NULL DEFAULT 'main',
-                language TEXT NOT NULL DEFAULT 'plaintext',
-                code TEXT NOT NULL DEFAULT '',
-                sort_order INTEGER NOT NULL DEFAULT 0,
-                FOREIGN KEY (entry_id) REFERENCES snippet_entries(id) ON DELETE CASCADE
-            );
-            CREATE INDEX IF NOT EXISTS idx_fragments_v2_entry_sort ON snippet_fragments_v2(entry_id, sort_order);
-            CREATE TABLE IF NOT EXISTS snippet_entry_tags (
-                entry_id INTEGER NOT NULL,
-                tag TEXT NOT NULL,
-                PRIMARY KEY (entry_id, tag),
-                FOREIGN KEY (entry_id) REFERENCES snippet_entries(id) ON DELETE CASCADE
-            );
-            CREATE INDEX IF NOT EXISTS idx_entry_tags_tag ON snippet_entry_tags(tag);"
-        )
-        .map_err(|e| format!("migration 8 failed: {e}"))?;
-        let _ = conn.execute_batch(
-            "CREATE VIRTUAL TABLE IF NOT EXISTS snippet_fts USING fts5(
-                entry_id UNINDEXED,
-                title,
-                description,
-                tags_text,
-                code_text
-            );"
-        );
-        set_schema_version(conn, 8)?;
+/// Blocks new connections, waits for the checked-out ones to be returned and
+/// closes the pool, so the file can be re-keyed or replaced without another
+/// thread (a job, the backup scheduler, the API) still writing to the old
+/// one. Must not be called while holding a `DbConn`.
+pub fn exclusive_db() -> Result<ExclusiveDb, String> {
+    let deadline = Instant::now() + DRAIN_TIMEOUT;
+    let mut gate = GATE_CHANGED
+        .wait_while(gate(), |g| g.exclusive)
+        .unwrap_or_else(|e| e.into_inner());
+    gate.exclusive = true;
+    while gate.checked_out > 0 {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            gate.exclusive = false;
+            GATE_CHANGED.notify_all();
+            return Err("the database is still in use, try again later".to_string());
+        }
+        gate = GATE_CHANGED.wait_timeout(gate, left).unwrap_or_else(|e| e.into_inner()).0;
     }
+    drop(gate);
+    close_db();
+    Ok(ExclusiveDb(()))
+}
 
-    Ok(())
+/// Stands in for the home directory in test builds: a fresh temporary
+/// directory per test process, so tests never open the real data directory.
+#[cfg(test)]
+fn test_home() -> &'static PathBuf {
+    static HOME: std::sync::OnceLock<PathBuf> = std::sync::OnceLock::new();
+    HOME.get_or_init(|| {
+        let home = std::env::temp_dir().join(format!("lazycat-test-home-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&home).expect("create test home");
+        home
+    })
 }
 
-pub fn db_conn() -> Result<Connection, String> {
-    let db_path = get_data_dir()?.join("lazycat.sqlite");
-    let conn = Connection::open(db_path).map_err(|e| format!("open db failed: {e}"))?;
-    run_migrations(&conn)?;
-    Ok(conn)
+/// The data directory of test builds, created if needed. Tests that reach
+/// `db_conn` call this first to make that dependency explicit.
+#[cfg(test)]
+pub fn use_test_data_dir() -> PathBuf {
+    get_base_dir().expect("test data dir")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exclusive_access_waits_for_checked_out_connections() {
+        use_test_data_dir();
+        let conn = db_conn().expect("db");
+        let waiter = std::thread::spawn(|| {
+            let started = Instant::now();
+            let exclusive = exclusive_db().expect("exclusive");
+            let waited = started.elapsed();
+            drop(exclusive);
+            waited
+        });
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(!waiter.is_finished());
+        drop(conn);
+        assert!(waiter.join().expect("join") >= Duration::from_millis(200));
+        db_conn().expect("usable again");
+    }
+
+    #[test]
+    fn opens_in_wal_mode() {
+        let path = std::env::temp_dir().join(format!("lazycat-wal-{}.sqlite", uuid::Uuid::new_v4()));
+        let conn = open_configured(&path).expect("open");
+        let mode: String = conn.query_row("PRAGMA journal_mode", [], |r| r.get(0)).expect("mode");
+        assert_eq!(mode, "wal");
+        conn.execute_batch("CREATE TABLE t (v); INSERT INTO t VALUES (1);").expect("write");
+        drop(conn);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = fs::remove_file(format!("{}{suffix}", path.display()));
+        }
+    }
 }
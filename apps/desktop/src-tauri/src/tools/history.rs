@@ -0,0 +1,366 @@
+//! History of `tool_execute` calls, with search, pinning and replay.
+//!
+//! Recording is controlled by settings: `history.enabled` (default true),
+//! `history.excludedDomains` (comma list, default `crypto,jwt`) and
+//! `history.limit` (unpinned entries kept, default 1000). A job or pipeline
+//! call is skipped when any step it wraps is in an excluded domain.
+
+use rusqlite::{params, params_from_iter, Connection};
+use serde_json::{json, Value};
+use std::time::Instant;
+
+use super::error::{ToolError, ToolResult};
+use super::helpers::db_conn;
+use super::registry::{ActionSpec, DomainSpec, FieldSpec};
+
+pub const KEY_ENABLED: &str = "history.enabled";
+pub const KEY_EXCLUDED: &str = "history.excludedDomains";
+pub const KEY_LIMIT: &str = "history.limit";
+/// Sensitive domains that are not recorded unless the user changes the setting.
+pub const DEFAULT_EXCLUDED: &str = "crypto,jwt";
+pub const DEFAULT_LIMIT: i64 = 1000;
+/// Never recorded: history itself (replay/list noise), the API token actions
+/// and encryption, whose payloads carry the master password.
+const ALWAYS_EXCLUDED: &[&str] = &["history", "api", "encryption"];
+/// Payloads larger than this are not stored, so such entries cannot be replayed.
+const MAX_PAYLOAD_BYTES: usize = 256 * 1024;
+const SUMMARY_CHARS: usize = 300;
+
+const ID: FieldSpec = FieldSpec::required("id", "integer", "history entry id");
+const LIMIT: FieldSpec = FieldSpec::optional("limit", "integer", "max entries, default 50");
+const OFFSET: FieldSpec = FieldSpec::optional("offset", "integer", "entries to skip, default 0");
+
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "history",
+    description: "Recorded tool calls: list, search, pin, replay and purge",
+    execute,
+    actions: &[
+        ActionSpec::new(
+            "list",
+            "Recent entries, pinned first",
+            &[
+                LIMIT,
+                OFFSET,
+                FieldSpec::optional("domain", "string", "only this domain"),
+                FieldSpec::optional("pinnedOnly", "boolean", "only pinned entries"),
+            ],
+            "array of { id, domain, action, payload, ok, resultSummary, errorCode, durationMs, pinned, replayable, createdAt }",
+        ),
+        ActionSpec::new(
+            "search",
+            "Entries whose domain, action, payload or result contain the query",
+            &[FieldSpec::required("query", "string", "text to find"), LIMIT, OFFSET],
+            "array of history entry",
+        ),
+        ActionSpec::new(
+            "pin",
+            "Pin or unpin an entry; pinned entries survive pruning and purge",
+            &[ID, FieldSpec::optional("pinned", "boolean", "default true")],
+            "object: { id, pinned }",
+        ),
+        ActionSpec::new("replay", "Run an entry's action again with its payload", &[ID], "object: { entryId, result }"),
+        ActionSpec::new(
+            "purge",
+            "Delete one entry, or all unpinned entries",
+            &[
+                FieldSpec::optional("id", "integer", "delete only this entry"),
+                FieldSpec::optional("includePinned", "boolean", "also delete pinned entries, default false"),
+            ],
+            "object: { deleted }",
+        ),
+    ],
+};
+
+pub fn execute(action: &str, payload: &Value) -> ToolResult {
+    match action {
+        "list" => history_list(payload),
+        "search" => history_search(payload),
+        "pin" => history_pin(payload),
+        "replay" => history_replay(payload),
+        "purge" => history_purge(payload),
+        _ => Err(ToolError::unsupported("history", action)),
+    }
+}
+
+fn setting(conn: &Connection, key: &str) -> Option<String> {
+    conn.query_row("SELECT value FROM user_settings WHERE key = ?1", params![key], |r| r.get(0))
+        .ok()
+}
+
+/// Whether calls to `domain` are recorded under the current settings.
+fn should_record(conn: &Connection, domain: &str) -> bool {
+    if ALWAYS_EXCLUDED.contains(&domain) {
+        return false;
+    }
+    if setting(conn, KEY_ENABLED).is_some_and(|v| v == "false" || v == "0") {
+        return false;
+    }
+    let excluded = setting(conn, KEY_EXCLUDED).unwrap_or_else(|| DEFAULT_EXCLUDED.to_string());
+    !excluded.split(',').any(|d| d.trim() == domain)
+}
+
+/// The domains a call runs, see `pipeline::reached_calls`.
+fn reached_domains(conn: &Connection, domain: &str, action: &str, payload: &Value) -> Vec<String> {
+    let mut calls = Vec::new();
+    super::pipeline::reached_calls(Some(conn), domain, action, payload, &mut calls);
+    calls.into_iter().map(|(domain, _, _)| domain).collect()
+}
+
+fn summarize(outcome: &ToolResult) -> (bool, String, Option<&'static str>) {
+    let (ok, text, code) = match outcome {
+        Ok(Value::String(s)) => (true, s.clone(), None),
+        Ok(v) => (true, v.to_string(), None),
+        Err(err) => (false, err.message(), Some(err.code())),
+    };
+    let mut summary: String = text.chars().take(SUMMARY_CHARS).collect();
+    if summary.len() < text.len() {
+        summary.push('…');
+    }
+    (ok, summary, code)
+}
+
+/// Record one call. Failures are swallowed: history must never break a tool call.
+pub fn record(domain: &str, action: &str, payload: &Value, outcome: &ToolResult, started: Instant) {
+    let _ = try_record(domain, action, payload, outcome, started.elapsed().as_millis() as i64);
+}
+
+fn try_record(domain: &str, action: &str, payload: &Value, outcome: &ToolResult, duration_ms: i64) -> Result<(), String> {
+    let conn = db_conn()?;
+    let domains = reached_domains(&conn, domain, action, payload);
+    if !domains.iter().all(|d| should_record(&conn, d)) {
+        return Ok(());
+    }
+    let mut payload_text = payload.to_string();
+    if payload_text.len() > MAX_PAYLOAD_BYTES {
+        payload_text.clear();
+    }
+    let (ok, summary, code) = summarize(outcome);
+    conn.execute(
+        "INSERT INTO tool_history(domain, action, payload, ok, result_summary, error_code, duration_ms)
+         VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![domain, action, payload_text, ok, summary, code, duration_ms],
+    )
+    .map_err(|e| format!("insert history failed: {e}"))?;
+
+    let limit = setting(&conn, KEY_LIMIT)
+        .and_then(|v| v.parse::<i64>().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(DEFAULT_LIMIT);
+    conn.execute(
+        "DELETE FROM tool_history WHERE pinned = 0 AND id NOT IN (
+            SELECT id FROM tool_history WHERE pinned = 0 ORDER BY id DESC LIMIT ?1
+         )",
+        params![limit],
+    )
+    .map_err(|e| format!("prune history failed: {e}"))?;
+    Ok(())
+}
+
+const SELECT_COLUMNS: &str =
+    "SELECT id, domain, action, payload, ok, result_summary, error_code, duration_ms, pinned, created_at FROM tool_history";
+
+fn row_to_json(row: &rusqlite::Row) -> rusqlite::Result<Value> {
+    let payload: String = row.get(3)?;
+    Ok(json!({
+        "id": row.get::<_, i64>(0)?,
+        "domain": row.get::<_, String>(1)?,
+        "action": row.get::<_, String>(2)?,
+        "payload": serde_json::from_str::<Value>(&payload).unwrap_or(Value::Null),
+        "ok": row.get::<_, bool>(4)?,
+        "resultSummary": row.get::<_, String>(5)?,
+        "errorCode": row.get::<_, Option<String>>(6)?,
+        "durationMs": row.get::<_, i64>(7)?,
+        "pinned": row.get::<_, bool>(8)?,
+        "replayable": !payload.is_empty(),
+        "createdAt": row.get::<_, String>(9)?,
+    }))
+}
+
+fn query_entries(sql: &str, args: Vec<Value>) -> ToolResult {
+    let conn = db_conn()?;
+    let mut stmt = conn.prepare(sql).map_err(|e| format!("prepare query failed: {e}"))?;
+    let args = args.into_iter().map(|v| match v {
+        Value::Number(n) => rusqlite::types::Value::Integer(n.as_i64().unwrap_or_default()),
+        Value::String(s) => rusqlite::types::Value::Text(s),
+        _ => rusqlite::types::Value::Null,
+    });
+    let rows = stmt
+        .query_map(params_from_iter(args), row_to_json)
+        .map_err(|e| format!("query history failed: {e}"))?;
+    let mut list = Vec::new();
+    for row in rows {
+        list.push(row.map_err(|e| e.to_string())?);
+    }
+    Ok(Value::Array(list))
+}
+
+fn page(payload: &Value) -> (i64, i64) {
+    let limit = payload["limit"].as_i64().filter(|n| *n > 0).unwrap_or(50).min(500);
+    let offset = payload["offset"].as_i64().filter(|n| *n > 0).unwrap_or(0);
+    (limit, offset)
+}
+
+fn history_list(payload: &Value) -> ToolResult {
+    let (limit, offset) = page(payload);
+    let mut clauses = Vec::new();
+    let mut args = Vec::new();
+    if let Some(domain) = payload["domain"].as_str().filter(|d| !d.is_empty()) {
+        clauses.push("domain = ?");
+        args.push(json!(domain));
+    }
+    if payload["pinnedOnly"].as_bool().unwrap_or(false) {
+        clauses.push("pinned = 1");
+    }
+    let filter = if clauses.is_empty() { String::new() } else { format!(" WHERE {}", clauses.join(" AND ")) };
+    args.push(json!(limit));
+    args.push(json!(offset));
+    query_entries(&format!("{SELECT_COLUMNS}{filter} ORDER BY pinned DESC, id DESC LIMIT ? OFFSET ?"), args)
+}
+
+fn history_search(payload: &Value) -> ToolResult {
+    let query = payload["query"].as_str().map(str::trim).unwrap_or_default();
+    if query.is_empty() {
+        return Err(ToolError::invalid_field("query", "query is required"));
+    }
+    let (limit, offset) = page(payload);
+    let pattern = format!("%{}%", query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+    query_entries(
+        &format!(
+            "{SELECT_COLUMNS} WHERE domain LIKE ?1 ESCAPE '\\' OR action LIKE ?1 ESCAPE '\\'
+             OR payload LIKE ?1 ESCAPE '\\' OR result_summary LIKE ?1 ESCAPE '\\'
+             ORDER BY pinned DESC, id DESC LIMIT ?2 OFFSET ?3"
+        ),
+        vec![json!(pattern), json!(limit), json!(offset)],
+    )
+}
+
+fn require_id(payload: &Value) -> ToolResult<i64> {
+    payload["id"]
+        .as_i64()
+        .ok_or_else(|| ToolError::invalid_field("id", "id is required"))
+}
+
+fn history_pin(payload: &Value) -> ToolResult {
+    let id = require_id(payload)?;
+    let pinned = payload["pinned"].as_bool().unwrap_or(true);
+    let conn = db_conn()?;
+    let n = conn
+        .execute("UPDATE tool_history SET pinned = ?1 WHERE id = ?2", params![pinned, id])
+        .map_err(|e| format!("pin history entry failed: {e}"))?;
+    if n == 0 {
+        return Err(ToolError::not_found(format!("history entry not found: {id}")));
+    }
+    Ok(json!({ "id": id, "pinned": pinned }))
+}
+
+fn history_replay(payload: &Value) -> ToolResult {
+    let id = require_id(payload)?;
+    let entry = query_entries(&format!("{SELECT_COLUMNS} WHERE id = ?"), vec![json!(id)])?
+        .as_array()
+        .and_then(|list| list.first().cloned())
+        .ok_or_else(|| ToolError::not_found(format!("history entry not found: {id}")))?;
+    if entry["replayable"] != true {
+        return Err(ToolError::invalid_field("id", "payload of this entry was too large to keep"));
+    }
+    let domain = entry["domain"].as_str().unwrap_or_default();
+    let action = entry["action"].as_str().unwrap_or_default();
+    let started = Instant::now();
+    let outcome = super::execute_tool(domain, action, &entry["payload"]);
+    record(domain, action, &entry["payload"], &outcome, started);
+    Ok(json!({ "entryId": id, "result": outcome? }))
+}
+
+fn history_purge(payload: &Value) -> ToolResult {
+    let conn = db_conn()?;
+    let deleted = match payload["id"].as_i64() {
+        Some(id) => conn.execute("DELETE FROM tool_history WHERE id = ?1", params![id]),
+        None if payload["includePinned"].as_bool().unwrap_or(false) => conn.execute("DELETE FROM tool_history", []),
+        None => conn.execute("DELETE FROM tool_history WHERE pinned = 0", []),
+    }
+    .map_err(|e| format!("purge history failed: {e}"))?;
+    Ok(json!({ "deleted": deleted }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory_conn(settings: &[(&str, &str)]) -> Connection {
+        let conn = Connection::open_in_memory().expect("open");
+        conn.execute_batch("CREATE TABLE user_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL);")
+            .expect("schema");
+        for (k, v) in settings {
+            conn.execute("INSERT INTO user_settings(key, value) VALUES(?1, ?2)", params![k, v])
+                .expect("insert");
+        }
+        conn
+    }
+
+    #[test]
+    fn sensitive_domains_are_excluded_by_default() {
+        let conn = memory_conn(&[]);
+        assert!(should_record(&conn, "encode"));
+        assert!(!should_record(&conn, "crypto"));
+        assert!(!should_record(&conn, "jwt"));
+        assert!(!should_record(&conn, "history"));
+        assert!(!should_record(&conn, "encryption"));
+    }
+
+    #[test]
+    fn excluded_domains_are_not_recorded_through_jobs_or_pipelines() {
+        let conn = memory_conn(&[]);
+        conn.execute_batch("CREATE TABLE pipelines (name TEXT PRIMARY KEY, steps TEXT NOT NULL);")
+            .expect("schema");
+        conn.execute(
+            "INSERT INTO pipelines(name, steps) VALUES('sign', ?1)",
+            params![json!([{ "domain": "encode", "action": "base64_encode" }, { "domain": "jwt", "action": "decode" }]).to_string()],
+        )
+        .expect("insert");
+        let records = |domain: &str, action: &str, payload: Value| {
+            reached_domains(&conn, domain, action, &payload).iter().all(|d| should_record(&conn, d))
+        };
+        assert!(records("jobs", "start", json!({ "domain": "encode", "action": "base64_encode" })));
+        assert!(!records("jobs", "start", json!({ "domain": "crypto", "action": "hash", "payload": { "input": "s3cret" } })));
+        let inline = json!({ "steps": [{ "domain": "encode", "action": "base64_encode" }, { "domain": "crypto", "action": "hash" }] });
+        assert!(!records("pipeline", "run", inline.clone()));
+        assert!(!records("pipeline", "save", inline.clone()));
+        assert!(!records("jobs", "start", json!({ "domain": "pipeline", "action": "run", "payload": inline })));
+        assert!(!records("pipeline", "run", json!({ "name": "sign", "input": "token" })));
+    }
+
+    #[test]
+    fn master_password_never_reaches_history() {
+        crate::tools::helpers::use_test_data_dir();
+        let secret = json!({ "password": "history-secret-pw" });
+        let wrapped = json!({ "domain": "encryption", "action": "unlock", "payload": secret });
+        for (domain, action, payload) in [("encryption", "enable", &secret), ("jobs", "start", &wrapped)] {
+            try_record(domain, action, payload, &Ok(json!({ "ok": true })), 1).expect("record");
+        }
+
+        let conn = db_conn().expect("db");
+        let leaked: i64 = conn
+            .query_row("SELECT count(*) FROM tool_history WHERE payload LIKE '%history-secret-pw%'", [], |r| r.get(0))
+            .expect("query history");
+        assert_eq!(leaked, 0);
+    }
+
+    #[test]
+    fn settings_control_recording() {
+        let conn = memory_conn(&[(KEY_EXCLUDED, "encode, format")]);
+        assert!(!should_record(&conn, "format"));
+        assert!(should_record(&conn, "jwt"));
+        let conn = memory_conn(&[(KEY_ENABLED, "false")]);
+        assert!(!should_record(&conn, "encode"));
+    }
+
+    #[test]
+    fn summary_is_truncated() {
+        let long = "x".repeat(SUMMARY_CHARS + 10);
+        let (ok, summary, code) = summarize(&Ok(json!(long)));
+        assert!(ok && code.is_none());
+        assert_eq!(summary.chars().count(), SUMMARY_CHARS + 1);
+        let (ok, _, code) = summarize(&Err(ToolError::not_found("gone")));
+        assert!(!ok);
+        assert_eq!(code, Some("NOT_FOUND"));
+    }
+}
@@ -1,35 +1,170 @@
 use chrono::Local;
-use rusqlite::params;
+use rusqlite::{params, Connection, OptionalExtension};
 use serde_json::{json, Value};
 use std::fs;
 use std::io;
-use std::path::PathBuf;
-use std::process::Command;
+use std::path::{Path, PathBuf};
 
 use super::helpers::{db_conn, get_data_dir};
+use super::hosts_file::{self, HostsDocument};
+use super::hosts_merge::{self, DiffOp};
+use super::hosts_subscription;
+use super::hosts_verify;
+use super::error::{ToolError, ToolResult};
+use super::registry::{ActionSpec, DomainSpec, FieldSpec};
 
-pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
+/// Leaves room for the timestamp and suffix of a backup file name.
+const MAX_BACKUP_LABEL_BYTES: usize = 200;
+
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "hosts",
+    description: "Hosts file profiles, activation and backups",
+    execute,
+    actions: &[
+        ActionSpec::new(
+            "save",
+            "Create or update a profile",
+            &[
+                FieldSpec::required("name", "string", "profile name"),
+                FieldSpec::required("content", "string", "hosts file content"),
+            ],
+            "object: { ok }",
+        ),
+        ActionSpec::new(
+            "list",
+            "List profiles",
+            &[],
+            "array: [{ id, name, content, enabled, updatedAt, sortOrder, source, schedule, lastRefreshedAt, lastError }]",
+        ),
+        ActionSpec::new(
+            "subscribe",
+            "Create or update a profile fed from a file path or http(s) URL",
+            &[
+                FieldSpec::required("name", "string", "profile name"),
+                FieldSpec::required("source", "string", "local path, file:// or http(s):// URL"),
+                FieldSpec::optional("schedule", "string", "5- or 6-field cron expression, default hourly"),
+            ],
+            "object: { ok, name, bytes }",
+        ),
+        ActionSpec::new(
+            "refresh",
+            "Fetch subscriptions now, keeping the last good content on failure",
+            &[FieldSpec::optional("name", "string", "one subscription; all when omitted")],
+            "array: [{ name, ok, changed, error }]",
+        ),
+        ActionSpec::new(
+            "unsubscribe",
+            "Turn a subscription into a plain profile with its current content",
+            &[FieldSpec::required("name", "string", "profile name")],
+            "object: { ok }",
+        ),
+        ActionSpec::new(
+            "delete",
+            "Delete a profile",
+            &[FieldSpec::required("name", "string", "profile name")],
+            "object: { ok }",
+        ),
+        ActionSpec::new(
+            "activate",
+            "Back up the system hosts file and write one profile, or several merged, to it",
+            &[
+                FieldSpec::optional("profileName", "string", "profile name (single-profile mode)"),
+                FieldSpec::optional("content", "string", "content to write instead of the stored one"),
+                FieldSpec::optional("profileNames", "array", "profiles to merge in sort order (multi-profile mode)"),
+                FieldSpec::optional("flushDns", "boolean", "flush the OS resolver cache after writing"),
+                FieldSpec::optional("verify", "boolean", "resolve every hostname and compare with the written IPs"),
+            ],
+            "object: { backupPath, digest, profiles?, conflicts?, dnsFlush?: { ok, method?, error? }, verification? }",
+        ),
+        ActionSpec::new(
+            "verify",
+            "Resolve every hostname of the system hosts file and compare with its IPs",
+            &[FieldSpec::optional("flushDns", "boolean", "flush the OS resolver cache first")],
+            "object: { dnsFlush?, verification: { passed, failed, skipped, hosts: [{ hostname, expected, resolved, ok, error }] } }",
+        ),
+        ActionSpec::new(
+            "preview",
+            "Merge profiles without writing and diff the result against the system hosts file",
+            &[FieldSpec::required("profileNames", "array", "profiles to merge in sort order")],
+            "object: { profiles, added, removed, conflicts: [{ hostname, winner, mappings: [{ profile, ip }] }], diff: [{ op, line }], content }",
+        ),
+        ActionSpec::new(
+            "reorder",
+            "Set the display order of profiles",
+            &[FieldSpec::required("ids", "array", "profile ids in the new order")],
+            "object: { ok }",
+        ),
+        ActionSpec::new(
+            "parse",
+            "Split hosts content into entries, keeping disabled (commented-out) ones",
+            &[
+                FieldSpec::optional("content", "string", "hosts content to parse"),
+                FieldSpec::optional("name", "string", "stored profile to parse when content is absent"),
+            ],
+            "object: { entries: [{ line, ip, hostnames, comment, enabled }] }",
+        ),
+        ActionSpec::new(
+            "lint",
+            "Report invalid IPs and hostnames, malformed lines, duplicates and shadowed entries",
+            &[
+                FieldSpec::optional("content", "string", "hosts content to check"),
+                FieldSpec::optional("name", "string", "stored profile to check when content is absent"),
+            ],
+            "object: { issues: [{ line, severity, code, message }] }",
+        ),
+        ActionSpec::new(
+            "toggle_entry",
+            "Comment out or restore one entry of a stored profile",
+            &[
+                FieldSpec::required("name", "string", "profile name"),
+                FieldSpec::required("line", "integer", "1-based line number of the entry"),
+                FieldSpec::required("enabled", "boolean", "whether the entry should be active"),
+            ],
+            "object: { content, entry: { line, ip, hostnames, comment, enabled } }",
+        ),
+        ActionSpec::new("read_system", "Read the system hosts file", &[], "object: { content }"),
+        ActionSpec::new("admin_check", "Check write access to the system hosts file", &[], "object: { canWrite, path, elevation }"),
+        ActionSpec::new("backup_list", "List hosts backups, newest first", &[], "array: [{ filename, size, modifiedAt }]"),
+        ActionSpec::new(
+            "backup_restore",
+            "Restore a backup into the system hosts file",
+            &[FieldSpec::required("filename", "string", "backup file name")],
+            "object: { ok, restoredFrom }",
+        ),
+    ],
+};
+
+pub fn execute(action: &str, payload: &Value) -> ToolResult {
     match action {
         "save" => hosts_save(payload),
         "list" => hosts_list(),
         "delete" => hosts_delete(payload),
-        "activate" => hosts_activate(payload),
+        "activate" => hosts_activate(payload, &hosts_path()),
+        "preview" => hosts_preview(payload),
+        "verify" => hosts_verify_system(payload),
         "reorder" => hosts_reorder(payload),
+        "subscribe" => hosts_subscribe(payload),
+        "refresh" => hosts_refresh(payload),
+        "unsubscribe" => hosts_unsubscribe(payload),
+        "parse" => hosts_parse(payload),
+        "lint" => hosts_lint(payload),
+        "toggle_entry" => hosts_toggle_entry(payload),
         "read_system" => hosts_read_system(),
         "admin_check" => hosts_admin_check(),
         "backup_list" => hosts_backup_list(),
-        "backup_restore" => hosts_backup_restore(payload),
-        _ => Err(format!("unsupported hosts action: {action}")),
+        "backup_restore" => hosts_backup_restore(payload, &hosts_path()),
+        _ => Err(ToolError::unsupported("hosts", action)),
     }
 }
 
-fn hosts_save(payload: &Value) -> Result<Value, String> {
+fn hosts_save(payload: &Value) -> ToolResult {
     let name = payload["name"].as_str().unwrap_or_default();
     let content = payload["content"].as_str().unwrap_or_default();
     if name.is_empty() {
-        return Err("hosts profile name is empty".into());
+        return Err(ToolError::invalid_field("name", "hosts profile name is empty"));
     }
     let conn = db_conn()?;
+    hosts_subscription::ensure_local(&conn, name)?;
     let next_order: i64 = conn
         .query_row("SELECT COALESCE(MAX(sort_order), 0) + 1 FROM hosts_profiles", [], |r| r.get(0))
         .unwrap_or(0);
@@ -42,10 +177,13 @@ fn hosts_save(payload: &Value) -> Result<Value, String> {
     Ok(json!({"ok": true}))
 }
 
-fn hosts_list() -> Result<Value, String> {
+fn hosts_list() -> ToolResult {
     let conn = db_conn()?;
     let mut stmt = conn
-        .prepare("SELECT id, name, content, enabled, updated_at, sort_order FROM hosts_profiles ORDER BY enabled DESC, sort_order ASC, id ASC")
+        .prepare(
+            "SELECT id, name, content, enabled, updated_at, sort_order, source, refresh_cron, last_refreshed_at, last_error
+            FROM hosts_profiles ORDER BY enabled DESC, sort_order ASC, id ASC",
+        )
         .map_err(|e| format!("prepare query failed: {e}"))?;
     let rows = stmt
         .query_map([], |row| {
@@ -56,6 +194,10 @@ fn hosts_list() -> Result<Value, String> {
                 "enabled": row.get::<_, i64>(3)? == 1,
                 "updatedAt": row.get::<_, String>(4)?,
                 "sortOrder": row.get::<_, i64>(5)?,
+                "source": row.get::<_, Option<String>>(6)?,
+                "schedule": row.get::<_, Option<String>>(7)?,
+                "lastRefreshedAt": row.get::<_, Option<String>>(8)?,
+                "lastError": row.get::<_, Option<String>>(9)?,
             }))
         })
         .map_err(|e| format!("query hosts failed: {e}"))?;
@@ -66,7 +208,39 @@ fn hosts_list() -> Result<Value, String> {
     Ok(Value::Array(out))
 }
 
-fn hosts_delete(payload: &Value) -> Result<Value, String> {
+fn hosts_subscribe(payload: &Value) -> ToolResult {
+    let name = payload["name"].as_str().unwrap_or_default().trim();
+    let source = payload["source"].as_str().unwrap_or_default().trim();
+    if name.is_empty() {
+        return Err(ToolError::invalid_field("name", "hosts profile name is empty"));
+    }
+    if source.is_empty() {
+        return Err(ToolError::invalid_field("source", "subscription source is empty"));
+    }
+    let schedule = payload["schedule"]
+        .as_str()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .unwrap_or(hosts_subscription::DEFAULT_SCHEDULE);
+    let conn = db_conn()?;
+    hosts_subscription::subscribe(&conn, name, source, schedule)
+}
+
+fn hosts_refresh(payload: &Value) -> ToolResult {
+    let conn = db_conn()?;
+    match payload["name"].as_str().filter(|n| !n.is_empty()) {
+        Some(name) => Ok(Value::Array(vec![hosts_subscription::refresh(&conn, name)?])),
+        None => hosts_subscription::refresh_all(&conn),
+    }
+}
+
+fn hosts_unsubscribe(payload: &Value) -> ToolResult {
+    let name = payload["name"].as_str().unwrap_or_default();
+    let conn = db_conn()?;
+    hosts_subscription::unsubscribe(&conn, name)
+}
+
+fn hosts_delete(payload: &Value) -> ToolResult {
     let name = payload["name"].as_str().unwrap_or_default();
     let conn = db_conn()?;
     conn.execute("DELETE FROM hosts_profiles WHERE name = ?1", params![name])
@@ -74,7 +248,71 @@ fn hosts_delete(payload: &Value) -> Result<Value, String> {
     Ok(json!({"ok": true}))
 }
 
-fn hosts_activate(payload: &Value) -> Result<Value, String> {
+fn profile_content(conn: &Connection, name: &str) -> ToolResult<String> {
+    conn.query_row("SELECT content FROM hosts_profiles WHERE name = ?1", params![name], |row| row.get(0))
+        .optional()
+        .map_err(|e| format!("query profile failed: {e}"))?
+        .ok_or_else(|| ToolError::not_found(format!("hosts profile not found: {name}")))
+}
+
+/// `content` from the payload, or the stored profile named by `name`.
+fn payload_content(payload: &Value) -> ToolResult<String> {
+    if let Some(content) = payload["content"].as_str() {
+        return Ok(content.to_string());
+    }
+    match payload["name"].as_str().filter(|n| !n.is_empty()) {
+        Some(name) => {
+            let conn = db_conn()?;
+            profile_content(&conn, name)
+        }
+        None => Err(ToolError::invalid("either content or name is required")),
+    }
+}
+
+fn hosts_parse(payload: &Value) -> ToolResult {
+    let doc = HostsDocument::parse(&payload_content(payload)?);
+    let entries: Vec<Value> = doc.entries().map(|(line, entry)| hosts_file::entry_json(line, entry)).collect();
+    Ok(json!({ "entries": entries }))
+}
+
+fn hosts_lint(payload: &Value) -> ToolResult {
+    let doc = HostsDocument::parse(&payload_content(payload)?);
+    let issues: Vec<Value> = hosts_file::lint(&doc).iter().map(|i| i.to_json()).collect();
+    Ok(json!({ "issues": issues }))
+}
+
+fn hosts_toggle_entry(payload: &Value) -> ToolResult {
+    let name = payload["name"].as_str().unwrap_or_default();
+    let line = payload["line"]
+        .as_u64()
+        .ok_or_else(|| ToolError::invalid_field("line", "must be a positive line number"))? as usize;
+    let enabled = payload["enabled"]
+        .as_bool()
+        .ok_or_else(|| ToolError::invalid_field("enabled", "must be a boolean"))?;
+    let conn = db_conn()?;
+    hosts_subscription::ensure_local(&conn, name)?;
+    let mut doc = HostsDocument::parse(&profile_content(&conn, name)?);
+    doc.set_enabled(line, enabled).map_err(|e| ToolError::invalid_field("line", e))?;
+    let content = doc.render();
+    conn.execute(
+        "UPDATE hosts_profiles SET content = ?1, updated_at = CURRENT_TIMESTAMP WHERE name = ?2",
+        params![content, name],
+    )
+    .map_err(|e| format!("save hosts profile failed: {e}"))?;
+    let entry = doc
+        .entries()
+        .find(|(n, _)| *n == line)
+        .map(|(n, e)| hosts_file::entry_json(n, e))
+        .unwrap_or(Value::Null);
+    Ok(json!({ "content": content, "entry": entry }))
+}
+
+/// Writes the profile(s) to the hosts file at `hosts`, the system one
+/// outside tests.
+fn hosts_activate(payload: &Value, hosts: &Path) -> ToolResult {
+    if payload["profileNames"].is_array() {
+        return hosts_activate_merged(payload, hosts);
+    }
     let profile_name = payload["profileName"].as_str().unwrap_or_default();
     let mut content = payload["content"].as_str().unwrap_or_default().to_string();
     let conn = db_conn()?;
@@ -90,30 +328,153 @@ fn hosts_activate(payload: &Value) -> Result<Value, String> {
         }
     }
     if content.is_empty() {
-        return Err("Hosts profile content is empty.".into());
+        return Err(ToolError::invalid("Hosts profile content is empty."));
+    }
+    let backup_path = backup_and_write(hosts, profile_name, &content)?;
+    mark_enabled(&conn, &[profile_name.to_string()])?;
+    let mut result = json!({
+      "backupPath": backup_path.to_string_lossy().to_string(),
+      "digest": format!("{:x}", md5::compute(content.as_bytes()))
+    });
+    post_switch_checks(payload, &content, &mut result);
+    Ok(result)
+}
+
+/// Multi-profile mode: the selected profiles are merged in `sort_order`.
+fn hosts_activate_merged(payload: &Value, hosts: &Path) -> ToolResult {
+    let conn = db_conn()?;
+    let profiles = selected_profiles(&conn, payload)?;
+    let content = hosts_merge::merge(&profiles);
+    let names: Vec<String> = profiles.iter().map(|(name, _)| name.clone()).collect();
+    let backup_path = backup_and_write(hosts, &merged_label(&names), &content)?;
+    mark_enabled(&conn, &names)?;
+    let conflicts: Vec<Value> = hosts_merge::conflicts(&profiles).iter().map(|c| c.to_json()).collect();
+    let mut result = json!({
+      "backupPath": backup_path.to_string_lossy().to_string(),
+      "digest": format!("{:x}", md5::compute(content.as_bytes())),
+      "profiles": names,
+      "conflicts": conflicts,
+    });
+    post_switch_checks(payload, &content, &mut result);
+    Ok(result)
+}
+
+/// Optional `flushDns` / `verify` steps after a successful write. The file is
+/// already switched by then, so failures are reported, not raised.
+fn post_switch_checks(payload: &Value, content: &str, result: &mut Value) {
+    if payload["flushDns"].as_bool().unwrap_or(false) {
+        result["dnsFlush"] = dns_flush_json();
+    }
+    if payload["verify"].as_bool().unwrap_or(false) {
+        result["verification"] = hosts_verify::verify(content);
+    }
+}
+
+fn dns_flush_json() -> Value {
+    match platform::flush_dns() {
+        Ok(method) => json!({ "ok": true, "method": method }),
+        Err(e) => json!({ "ok": false, "error": e.message() }),
+    }
+}
+
+/// Re-checks the current system hosts file against the resolver.
+fn hosts_verify_system(payload: &Value) -> ToolResult {
+    let content = fs::read_to_string(hosts_path()).map_err(|e| ToolError::io("read system hosts failed", e))?;
+    let mut result = json!({});
+    if payload["flushDns"].as_bool().unwrap_or(false) {
+        result["dnsFlush"] = dns_flush_json();
     }
+    result["verification"] = hosts_verify::verify(&content);
+    Ok(result)
+}
+
+/// What activating `profileNames` would write, compared to the system file.
+fn hosts_preview(payload: &Value) -> ToolResult {
+    let conn = db_conn()?;
+    let profiles = selected_profiles(&conn, payload)?;
+    let content = hosts_merge::merge(&profiles);
+    let current = fs::read_to_string(hosts_path()).map_err(|e| ToolError::io("read system hosts failed", e))?;
+    let diff = hosts_merge::line_diff(&current, &content);
+    let count = |op| diff.iter().filter(|(o, _)| *o == op).count();
+    let conflicts: Vec<Value> = hosts_merge::conflicts(&profiles).iter().map(|c| c.to_json()).collect();
+    Ok(json!({
+        "profiles": profiles.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+        "added": count(DiffOp::Added),
+        "removed": count(DiffOp::Removed),
+        "conflicts": conflicts,
+        "diff": hosts_merge::diff_json(&diff),
+        "content": content,
+    }))
+}
+
+/// `(name, content)` of the profiles listed in `profileNames`, in `sort_order`.
+fn selected_profiles(conn: &Connection, payload: &Value) -> ToolResult<Vec<(String, String)>> {
+    let names = payload["profileNames"]
+        .as_array()
+        .ok_or_else(|| ToolError::invalid_field("profileNames", "must be an array of profile names"))?;
+    let names: Vec<&str> = names.iter().filter_map(Value::as_str).filter(|n| !n.is_empty()).collect();
+    if names.is_empty() {
+        return Err(ToolError::invalid_field("profileNames", "select at least one profile"));
+    }
+    let mut stmt = conn
+        .prepare("SELECT name, content FROM hosts_profiles ORDER BY sort_order ASC, id ASC")
+        .map_err(|e| format!("prepare query failed: {e}"))?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| format!("query hosts failed: {e}"))?;
+    let mut profiles = Vec::new();
+    for row in rows {
+        let (name, content) = row.map_err(|e| e.to_string())?;
+        if names.contains(&name.as_str()) {
+            profiles.push((name, content));
+        }
+    }
+    if let Some(missing) = names.iter().find(|n| !profiles.iter().any(|(name, _)| name == *n)) {
+        return Err(ToolError::not_found(format!("hosts profile not found: {missing}")));
+    }
+    Ok(profiles)
+}
+
+/// Backup label for a merge: the first profile and how many follow.
+fn merged_label(names: &[String]) -> String {
+    match names {
+        [first, rest @ ..] if !rest.is_empty() => format!("{first}+{} more", rest.len()),
+        _ => names.concat(),
+    }
+}
+
+/// Backs up the current hosts file under `label`, then writes `content`.
+/// The label is cut so the file name stays within the 255-byte limit.
+fn backup_and_write(hosts: &Path, label: &str, content: &str) -> ToolResult<PathBuf> {
     let backup_dir = get_data_dir()?.join("hosts-backups");
     fs::create_dir_all(&backup_dir).map_err(|e| format!("create backup dir failed: {e}"))?;
-    let original = fs::read_to_string(hosts_path()).map_err(|e| format!("read hosts failed: {e}"))?;
+    let original = fs::read_to_string(hosts).map_err(|e| format!("read hosts failed: {e}"))?;
     let stamp = Local::now().format("%Y-%m-%dT%H-%M-%S").to_string();
-    let backup_path = backup_dir.join(format!("{stamp}-{profile_name}.hosts.bak"));
+    let mut end = label.len().min(MAX_BACKUP_LABEL_BYTES);
+    while !label.is_char_boundary(end) {
+        end -= 1;
+    }
+    let backup_path = backup_dir.join(format!("{stamp}-{}.hosts.bak", &label[..end]));
     fs::write(&backup_path, original).map_err(|e| format!("write backup failed: {e}"))?;
-    write_hosts_file(&content)?;
+    write_hosts_file(hosts, content)?;
+    Ok(backup_path)
+}
+
+fn mark_enabled(conn: &Connection, names: &[String]) -> ToolResult<()> {
     conn.execute("UPDATE hosts_profiles SET enabled = 0", [])
         .map_err(|e| format!("disable previous profiles failed: {e}"))?;
-    conn.execute(
-        "UPDATE hosts_profiles SET enabled = 1, updated_at = CURRENT_TIMESTAMP WHERE name = ?1",
-        params![profile_name],
-    )
-    .map_err(|e| format!("mark profile enabled failed: {e}"))?;
-    Ok(json!({
-      "backupPath": backup_path.to_string_lossy().to_string(),
-      "digest": format!("{:x}", md5::compute(content.as_bytes()))
-    }))
+    for name in names {
+        conn.execute(
+            "UPDATE hosts_profiles SET enabled = 1, updated_at = CURRENT_TIMESTAMP WHERE name = ?1",
+            params![name],
+        )
+        .map_err(|e| format!("mark profile enabled failed: {e}"))?;
+    }
+    Ok(())
 }
 
 /// Accepts { "ids": [3, 1, 2] } — new display order of profile IDs.
-fn hosts_reorder(payload: &Value) -> Result<Value, String> {
+fn hosts_reorder(payload: &Value) -> ToolResult {
     let ids = payload["ids"]
         .as_array()
         .ok_or("ids must be an array")?;
@@ -130,84 +491,267 @@ fn hosts_reorder(payload: &Value) -> Result<Value, String> {
 }
 
 fn hosts_path() -> PathBuf {
-    PathBuf::from("C:\\Windows\\System32\\drivers\\etc\\hosts")
+    PathBuf::from(platform::HOSTS_PATH)
 }
 
-/// Try direct write first; on PermissionDenied, trigger UAC elevation via PowerShell.
-fn write_hosts_file(content: &str) -> Result<(), String> {
-    let path = hosts_path();
-    match fs::write(&path, content.as_bytes()) {
-        Ok(()) => Ok(()),
+/// Try direct write first; on PermissionDenied, go through the platform's
+/// elevation helper. Either way the file is read back to confirm the write.
+fn write_hosts_file(path: &Path, content: &str) -> ToolResult<()> {
+    match fs::write(path, content.as_bytes()) {
+        Ok(()) => {
+            if !hosts_matches(path, content)? {
+                return Err(ToolError::Execution("hosts 文件写入后内容不一致".into()));
+            }
+            Ok(())
+        }
         Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
-            elevated_write_hosts(content)?;
-            // Verify the elevated write actually succeeded
-            let actual = fs::read_to_string(&path)
-                .map_err(|e| format!("verify hosts write failed: {e}"))?;
-            if actual.replace('\r', "") != content.replace('\r', "") {
-                return Err("hosts 文件未被更新，UAC 提权可能被取消".into());
+            elevated_write_hosts(path, content)?;
+            if !hosts_matches(path, content)? {
+                return Err(ToolError::PermissionDenied(format!(
+                    "hosts 文件未被更新，{} 提权可能被取消",
+                    platform::ELEVATION
+                )));
             }
             Ok(())
         }
-        Err(e) => Err(format!("write hosts failed: {e}")),
+        Err(e) => Err(ToolError::io("write hosts failed", e)),
     }
 }
 
-/// Write hosts via UAC-elevated PowerShell process.
-/// 1. Save content to a temp file in .lazycat/
-/// 2. Generate a .ps1 script that copies temp -> hosts
-/// 3. Launch it elevated via `Start-Process -Verb RunAs`
-fn elevated_write_hosts(content: &str) -> Result<(), String> {
+/// Compares ignoring `\r`, since Windows tooling may rewrite line endings.
+fn hosts_matches(path: &Path, content: &str) -> ToolResult<bool> {
+    let actual = fs::read_to_string(path).map_err(|e| format!("verify hosts write failed: {e}"))?;
+    Ok(actual.replace('\r', "") == content.replace('\r', ""))
+}
+
+/// Stage the content in .lazycat/ and let an elevated helper copy it over
+/// the target.
+fn elevated_write_hosts(target: &Path, content: &str) -> ToolResult<()> {
     let data_dir = get_data_dir()?;
     let temp_path = data_dir.join("hosts-pending.tmp");
-    let script_path = data_dir.join("hosts-elevate.ps1");
-    let hosts = hosts_path();
-
     fs::write(&temp_path, content).map_err(|e| format!("write temp file failed: {e}"))?;
-
-    // PS1 script: copy temp file to hosts location, then clean up temp
-    let ps1 = format!(
-        "Copy-Item -LiteralPath '{}' -Destination '{}' -Force\r\nRemove-Item -LiteralPath '{}' -Force\r\n",
-        temp_path.to_string_lossy().replace('\'', "''"),
-        hosts.to_string_lossy().replace('\'', "''"),
-        temp_path.to_string_lossy().replace('\'', "''"),
-    );
-    fs::write(&script_path, &ps1).map_err(|e| format!("write elevate script failed: {e}"))?;
-
-    // Outer PowerShell launches the script elevated via -Verb RunAs
-    let launcher = format!(
-        "Start-Process -FilePath 'powershell.exe' -ArgumentList '-NoProfile -ExecutionPolicy Bypass -File \"{}\"' -Verb RunAs -Wait",
-        script_path.to_string_lossy()
-    );
-
-    let status = Command::new("powershell")
-        .args(["-NoProfile", "-Command", &launcher])
-        .status()
-        .map_err(|e| format!("launch UAC elevation failed: {e}"))?;
-
-    // Cleanup (script may have self-cleaned temp already)
-    let _ = fs::remove_file(&script_path);
+    let result = platform::elevated_copy(&data_dir, &temp_path, target);
     let _ = fs::remove_file(&temp_path);
+    result
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::fs;
+    use std::path::Path;
+    use std::process::Command;
 
-    if !status.success() {
-        return Err("UAC 提权被取消或失败".into());
+    use crate::tools::error::{ToolError, ToolResult};
+
+    pub const HOSTS_PATH: &str = "C:\\Windows\\System32\\drivers\\etc\\hosts";
+    pub const ELEVATION: &str = "UAC";
+
+    /// 1. Generate a .ps1 script that copies temp -> hosts
+    /// 2. Launch it elevated via `Start-Process -Verb RunAs`
+    pub fn elevated_copy(data_dir: &Path, source: &Path, target: &Path) -> ToolResult<()> {
+        let script_path = data_dir.join("hosts-elevate.ps1");
+
+        // PS1 script: copy temp file to hosts location, then clean up temp
+        let ps1 = format!(
+            "Copy-Item -LiteralPath '{}' -Destination '{}' -Force\r\nRemove-Item -LiteralPath '{}' -Force\r\n",
+            source.to_string_lossy().replace('\'', "''"),
+            target.to_string_lossy().replace('\'', "''"),
+            source.to_string_lossy().replace('\'', "''"),
+        );
+        fs::write(&script_path, &ps1).map_err(|e| format!("write elevate script failed: {e}"))?;
+
+        // Outer PowerShell launches the script elevated via -Verb RunAs
+        let launcher = format!(
+            "Start-Process -FilePath 'powershell.exe' -ArgumentList '-NoProfile -ExecutionPolicy Bypass -File \"{}\"' -Verb RunAs -Wait",
+            script_path.to_string_lossy()
+        );
+
+        let status = Command::new("powershell")
+            .args(["-NoProfile", "-Command", &launcher])
+            .status()
+            .map_err(|e| format!("launch UAC elevation failed: {e}"))?;
+
+        let _ = fs::remove_file(&script_path);
+
+        if !status.success() {
+            return Err(ToolError::PermissionDenied("UAC 提权被取消或失败".into()));
+        }
+        Ok(())
     }
 
-    Ok(())
+    pub fn flush_dns() -> ToolResult<String> {
+        let output = Command::new("ipconfig")
+            .arg("/flushdns")
+            .output()
+            .map_err(|e| format!("launch ipconfig failed: {e}"))?;
+        if !output.status.success() {
+            return Err(ToolError::Execution("ipconfig /flushdns 执行失败".into()));
+        }
+        Ok("ipconfig /flushdns".into())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use std::path::Path;
+    use std::process::Command;
+
+    use crate::tools::error::{ToolError, ToolResult};
+
+    pub const HOSTS_PATH: &str = "/etc/hosts";
+    pub const ELEVATION: &str = "管理员";
+
+    fn applescript_string(path: &Path) -> String {
+        let escaped = path.to_string_lossy().replace('\\', "\\\\").replace('"', "\\\"");
+        format!("quoted form of \"{escaped}\"")
+    }
+
+    /// Runs `cp` through `do shell script ... with administrator privileges`,
+    /// which shows the standard macOS password dialog.
+    pub fn elevated_copy(_data_dir: &Path, source: &Path, target: &Path) -> ToolResult<()> {
+        let script = format!(
+            "do shell script \"/bin/cp \" & {} & \" \" & {} with administrator privileges",
+            applescript_string(source),
+            applescript_string(target),
+        );
+        let output = Command::new("osascript")
+            .args(["-e", &script])
+            .output()
+            .map_err(|e| format!("launch osascript failed: {e}"))?;
+        if output.status.success() {
+            return Ok(());
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        // -128 is "User canceled."
+        if stderr.contains("-128") {
+            return Err(ToolError::PermissionDenied("管理员授权已取消".into()));
+        }
+        Err(ToolError::PermissionDenied(format!("管理员授权失败: {}", stderr.trim())))
+    }
+
+    /// `dscacheutil` clears the directory-service cache; mDNSResponder keeps
+    /// its own and only drops it on SIGHUP, which needs root.
+    pub fn flush_dns() -> ToolResult<String> {
+        let _ = Command::new("dscacheutil").arg("-flushcache").status();
+        let output = Command::new("killall")
+            .args(["-HUP", "mDNSResponder"])
+            .output()
+            .map_err(|e| format!("launch killall failed: {e}"))?;
+        if !output.status.success() {
+            return Err(ToolError::PermissionDenied(format!(
+                "刷新 mDNSResponder 缓存需要管理员权限: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok("dscacheutil -flushcache; killall -HUP mDNSResponder".into())
+    }
 }
 
-fn hosts_read_system() -> Result<Value, String> {
+#[cfg(all(unix, not(target_os = "macos")))]
+mod linux {
+    use std::env;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+
+    use crate::tools::error::{ToolError, ToolResult};
+
+    pub const HOSTS_PATH: &str = "/etc/hosts";
+    pub const ELEVATION: &str = "pkexec/sudo";
+
+    fn find_program(name: &str) -> Option<PathBuf> {
+        env::split_paths(&env::var_os("PATH")?)
+            .map(|dir| dir.join(name))
+            .find(|p| p.is_file())
+    }
+
+    /// pkexec needs a polkit agent, so it is only tried in a graphical
+    /// session; otherwise `sudo -A` asks through `$SUDO_ASKPASS`.
+    fn helper() -> ToolResult<Command> {
+        let graphical = env::var_os("DISPLAY").is_some() || env::var_os("WAYLAND_DISPLAY").is_some();
+        if graphical {
+            if let Some(pkexec) = find_program("pkexec") {
+                return Ok(Command::new(pkexec));
+            }
+        }
+        if env::var_os("SUDO_ASKPASS").is_some() {
+            if let Some(sudo) = find_program("sudo") {
+                let mut cmd = Command::new(sudo);
+                cmd.arg("-A");
+                return Ok(cmd);
+            }
+        }
+        Err(ToolError::PermissionDenied(
+            "无法提权写入 hosts：需要图形会话中的 pkexec，或设置 SUDO_ASKPASS 以使用 sudo -A".into(),
+        ))
+    }
+
+    /// `cp` onto an existing file keeps its owner and mode.
+    pub fn elevated_copy(_data_dir: &Path, source: &Path, target: &Path) -> ToolResult<()> {
+        let output = helper()?
+            .arg("cp")
+            .arg("--")
+            .arg(source)
+            .arg(target)
+            .output()
+            .map_err(|e| format!("launch privilege helper failed: {e}"))?;
+        match output.status.code() {
+            Some(0) => Ok(()),
+            // pkexec: 126 = dialog dismissed, 127 = not authorised.
+            Some(126) | Some(127) => Err(ToolError::PermissionDenied("提权被取消或未获授权".into())),
+            _ => Err(ToolError::PermissionDenied(format!(
+                "提权写入 hosts 失败: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))),
+        }
+    }
+
+    /// glibc itself keeps no cache; systemd-resolved and nscd do.
+    pub fn flush_dns() -> ToolResult<String> {
+        const CANDIDATES: &[(&str, &[&str])] = &[
+            ("resolvectl", &["flush-caches"]),
+            ("systemd-resolve", &["--flush-caches"]),
+            ("nscd", &["-i", "hosts"]),
+        ];
+        let mut last_error = None;
+        for (program, args) in CANDIDATES {
+            let Some(path) = find_program(program) else { continue };
+            match Command::new(path).args(*args).output() {
+                Ok(output) if output.status.success() => return Ok(format!("{program} {}", args.join(" "))),
+                Ok(output) => last_error = Some(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+                Err(e) => last_error = Some(e.to_string()),
+            }
+        }
+        match last_error {
+            Some(e) => Err(ToolError::Execution(format!("刷新 DNS 缓存失败: {e}"))),
+            None => Ok("none (no resolver cache running)".into()),
+        }
+    }
+}
+
+#[cfg(windows)]
+use windows as platform;
+#[cfg(target_os = "macos")]
+use macos as platform;
+#[cfg(all(unix, not(target_os = "macos")))]
+use linux as platform;
+
+fn hosts_read_system() -> ToolResult {
     let content = fs::read_to_string(hosts_path())
-        .map_err(|e| format!("read system hosts failed: {e}"))?;
+        .map_err(|e| ToolError::io("read system hosts failed", e))?;
     Ok(json!({ "content": content }))
 }
 
-fn hosts_admin_check() -> Result<Value, String> {
+fn hosts_admin_check() -> ToolResult {
     let path = hosts_path();
     let can_write = fs::OpenOptions::new().write(true).open(&path).is_ok();
-    Ok(json!({ "canWrite": can_write }))
+    Ok(json!({
+        "canWrite": can_write,
+        "path": path.to_string_lossy(),
+        "elevation": platform::ELEVATION,
+    }))
 }
 
-fn hosts_backup_list() -> Result<Value, String> {
+fn hosts_backup_list() -> ToolResult {
     let backup_dir = get_data_dir()?.join("hosts-backups");
     if !backup_dir.exists() {
         return Ok(json!([]));
@@ -245,23 +789,23 @@ fn hosts_backup_list() -> Result<Value, String> {
     Ok(Value::Array(entries))
 }
 
-fn hosts_backup_restore(payload: &Value) -> Result<Value, String> {
+fn hosts_backup_restore(payload: &Value, hosts: &Path) -> ToolResult {
     let filename = payload["filename"].as_str().unwrap_or_default();
     if filename.is_empty() {
-        return Err("backup filename is empty".into());
+        return Err(ToolError::invalid_field("filename", "backup filename is empty"));
     }
     if filename.contains("..") || filename.contains('/') || filename.contains('\\') {
-        return Err("invalid backup filename".into());
+        return Err(ToolError::invalid_field("filename", "invalid backup filename"));
     }
     let backup_dir = get_data_dir()?.join("hosts-backups");
     let backup_path = backup_dir.join(filename);
     if !backup_path.starts_with(&backup_dir) || !backup_path.exists() {
-        return Err("backup file not found".into());
+        return Err(ToolError::not_found("backup file not found"));
     }
     let backup_content = fs::read_to_string(&backup_path)
         .map_err(|e| format!("read backup file failed: {e}"))?;
 
-    let current = fs::read_to_string(hosts_path())
+    let current = fs::read_to_string(hosts)
         .map_err(|e| format!("read current hosts failed: {e}"))?;
     fs::create_dir_all(&backup_dir).map_err(|e| format!("create backup dir failed: {e}"))?;
     let stamp = Local::now().format("%Y-%m-%dT%H-%M-%S").to_string();
@@ -269,6 +813,51 @@ fn hosts_backup_restore(payload: &Value) -> Result<Value, String> {
     fs::write(backup_dir.join(&pre_restore_name), current)
         .map_err(|e| format!("write pre-restore backup failed: {e}"))?;
 
-    write_hosts_file(&backup_content)?;
+    write_hosts_file(hosts, &backup_content)?;
     Ok(json!({ "ok": true, "restoredFrom": filename }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merged_backup_label_names_the_first_profile() {
+        let names: Vec<String> = (0..40).map(|i| format!("profile-with-a-rather-long-name-{i}")).collect();
+        assert_eq!(merged_label(&names), "profile-with-a-rather-long-name-0+39 more");
+        assert_eq!(merged_label(&names[..1]), "profile-with-a-rather-long-name-0");
+    }
+
+    #[test]
+    fn writes_and_verifies_hosts_file() {
+        let path = std::env::temp_dir().join(format!("lazycat-hosts-{}", uuid::Uuid::new_v4()));
+        fs::write(&path, "127.0.0.1 localhost\r\n").expect("seed");
+        write_hosts_file(&path, "127.0.0.1 dev.local\n").expect("write");
+        assert_eq!(fs::read_to_string(&path).expect("read"), "127.0.0.1 dev.local\n");
+        assert!(hosts_matches(&path, "127.0.0.1 dev.local\r\n").expect("verify"));
+        assert!(!hosts_matches(&path, "127.0.0.1 other\n").expect("verify"));
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn activates_and_restores_against_a_temp_hosts_file() {
+        crate::tools::helpers::use_test_data_dir();
+        let path = std::env::temp_dir().join(format!("lazycat-hosts-{}", uuid::Uuid::new_v4()));
+        fs::write(&path, "127.0.0.1 localhost\n").expect("seed");
+
+        let payload = json!({ "profileName": "temp-dev", "content": "127.0.0.1 dev.local\n" });
+        let activated = hosts_activate(&payload, &path).expect("activate");
+        assert_eq!(fs::read_to_string(&path).expect("read"), "127.0.0.1 dev.local\n");
+        let backup = PathBuf::from(activated["backupPath"].as_str().expect("backup path"));
+        assert_eq!(fs::read_to_string(&backup).expect("read backup"), "127.0.0.1 localhost\n");
+
+        let filename = backup.file_name().expect("name").to_string_lossy().into_owned();
+        let listed = hosts_backup_list().expect("list");
+        assert!(listed.as_array().expect("array").iter().any(|b| b["filename"] == filename));
+        hosts_backup_restore(&json!({ "filename": filename }), &path).expect("restore");
+        assert_eq!(fs::read_to_string(&path).expect("read"), "127.0.0.1 localhost\n");
+
+        let _ = fs::remove_file(backup);
+        let _ = fs::remove_file(path);
+    }
+}
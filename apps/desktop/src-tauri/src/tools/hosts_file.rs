@@ -0,0 +1,267 @@
+//! Line-level model of a hosts file.
+//!
+//! Every line keeps its raw text, so rendering an untouched document gives
+//! back the input byte for byte, line endings included. A commented-out line
+//! that still reads as `ip host...` is a disabled entry rather than a comment.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub ip: String,
+    pub hostnames: Vec<String>,
+    pub comment: Option<String>,
+    pub enabled: bool,
+}
+
+impl Entry {
+    /// The address without an IPv6 zone suffix (`fe80::1%lo0`).
+    pub fn addr(&self) -> Option<IpAddr> {
+        self.ip.split('%').next().unwrap_or_default().parse().ok()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LineKind {
+    Blank,
+    Comment,
+    Entry(Entry),
+    Malformed(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Line {
+    /// 1-based line number.
+    pub number: usize,
+    /// Original text, including a trailing `\r` if the file uses CRLF.
+    pub raw: String,
+    pub kind: LineKind,
+}
+
+pub struct HostsDocument {
+    pub lines: Vec<Line>,
+}
+
+impl HostsDocument {
+    pub fn parse(content: &str) -> Self {
+        let lines = content
+            .split('\n')
+            .enumerate()
+            .map(|(i, raw)| Line { number: i + 1, raw: raw.to_string(), kind: parse_line(raw) })
+            .collect();
+        HostsDocument { lines }
+    }
+
+    pub fn render(&self) -> String {
+        self.lines.iter().map(|l| l.raw.as_str()).collect::<Vec<_>>().join("\n")
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (usize, &Entry)> {
+        self.lines.iter().filter_map(|l| match &l.kind {
+            LineKind::Entry(e) => Some((l.number, e)),
+            _ => None,
+        })
+    }
+
+    /// Comments out or restores the entry on `number`, touching nothing else.
+    pub fn set_enabled(&mut self, number: usize, enabled: bool) -> Result<(), String> {
+        let line = self
+            .lines
+            .get_mut(number.wrapping_sub(1))
+            .ok_or_else(|| format!("line {number} does not exist"))?;
+        let LineKind::Entry(entry) = &line.kind else {
+            return Err(format!("line {number} is not a hosts entry"));
+        };
+        if entry.enabled == enabled {
+            return Ok(());
+        }
+        let indent_len = line.raw.len() - line.raw.trim_start().len();
+        let (indent, rest) = line.raw.split_at(indent_len);
+        let raw = if enabled {
+            let rest = rest.strip_prefix('#').unwrap_or(rest);
+            format!("{indent}{}", rest.strip_prefix(' ').unwrap_or(rest))
+        } else {
+            format!("{indent}# {rest}")
+        };
+        line.kind = parse_line(&raw);
+        line.raw = raw;
+        Ok(())
+    }
+}
+
+fn parse_line(raw: &str) -> LineKind {
+    let text = raw.trim();
+    if text.is_empty() {
+        return LineKind::Blank;
+    }
+    if let Some(rest) = text.strip_prefix('#') {
+        return match parse_entry(rest.trim()) {
+            Ok(entry) if entry.addr().is_some() => LineKind::Entry(Entry { enabled: false, ..entry }),
+            _ => LineKind::Comment,
+        };
+    }
+    match parse_entry(text) {
+        Ok(entry) => LineKind::Entry(entry),
+        Err(reason) => LineKind::Malformed(reason),
+    }
+}
+
+fn parse_entry(text: &str) -> Result<Entry, String> {
+    let (body, comment) = match text.find('#') {
+        Some(i) => (&text[..i], Some(text[i + 1..].trim().to_string())),
+        None => (text, None),
+    };
+    let mut fields = body.split_whitespace();
+    let ip = fields.next().unwrap_or_default().to_string();
+    let hostnames: Vec<String> = fields.map(str::to_string).collect();
+    if hostnames.is_empty() {
+        return Err("至少需要 IP 地址和一个主机名".into());
+    }
+    Ok(Entry { ip, hostnames, comment, enabled: true })
+}
+
+fn valid_hostname(host: &str) -> bool {
+    let host = host.strip_suffix('.').unwrap_or(host);
+    !host.is_empty()
+        && host.len() <= 253
+        && host.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+        })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintIssue {
+    pub line: usize,
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl LintIssue {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "line": self.line,
+            "severity": match self.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            },
+            "code": self.code,
+            "message": self.message,
+        })
+    }
+}
+
+/// Problems in enabled lines, ordered by line number. Disabled entries are
+/// ignored, since commenting a line out is the usual way to park it.
+pub fn lint(doc: &HostsDocument) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    let error = |line, code, message| LintIssue { line, severity: Severity::Error, code, message };
+    // (hostname, is_ipv6) -> (line, ip) of the entry resolvers will use
+    let mut first_seen: HashMap<(String, bool), (usize, IpAddr)> = HashMap::new();
+
+    for line in &doc.lines {
+        let entry = match &line.kind {
+            LineKind::Malformed(reason) => {
+                issues.push(error(line.number, "malformed", reason.clone()));
+                continue;
+            }
+            LineKind::Entry(entry) if entry.enabled => entry,
+            _ => continue,
+        };
+        let Some(addr) = entry.addr() else {
+            issues.push(error(line.number, "invalid_ip", format!("\"{}\" 不是有效的 IP 地址", entry.ip)));
+            continue;
+        };
+        for host in &entry.hostnames {
+            if !valid_hostname(host) {
+                issues.push(error(line.number, "invalid_hostname", format!("\"{host}\" 不是有效的主机名")));
+                continue;
+            }
+            let key = (host.to_ascii_lowercase(), addr.is_ipv6());
+            match first_seen.get(&key) {
+                Some(&(first, ip)) if ip == addr => issues.push(LintIssue {
+                    line: line.number,
+                    severity: Severity::Warning,
+                    code: "duplicate",
+                    message: format!("\"{host}\" 已在第 {first} 行以相同 IP 定义"),
+                }),
+                Some(&(first, ip)) => issues.push(LintIssue {
+                    line: line.number,
+                    severity: Severity::Warning,
+                    code: "shadowed",
+                    message: format!("\"{host}\" 被第 {first} 行的 {ip} 覆盖，此行不会生效"),
+                }),
+                None => {
+                    first_seen.insert(key, (line.number, addr));
+                }
+            }
+        }
+    }
+    issues
+}
+
+pub fn entry_json(number: usize, entry: &Entry) -> Value {
+    json!({
+        "line": number,
+        "ip": entry.ip,
+        "hostnames": entry.hostnames,
+        "comment": entry.comment,
+        "enabled": entry.enabled,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "# team hosts\r\n127.0.0.1\tlocalhost\r\n  # 10.0.0.1 api.test  # staging\r\n10.0.0.2 api.test\r\n";
+
+    #[test]
+    fn round_trips_and_toggles_single_lines() {
+        let mut doc = HostsDocument::parse(SAMPLE);
+        assert_eq!(doc.render(), SAMPLE);
+        let entries: Vec<_> = doc.entries().map(|(n, e)| (n, e.enabled)).collect();
+        assert_eq!(entries, vec![(2, true), (3, false), (4, true)]);
+        let (_, staging) = doc.entries().nth(1).unwrap();
+        assert_eq!(staging.comment.as_deref(), Some("staging"));
+
+        doc.set_enabled(3, true).unwrap();
+        doc.set_enabled(4, false).unwrap();
+        assert_eq!(
+            doc.render(),
+            "# team hosts\r\n127.0.0.1\tlocalhost\r\n  10.0.0.1 api.test  # staging\r\n# 10.0.0.2 api.test\r\n"
+        );
+        assert!(doc.set_enabled(1, true).is_err());
+    }
+
+    #[test]
+    fn lints_with_line_numbers() {
+        let doc = HostsDocument::parse(
+            "127.0.0.1 localhost\n::1 localhost\n300.1.1.1 bad.test\nlonely\n10.0.0.1 a.test a.test\n10.0.0.2 a.test\n# 10.0.0.3 a.test\n1.2.3.4 -x.test\n",
+        );
+        let found: Vec<_> = lint(&doc).into_iter().map(|i| (i.line, i.code)).collect();
+        assert_eq!(
+            found,
+            vec![
+                (3, "invalid_ip"),
+                (4, "malformed"),
+                (5, "duplicate"),
+                (6, "shadowed"),
+                (8, "invalid_hostname"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,205 @@
+//! Stacking several hosts profiles into one file.
+//!
+//! Profiles are concatenated in `sort_order`, each inside marked sections so
+//! the origin of every line stays visible in the system file. Resolvers use
+//! the first matching line, so on conflicts the earliest profile wins.
+
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+
+use serde_json::{json, Value};
+
+use super::hosts_file::HostsDocument;
+
+pub const SECTION_BEGIN: &str = "# >>> lazycat profile:";
+pub const SECTION_END: &str = "# <<< lazycat profile:";
+
+/// Cells of the LCS table (4 bytes each, so 4 MiB) above which the diff
+/// degrades to remove-all/add-all. Common leading and trailing lines are
+/// trimmed first, so only the changed middle counts.
+const DIFF_CELL_LIMIT: usize = 1 << 20;
+
+/// `(ip, hostname)` pairs of enabled entries with a valid address.
+pub fn host_mappings(content: &str) -> Vec<(IpAddr, String)> {
+    let doc = HostsDocument::parse(content);
+    let mut out = Vec::new();
+    for (_, entry) in doc.entries().filter(|(_, e)| e.enabled) {
+        let Some(ip) = entry.addr() else { continue };
+        for host in &entry.hostnames {
+            out.push((ip, host.to_ascii_lowercase()));
+        }
+    }
+    out
+}
+
+/// Concatenates `(profile, content)` pairs into one hosts file.
+pub fn merge(profiles: &[(String, String)]) -> String {
+    let mut out = String::new();
+    for (name, content) in profiles {
+        out.push_str(&format!("{SECTION_BEGIN} {name}\n"));
+        let body = content.replace('\r', "");
+        out.push_str(body.trim_end_matches('\n'));
+        out.push('\n');
+        out.push_str(&format!("{SECTION_END} {name}\n\n"));
+    }
+    out.truncate(out.trim_end_matches('\n').len());
+    out.push('\n');
+    out
+}
+
+/// One hostname mapped to different addresses of the same family by
+/// different profiles. `localhost` on both `127.0.0.1` and `::1` is fine.
+#[derive(Debug, PartialEq)]
+pub struct Conflict {
+    pub hostname: String,
+    /// The profile whose line resolvers will actually use.
+    pub winner: String,
+    pub mappings: Vec<(String, IpAddr)>,
+}
+
+impl Conflict {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "hostname": self.hostname,
+            "winner": self.winner,
+            "mappings": self.mappings.iter()
+                .map(|(profile, ip)| json!({ "profile": profile, "ip": ip.to_string() }))
+                .collect::<Vec<_>>(),
+        })
+    }
+}
+
+pub fn conflicts(profiles: &[(String, String)]) -> Vec<Conflict> {
+    // (hostname, is_ipv6) -> first-seen ordered (profile, ip), deduplicated
+    let mut seen: BTreeMap<(String, bool), Vec<(String, IpAddr)>> = BTreeMap::new();
+    for (name, content) in profiles {
+        for (ip, host) in host_mappings(content) {
+            let mappings = seen.entry((host, ip.is_ipv6())).or_default();
+            if !mappings.iter().any(|(p, i)| p == name && *i == ip) {
+                mappings.push((name.clone(), ip));
+            }
+        }
+    }
+    seen.into_iter()
+        .filter(|(_, mappings)| mappings.iter().any(|(_, ip)| *ip != mappings[0].1))
+        .map(|((hostname, _), mappings)| Conflict { hostname, winner: mappings[0].0.clone(), mappings })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiffOp {
+    Same,
+    Added,
+    Removed,
+}
+
+/// Line diff of `old` -> `new`, ignoring `\r`.
+pub fn line_diff<'a>(old: &'a str, new: &'a str) -> Vec<(DiffOp, &'a str)> {
+    let a: Vec<&str> = old.lines().map(|l| l.trim_end_matches('\r')).collect();
+    let b: Vec<&str> = new.lines().map(|l| l.trim_end_matches('\r')).collect();
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..].iter().rev().zip(b[prefix..].iter().rev()).take_while(|(x, y)| x == y).count();
+    let (mid_a, mid_b) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut out: Vec<(DiffOp, &str)> = a[..prefix].iter().map(|l| (DiffOp::Same, *l)).collect();
+    let (n, m) = (mid_a.len(), mid_b.len());
+    if (n + 1).saturating_mul(m + 1) > DIFF_CELL_LIMIT {
+        out.extend(mid_a.iter().map(|l| (DiffOp::Removed, *l)));
+        out.extend(mid_b.iter().map(|l| (DiffOp::Added, *l)));
+    } else {
+        // lcs[i * w + j] = LCS length of mid_a[i..] and mid_b[j..]
+        let w = m + 1;
+        let mut lcs = vec![0u32; (n + 1) * w];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[i * w + j] = if mid_a[i] == mid_b[j] {
+                    lcs[(i + 1) * w + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * w + j].max(lcs[i * w + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && mid_a[i] == mid_b[j] {
+                out.push((DiffOp::Same, mid_a[i]));
+                i += 1;
+                j += 1;
+            } else if i < n && (j == m || lcs[(i + 1) * w + j] >= lcs[i * w + j + 1]) {
+                out.push((DiffOp::Removed, mid_a[i]));
+                i += 1;
+            } else {
+                out.push((DiffOp::Added, mid_b[j]));
+                j += 1;
+            }
+        }
+    }
+    out.extend(a[a.len() - suffix..].iter().map(|l| (DiffOp::Same, *l)));
+    out
+}
+
+pub fn diff_json(diff: &[(DiffOp, &str)]) -> Value {
+    let lines: Vec<Value> = diff
+        .iter()
+        .map(|(op, line)| {
+            let op = match op {
+                DiffOp::Same => " ",
+                DiffOp::Added => "+",
+                DiffOp::Removed => "-",
+            };
+            json!({ "op": op, "line": line })
+        })
+        .collect();
+    Value::Array(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profiles() -> Vec<(String, String)> {
+        vec![
+            ("base".into(), "127.0.0.1 localhost\n::1 localhost\n10.0.0.1 api.test\r\n".into()),
+            ("override".into(), "# temp\n10.0.0.9 API.test db.test\n".into()),
+        ]
+    }
+
+    #[test]
+    fn merges_sections_and_reports_conflicts() {
+        let merged = merge(&profiles());
+        assert!(merged.starts_with("# >>> lazycat profile: base\n127.0.0.1 localhost\n"));
+        assert!(merged.contains("# <<< lazycat profile: base\n\n# >>> lazycat profile: override\n"));
+        assert!(merged.ends_with("# <<< lazycat profile: override\n"));
+        assert!(!merged.contains('\r'));
+
+        let found = conflicts(&profiles());
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].hostname, "api.test");
+        assert_eq!(found[0].winner, "base");
+        assert_eq!(found[0].mappings.len(), 2);
+    }
+
+    #[test]
+    fn diffs_lines() {
+        let diff = line_diff("a\nb\nc\n", "a\nx\nc\nd\n");
+        assert_eq!(
+            diff,
+            vec![
+                (DiffOp::Same, "a"),
+                (DiffOp::Removed, "b"),
+                (DiffOp::Added, "x"),
+                (DiffOp::Same, "c"),
+                (DiffOp::Added, "d"),
+            ]
+        );
+
+        // Too large for the table: everything in the changed middle is replaced.
+        let old: String = (0..2000).fold("# head\n".into(), |s, i| s + &format!("10.0.0.1 old{i}.test\n"));
+        let new: String = (0..2000).fold("# head\n".into(), |s, i| s + &format!("10.0.0.2 new{i}.test\n"));
+        let diff = line_diff(&old, &new);
+        assert_eq!(diff.len(), 4001);
+        assert_eq!(diff[0], (DiffOp::Same, "# head"));
+        assert!(diff[1..2001].iter().all(|(op, _)| *op == DiffOp::Removed));
+        assert!(diff[2001..].iter().all(|(op, _)| *op == DiffOp::Added));
+    }
+}
@@ -0,0 +1,344 @@
+//! Hosts profiles whose content comes from a shared file or URL.
+//!
+//! A subscription is a `hosts_profiles` row with a `source`. It is refreshed
+//! on its cron schedule by a background thread, or on demand. A failed fetch
+//! only records the error; the profile keeps its last good content.
+
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use chrono::{Local, NaiveDateTime};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::{json, Value};
+
+use super::cron;
+use super::error::{ToolError, ToolResult};
+use super::events;
+use super::helpers::db_conn;
+use super::hosts_file::HostsDocument;
+
+/// Top of every hour.
+pub const DEFAULT_SCHEDULE: &str = "0 0 * * * *";
+/// Raised after a refresh changed a profile: `{ name, enabled }`. An enabled
+/// profile is not rewritten into the system file automatically, since that
+/// may need elevation; the UI offers to re-activate instead.
+pub const EVENT_REFRESHED: &str = "hosts-subscription-refreshed";
+
+const MAX_BYTES: u64 = 16 * 1024 * 1024;
+const FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+const TICK: Duration = Duration::from_secs(60);
+
+static SCHEDULER_STARTED: AtomicBool = AtomicBool::new(false);
+
+/// Reads `source`: an `http(s)://` URL, a `file://` URL or a plain path.
+/// Content without a single hosts entry (an error page, an empty file) is
+/// rejected so it cannot replace a good copy.
+pub fn fetch(source: &str) -> Result<String, String> {
+    let content = if source.starts_with("http://") || source.starts_with("https://") {
+        let agent = ureq::AgentBuilder::new().timeout(FETCH_TIMEOUT).build();
+        let response = agent.get(source).call().map_err(|e| e.to_string())?;
+        let mut bytes = Vec::new();
+        response
+            .into_reader()
+            .take(MAX_BYTES + 1)
+            .read_to_end(&mut bytes)
+            .map_err(|e| format!("read response failed: {e}"))?;
+        if bytes.len() as u64 > MAX_BYTES {
+            return Err(format!("content is larger than {} MB", MAX_BYTES / 1024 / 1024));
+        }
+        String::from_utf8(bytes).map_err(|_| "content is not valid UTF-8".to_string())?
+    } else {
+        let path = source.strip_prefix("file://").unwrap_or(source);
+        fs::read_to_string(Path::new(path)).map_err(|e| format!("read {path} failed: {e}"))?
+    };
+    if !HostsDocument::parse(&content).entries().any(|(_, e)| e.addr().is_some()) {
+        return Err("内容中没有任何 hosts 条目，已保留上次成功获取的版本".into());
+    }
+    Ok(content)
+}
+
+/// `Some(source)` when `name` is a subscription, `None` for a local profile.
+pub fn source_of(conn: &Connection, name: &str) -> ToolResult<Option<String>> {
+    conn.query_row("SELECT source FROM hosts_profiles WHERE name = ?1", params![name], |row| {
+        row.get::<_, Option<String>>(0)
+    })
+    .optional()
+    .map(Option::flatten)
+    .map_err(|e| ToolError::from(format!("query profile failed: {e}")))
+}
+
+/// Rejects edits to subscription content, which the next refresh would undo.
+pub fn ensure_local(conn: &Connection, name: &str) -> ToolResult<()> {
+    match source_of(conn, name)? {
+        Some(source) => Err(ToolError::invalid(format!(
+            "\"{name}\" 是订阅配置（来源 {source}），内容会在刷新时被覆盖；请先取消订阅"
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Creates or updates a subscription, fetching it once up front so a bad
+/// source is reported right away.
+pub fn subscribe(conn: &Connection, name: &str, source: &str, schedule: &str) -> ToolResult {
+    cron::schedule(schedule).map_err(|e| ToolError::invalid_field("schedule", e))?;
+    let existing_local = conn
+        .query_row("SELECT source IS NULL FROM hosts_profiles WHERE name = ?1", params![name], |row| {
+            row.get::<_, bool>(0)
+        })
+        .optional()
+        .map_err(|e| format!("query profile failed: {e}"))?;
+    if existing_local == Some(true) {
+        return Err(ToolError::invalid_field("name", format!("已存在同名的本地配置 \"{name}\"")));
+    }
+    let content = fetch(source).map_err(ToolError::Execution)?;
+    let next_order: i64 = conn
+        .query_row("SELECT COALESCE(MAX(sort_order), 0) + 1 FROM hosts_profiles", [], |r| r.get(0))
+        .unwrap_or(0);
+    conn.execute(
+        "INSERT INTO hosts_profiles(name, content, enabled, sort_order, source, refresh_cron,
+            last_refreshed_at, last_attempt_at, last_error, updated_at)
+        VALUES(?1, ?2, 0, ?3, ?4, ?5, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP, NULL, CURRENT_TIMESTAMP)
+        ON CONFLICT(name) DO UPDATE SET content=excluded.content, source=excluded.source,
+            refresh_cron=excluded.refresh_cron, last_refreshed_at=CURRENT_TIMESTAMP,
+            last_attempt_at=CURRENT_TIMESTAMP, last_error=NULL, updated_at=CURRENT_TIMESTAMP",
+        params![name, content, next_order, source, schedule],
+    )
+    .map_err(|e| format!("save subscription failed: {e}"))?;
+    Ok(json!({ "ok": true, "name": name, "bytes": content.len() }))
+}
+
+/// Turns a subscription back into a plain profile with its current content.
+pub fn unsubscribe(conn: &Connection, name: &str) -> ToolResult {
+    let changed = conn
+        .execute(
+            "UPDATE hosts_profiles SET source = NULL, refresh_cron = NULL, last_error = NULL
+            WHERE name = ?1 AND source IS NOT NULL",
+            params![name],
+        )
+        .map_err(|e| format!("unsubscribe failed: {e}"))?;
+    if changed == 0 {
+        return Err(ToolError::not_found(format!("hosts subscription not found: {name}")));
+    }
+    Ok(json!({ "ok": true }))
+}
+
+/// Fetches one subscription and records the outcome.
+pub fn refresh(conn: &Connection, name: &str) -> ToolResult {
+    let (source, old, enabled) = subscription(conn, name)?;
+    record(conn, name, &old, enabled, fetch(&source))
+}
+
+/// `(source, content, enabled)` of a subscription.
+fn subscription(conn: &Connection, name: &str) -> ToolResult<(String, String, bool)> {
+    conn.query_row(
+        "SELECT source, content, enabled FROM hosts_profiles WHERE name = ?1 AND source IS NOT NULL",
+        params![name],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get::<_, i64>(2)? == 1)),
+    )
+    .optional()
+    .map_err(|e| format!("query subscription failed: {e}"))?
+    .ok_or_else(|| ToolError::not_found(format!("hosts subscription not found: {name}")))
+}
+
+/// Stores a fetch result; `old` is the content it is compared against.
+fn record(conn: &Connection, name: &str, old: &str, enabled: bool, fetched: Result<String, String>) -> ToolResult {
+    match fetched {
+        Ok(content) => {
+            let changed = content != old;
+            conn.execute(
+                "UPDATE hosts_profiles SET content = ?1, last_refreshed_at = CURRENT_TIMESTAMP,
+                    last_attempt_at = CURRENT_TIMESTAMP, last_error = NULL,
+                    updated_at = CASE WHEN ?2 THEN CURRENT_TIMESTAMP ELSE updated_at END
+                WHERE name = ?3",
+                params![content, changed, name],
+            )
+            .map_err(|e| format!("save subscription failed: {e}"))?;
+            if changed {
+                events::emit(EVENT_REFRESHED, &json!({ "name": name, "enabled": enabled }));
+            }
+            Ok(json!({ "name": name, "ok": true, "changed": changed, "error": null }))
+        }
+        Err(error) => {
+            conn.execute(
+                "UPDATE hosts_profiles SET last_attempt_at = CURRENT_TIMESTAMP, last_error = ?1 WHERE name = ?2",
+                params![error, name],
+            )
+            .map_err(|e| format!("save subscription state failed: {e}"))?;
+            Ok(json!({ "name": name, "ok": false, "changed": false, "error": error }))
+        }
+    }
+}
+
+pub fn refresh_all(conn: &Connection) -> ToolResult {
+    let names = subscription_names(conn)?;
+    let results = names.iter().map(|name| refresh(conn, name)).collect::<ToolResult<Vec<_>>>()?;
+    Ok(Value::Array(results))
+}
+
+fn subscription_names(conn: &Connection) -> ToolResult<Vec<String>> {
+    let mut stmt = conn
+        .prepare("SELECT name FROM hosts_profiles WHERE source IS NOT NULL ORDER BY sort_order, id")
+        .map_err(|e| format!("prepare query failed: {e}"))?;
+    let names = stmt
+        .query_map([], |row| row.get(0))
+        .and_then(|rows| rows.collect::<Result<Vec<String>, _>>())
+        .map_err(|e| format!("query subscriptions failed: {e}"))?;
+    Ok(names)
+}
+
+/// Whether a schedule has fired since `last_attempt` (UTC, as stored by
+/// `CURRENT_TIMESTAMP`). Never-attempted subscriptions are due.
+pub fn is_due(schedule: &str, last_attempt: Option<&str>, now: chrono::DateTime<Local>) -> bool {
+    let Ok(schedule) = cron::schedule(schedule) else {
+        return false;
+    };
+    let Some(last) = last_attempt.and_then(|s| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").ok()) else {
+        return true;
+    };
+    let last = last.and_utc().with_timezone(&Local);
+    schedule.after(&last).next().is_some_and(|next| next <= now)
+}
+
+fn refresh_due() -> ToolResult<()> {
+    let conn = db_conn()?;
+    let due: Vec<String> = {
+        let mut stmt = conn
+            .prepare("SELECT name, COALESCE(refresh_cron, ?1), last_attempt_at FROM hosts_profiles WHERE source IS NOT NULL")
+            .map_err(|e| format!("prepare query failed: {e}"))?;
+        let rows = stmt
+            .query_map(params![DEFAULT_SCHEDULE], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<String>>(2)?))
+            })
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| format!("query subscriptions failed: {e}"))?;
+        let now = Local::now();
+        rows.into_iter()
+            .filter(|(_, schedule, last)| is_due(schedule, last.as_deref(), now))
+            .map(|(name, _, _)| name)
+            .collect()
+    };
+    drop(conn);
+    for name in due {
+        if let Err(e) = refresh_detached(&name) {
+            eprintln!("hosts subscription {name} refresh failed: {}", e.message());
+        }
+    }
+    Ok(())
+}
+
+/// Like `refresh`, but no pooled connection is held during the fetch. A
+/// subscription removed or re-pointed meanwhile is left alone.
+fn refresh_detached(name: &str) -> ToolResult<()> {
+    let (source, _, _) = subscription(&*db_conn()?, name)?;
+    let fetched = fetch(&source);
+    let conn = db_conn()?;
+    let (current, old, enabled) = subscription(&conn, name)?;
+    if current == source {
+        record(&conn, name, &old, enabled, fetched)?;
+    }
+    Ok(())
+}
+
+/// Starts the background refresher; later calls are no-ops.
+pub fn start_scheduler() -> ToolResult<()> {
+    if SCHEDULER_STARTED.swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
+    thread::Builder::new()
+        .name("hosts-subscriptions".into())
+        .spawn(|| loop {
+            if let Err(e) = refresh_due() {
+                eprintln!("hosts subscription refresh failed: {}", e.message());
+            }
+            thread::sleep(TICK);
+        })
+        .map_err(|e| format!("spawn hosts subscription scheduler failed: {e}"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::io::Write;
+    use std::net::TcpListener;
+
+    fn serve_once(status: &str, body: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let url = format!("http://{}/hosts.txt", listener.local_addr().expect("addr"));
+        let status = status.to_string();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().expect("accept");
+            let mut buf = [0u8; 1024];
+            let _ = stream.read(&mut buf);
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+        });
+        url
+    }
+
+    #[test]
+    fn fetches_from_http_and_rejects_bad_content() {
+        let url = serve_once("200 OK", "# shared\n10.1.0.1 build.corp\n");
+        assert_eq!(fetch(&url).expect("fetch"), "# shared\n10.1.0.1 build.corp\n");
+
+        let url = serve_once("200 OK", "<html>login required</html>");
+        assert!(fetch(&url).is_err());
+        let url = serve_once("500 Internal Server Error", "10.1.0.1 build.corp\n");
+        assert!(fetch(&url).is_err());
+        assert!(fetch("/nonexistent/lazycat-hosts").is_err());
+    }
+
+    #[test]
+    fn schedules_from_last_attempt() {
+        let now = Local.with_ymd_and_hms(2026, 5, 1, 10, 30, 0).unwrap();
+        let utc = |h: u32, m: u32| {
+            Local.with_ymd_and_hms(2026, 5, 1, h, m, 0).unwrap().naive_utc().format("%Y-%m-%d %H:%M:%S").to_string()
+        };
+        assert!(is_due(DEFAULT_SCHEDULE, None, now));
+        assert!(is_due(DEFAULT_SCHEDULE, Some(&utc(9, 59)), now));
+        assert!(!is_due(DEFAULT_SCHEDULE, Some(&utc(10, 0)), now));
+        assert!(is_due("*/10 * * * *", Some(&utc(10, 15)), now));
+        assert!(!is_due("not a cron", None, now));
+    }
+
+    #[test]
+    fn scheduled_refresh_records_each_subscription() {
+        crate::tools::helpers::use_test_data_dir();
+        let broken = format!("broken-{}", uuid::Uuid::new_v4());
+        let good = format!("good-{}", uuid::Uuid::new_v4());
+        let url = serve_once("200 OK", "10.1.0.2 ci.corp\n");
+        {
+            let conn = db_conn().expect("db");
+            for (name, source, order) in [(&broken, "/nonexistent/lazycat-hosts", 1), (&good, url.as_str(), 2)] {
+                conn.execute(
+                    "INSERT INTO hosts_profiles(name, content, enabled, sort_order, source) VALUES(?1, '', 0, ?2, ?3)",
+                    params![name, order, source],
+                )
+                .expect("insert");
+            }
+        }
+        refresh_due().expect("refresh due");
+
+        let conn = db_conn().expect("db");
+        let state = |name: &str| -> (String, Option<String>) {
+            conn.query_row(
+                "SELECT content, last_error FROM hosts_profiles WHERE name = ?1",
+                params![name],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .expect("query")
+        };
+        let (content, error) = state(&broken);
+        assert_eq!(content, "");
+        assert!(error.is_some());
+        assert_eq!(state(&good), ("10.1.0.2 ci.corp\n".to_string(), None));
+    }
+}
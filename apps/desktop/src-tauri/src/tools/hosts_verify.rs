@@ -0,0 +1,121 @@
+//! Checking that the system resolver answers with what the hosts file says.
+//!
+//! Lookups go through `getaddrinfo` (via `ToSocketAddrs`), the same path
+//! browsers and most tools take, so caches and NSS order are accounted for.
+
+use std::collections::BTreeMap;
+use std::net::{IpAddr, ToSocketAddrs};
+use std::thread;
+
+use serde_json::{json, Value};
+
+use super::hosts_merge::host_mappings;
+
+/// Hosts beyond this are reported as skipped; block lists can hold
+/// tens of thousands of names.
+const MAX_HOSTS: usize = 256;
+const LOOKUP_THREADS: usize = 16;
+
+/// The effective address per family of every hostname: resolvers take the
+/// first matching line, so later mappings are ignored.
+pub fn expected(content: &str) -> BTreeMap<String, Vec<IpAddr>> {
+    let mut out: BTreeMap<String, Vec<IpAddr>> = BTreeMap::new();
+    for (ip, host) in host_mappings(content) {
+        let ips = out.entry(host).or_default();
+        if !ips.iter().any(|known| known.is_ipv6() == ip.is_ipv6()) {
+            ips.push(ip);
+        }
+    }
+    out
+}
+
+/// Passes when an expected address came back and no other address of an
+/// expected family did. Families the hosts file doesn't mention are ignored,
+/// e.g. `::1` that nss-myhostname adds for `localhost`.
+pub fn matches(expected: &[IpAddr], resolved: &[IpAddr]) -> bool {
+    let hit = resolved.iter().any(|ip| expected.contains(ip));
+    let stray = resolved
+        .iter()
+        .any(|ip| !expected.contains(ip) && expected.iter().any(|e| e.is_ipv6() == ip.is_ipv6()));
+    hit && !stray
+}
+
+fn lookup(host: &str) -> Result<Vec<IpAddr>, String> {
+    let mut ips: Vec<IpAddr> = Vec::new();
+    for addr in (host, 0).to_socket_addrs().map_err(|e| e.to_string())? {
+        if !ips.contains(&addr.ip()) {
+            ips.push(addr.ip());
+        }
+    }
+    Ok(ips)
+}
+
+/// Resolves every hostname of `content` and reports pass/fail per host.
+pub fn verify(content: &str) -> Value {
+    let expected = expected(content);
+    let skipped = expected.len().saturating_sub(MAX_HOSTS);
+    let targets: Vec<(&String, &Vec<IpAddr>)> = expected.iter().take(MAX_HOSTS).collect();
+    let chunk = targets.len().div_ceil(LOOKUP_THREADS).max(1);
+
+    let mut hosts: Vec<Value> = thread::scope(|scope| {
+        let workers: Vec<_> = targets
+            .chunks(chunk)
+            .map(|batch| {
+                scope.spawn(move || {
+                    batch
+                        .iter()
+                        .map(|(host, want)| {
+                            let resolved = lookup(host);
+                            let ok = resolved.as_ref().is_ok_and(|ips| matches(want, ips));
+                            json!({
+                                "hostname": host,
+                                "expected": want.iter().map(IpAddr::to_string).collect::<Vec<_>>(),
+                                "resolved": resolved.as_ref().map(|ips| ips.iter().map(IpAddr::to_string).collect::<Vec<_>>()).unwrap_or_default(),
+                                "ok": ok,
+                                "error": resolved.err(),
+                            })
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        workers.into_iter().flat_map(|w| w.join().unwrap_or_default()).collect()
+    });
+    // Failures first so the UI can show them without scrolling.
+    hosts.sort_by_key(|h| h["ok"].as_bool().unwrap_or(false));
+    let passed = hosts.iter().filter(|h| h["ok"] == true).count();
+    json!({
+        "passed": passed,
+        "failed": hosts.len() - passed,
+        "skipped": skipped,
+        "hosts": hosts,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn expects_first_address_per_family() {
+        let want = expected("10.0.0.1 api.test\n10.0.0.2 api.test\nfe80::1 api.test\n# 10.0.0.3 off.test\n");
+        assert_eq!(want.len(), 1);
+        assert_eq!(want["api.test"], vec![ip("10.0.0.1"), ip("fe80::1")]);
+
+        let v4 = [ip("127.0.0.1")];
+        assert!(matches(&v4, &[ip("127.0.0.1"), ip("::1")]));
+        assert!(!matches(&v4, &[ip("127.0.0.1"), ip("10.0.0.9")]));
+        assert!(!matches(&v4, &[ip("::1")]));
+        assert!(!matches(&v4, &[]));
+    }
+
+    #[test]
+    fn verifies_localhost() {
+        let report = verify("127.0.0.1 localhost\n");
+        assert_eq!(report["passed"], 1, "{report}");
+    }
+}
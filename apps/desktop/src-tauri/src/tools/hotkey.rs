@@ -2,6 +2,9 @@ use serde_json::Value;
 use std::path::PathBuf;
 use std::sync::OnceLock;
 
+use super::error::{ToolError, ToolResult};
+use super::registry::{ActionSpec, DomainSpec, FieldSpec};
+
 pub static HOTKEY_MAPPINGS_DIR: OnceLock<PathBuf> = OnceLock::new();
 
 #[cfg(target_os = "windows")]
@@ -762,7 +765,26 @@ mod win {
     }
 }
 
-pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
+const SHORTCUT: &[FieldSpec] = &[FieldSpec::required("shortcut", "string", "e.g. Ctrl+Alt+K")];
+
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "hotkey",
+    description: "Global hotkey conflict detection (Windows only)",
+    execute,
+    actions: &[
+        ActionSpec::new("check", "Check whether a shortcut can be registered", SHORTCUT, "object: availability report"),
+        ActionSpec::new(
+            "scan",
+            "Check a list of shortcuts, or the defaults when empty",
+            &[FieldSpec::optional("shortcuts", "array", "shortcut strings")],
+            "array: availability reports",
+        ),
+        ActionSpec::new("mappings", "Known application hotkey mappings", &[], "array: mapping entries"),
+        ActionSpec::new("detect_owner", "Guess which application owns a shortcut", SHORTCUT, "object: suspects and signals"),
+    ],
+};
+
+pub fn execute(action: &str, payload: &Value) -> ToolResult {
     #[cfg(target_os = "windows")]
     {
         match action {
@@ -770,8 +792,8 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
                 let shortcut = payload
                     .get("shortcut")
                     .and_then(|v| v.as_str())
-                    .ok_or("缺少 shortcut 参数")?;
-                win::check_hotkey(shortcut)
+                    .ok_or_else(|| ToolError::invalid_field("shortcut", "缺少 shortcut 参数"))?;
+                Ok(win::check_hotkey(shortcut)?)
             }
             "scan" => {
                 let shortcuts = payload
@@ -785,23 +807,23 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
                 // If empty array provided, treat as None (use defaults)
                 let shortcuts =
                     shortcuts.and_then(|v| if v.is_empty() { None } else { Some(v) });
-                win::scan_hotkeys(shortcuts)
+                Ok(win::scan_hotkeys(shortcuts)?)
             }
-            "mappings" => win::get_mappings(),
+            "mappings" => Ok(win::get_mappings()?),
             "detect_owner" => {
                 let shortcut = payload
                     .get("shortcut")
                     .and_then(|v| v.as_str())
-                    .ok_or("缺少 shortcut 参数")?;
-                win::detect_hotkey_owner(shortcut)
+                    .ok_or_else(|| ToolError::invalid_field("shortcut", "缺少 shortcut 参数"))?;
+                Ok(win::detect_hotkey_owner(shortcut)?)
             }
-            _ => Err(format!("unsupported hotkey action: {action}")),
+            _ => Err(ToolError::unsupported("hotkey", action)),
         }
     }
 
     #[cfg(not(target_os = "windows"))]
     {
         let _ = (action, payload);
-        Err("快捷键冲突检测仅支持 Windows 平台".to_string())
+        Err(ToolError::Execution("快捷键冲突检测仅支持 Windows 平台".to_string()))
     }
 }
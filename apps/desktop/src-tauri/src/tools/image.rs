@@ -4,11 +4,46 @@ use std::fs;
 use std::io::BufWriter;
 use std::path::PathBuf;
 
-pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
+use super::error::{ToolError, ToolResult};
+use super::jobs::report_progress;
+use super::registry::{ActionSpec, DomainSpec, FieldSpec};
+
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "image",
+    description: "Image conversion and inspection",
+    execute,
+    actions: &[
+        ActionSpec::new(
+            "convert",
+            "Crop, resize and re-encode an image",
+            &[
+                FieldSpec::required("inputPath", "string", "source image"),
+                FieldSpec::required("outputPath", "string", "target image"),
+                FieldSpec::optional("format", "string", "png | jpeg | webp | avif, default png"),
+                FieldSpec::optional("quality", "integer", "JPEG quality 0-100, default 80"),
+                FieldSpec::optional("width", "integer", "resize width"),
+                FieldSpec::optional("height", "integer", "resize height"),
+                FieldSpec::optional("cropX", "integer", "crop left offset"),
+                FieldSpec::optional("cropY", "integer", "crop top offset"),
+                FieldSpec::optional("cropWidth", "integer", "crop width"),
+                FieldSpec::optional("cropHeight", "integer", "crop height"),
+            ],
+            "object: { outputPath, width, height, size }",
+        ),
+        ActionSpec::new(
+            "info",
+            "Dimensions, size and format of an image",
+            &[FieldSpec::required("inputPath", "string", "image path")],
+            "object: { width, height, size, format }",
+        ),
+    ],
+};
+
+pub fn execute(action: &str, payload: &Value) -> ToolResult {
     match action {
         "convert" => image_convert(payload),
         "info" => image_info(payload),
-        _ => Err(format!("unsupported image action: {action}")),
+        _ => Err(ToolError::unsupported("image", action)),
     }
 }
 
@@ -31,10 +66,10 @@ fn detect_format_name(path: &std::path::Path) -> String {
     }
 }
 
-fn image_info(payload: &Value) -> Result<Value, String> {
+fn image_info(payload: &Value) -> ToolResult {
     let input_path = PathBuf::from(payload["inputPath"].as_str().unwrap_or_default());
     if !input_path.exists() {
-        return Err("input image not found".into());
+        return Err(ToolError::not_found("input image not found"));
     }
     let metadata = fs::metadata(&input_path).map_err(|e| format!("stat failed: {e}"))?;
     let img = image::open(&input_path).map_err(|e| format!("open image failed: {e}"))?;
@@ -47,13 +82,15 @@ fn image_info(payload: &Value) -> Result<Value, String> {
     }))
 }
 
-fn image_convert(payload: &Value) -> Result<Value, String> {
+fn image_convert(payload: &Value) -> ToolResult {
     let input_path = PathBuf::from(payload["inputPath"].as_str().unwrap_or_default());
     let output_path = PathBuf::from(payload["outputPath"].as_str().unwrap_or_default());
     if !input_path.exists() {
-        return Err("input image not found".into());
+        return Err(ToolError::not_found("input image not found"));
     }
+    report_progress(json!({ "step": "decode", "stepIndex": 1, "totalSteps": 3 }))?;
     let mut img = image::open(&input_path).map_err(|e| format!("open image failed: {e}"))?;
+    report_progress(json!({ "step": "transform", "stepIndex": 2, "totalSteps": 3 }))?;
 
     // Apply cropping if cropWidth and cropHeight provided
     if let (Some(cw), Some(ch)) = (payload["cropWidth"].as_u64(), payload["cropHeight"].as_u64()) {
@@ -75,6 +112,7 @@ fn image_convert(payload: &Value) -> Result<Value, String> {
         img
     };
 
+    report_progress(json!({ "step": "encode", "stepIndex": 3, "totalSteps": 3 }))?;
     let format = payload["format"].as_str().unwrap_or("png").to_lowercase();
     let quality = payload["quality"].as_u64().unwrap_or(80).min(100) as u8;
 
@@ -0,0 +1,358 @@
+use chrono::Local;
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::error::{ToolError, ToolResult};
+use super::events;
+use super::registry::{ActionSpec, DomainSpec, FieldSpec};
+
+/// Emitted while a job runs: `{ jobId, domain, action, progress }`.
+pub const EVENT_PROGRESS: &str = "tool-job-progress";
+/// Emitted once when a job ends, carrying the full job status.
+pub const EVENT_FINISHED: &str = "tool-job-finished";
+
+/// Finished jobs kept for `status`/`list`; older ones are dropped first.
+const MAX_FINISHED_JOBS: usize = 50;
+/// Progress events are throttled to this interval; the registry is always current.
+const PROGRESS_EVENT_INTERVAL: Duration = Duration::from_millis(100);
+
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "jobs",
+    description: "Run tool actions in the background with progress and cancellation",
+    execute,
+    actions: &[
+        ActionSpec::new(
+            "start",
+            "Start a tool action as a background job",
+            &[
+                FieldSpec::required("domain", "string", "tool domain"),
+                FieldSpec::required("action", "string", "tool action"),
+                FieldSpec::optional("payload", "object", "payload for the action"),
+            ],
+            "object: job status",
+        ),
+        ActionSpec::new(
+            "cancel",
+            "Request cancellation of a running job",
+            &[FieldSpec::required("jobId", "string", "job id")],
+            "object: { jobId, cancelled }",
+        ),
+        ActionSpec::new(
+            "status",
+            "Status, progress and result of one job",
+            &[FieldSpec::required("jobId", "string", "job id")],
+            "object: { jobId, domain, action, status, progress, result, error, startedAt, finishedAt }",
+        ),
+        ActionSpec::new("list", "Running and recently finished jobs", &[], "array of job status"),
+    ],
+};
+
+pub fn execute(action: &str, payload: &Value) -> ToolResult {
+    match action {
+        "start" => job_start(payload),
+        "cancel" => job_cancel(payload),
+        "status" => job_status(payload),
+        "list" => job_list(),
+        _ => Err(ToolError::unsupported("jobs", action)),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum JobStatus {
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            JobStatus::Running => "running",
+            JobStatus::Succeeded => "succeeded",
+            JobStatus::Failed => "failed",
+            JobStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+struct Job {
+    id: String,
+    domain: String,
+    action: String,
+    status: JobStatus,
+    progress: Value,
+    result: Option<Value>,
+    error: Option<Value>,
+    started_at: String,
+    finished_at: Option<String>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Job {
+    fn to_json(&self) -> Value {
+        json!({
+            "jobId": self.id,
+            "domain": self.domain,
+            "action": self.action,
+            "status": self.status.as_str(),
+            "progress": self.progress,
+            "result": self.result,
+            "error": self.error,
+            "startedAt": self.started_at,
+            "finishedAt": self.finished_at,
+        })
+    }
+}
+
+/// Jobs in start order.
+static JOBS: OnceLock<Mutex<Vec<Job>>> = OnceLock::new();
+
+fn jobs() -> MutexGuard<'static, Vec<Job>> {
+    JOBS.get_or_init(|| Mutex::new(Vec::new()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// The job running on the current thread, if any.
+struct JobContext {
+    id: String,
+    cancel: Arc<AtomicBool>,
+    last_event: Option<Instant>,
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<JobContext>> = const { RefCell::new(None) };
+}
+
+/// Report progress of the current job and stop early if it was cancelled.
+///
+/// Long-running actions call this between units of work. Outside a job
+/// (plain `tool_execute`) it does nothing and always returns `Ok`.
+pub fn report_progress(progress: Value) -> ToolResult<()> {
+    CURRENT.with(|current| {
+        let mut current = current.borrow_mut();
+        let Some(ctx) = current.as_mut() else {
+            return Ok(());
+        };
+        if ctx.cancel.load(Ordering::Relaxed) {
+            return Err(ToolError::Cancelled);
+        }
+        let event = {
+            let mut jobs = jobs();
+            let Some(job) = jobs.iter_mut().find(|j| j.id == ctx.id) else {
+                return Ok(());
+            };
+            job.progress = progress;
+            json!({
+                "jobId": job.id,
+                "domain": job.domain,
+                "action": job.action,
+                "progress": job.progress,
+            })
+        };
+        if ctx.last_event.is_none_or(|t| t.elapsed() >= PROGRESS_EVENT_INTERVAL) {
+            ctx.last_event = Some(Instant::now());
+            events::emit(EVENT_PROGRESS, &event);
+        }
+        Ok(())
+    })
+}
+
+fn job_start(payload: &Value) -> ToolResult {
+    let domain = payload["domain"].as_str().unwrap_or_default().to_string();
+    let action = payload["action"].as_str().unwrap_or_default().to_string();
+    if domain.is_empty() {
+        return Err(ToolError::invalid_field("domain", "domain is required"));
+    }
+    if action.is_empty() {
+        return Err(ToolError::invalid_field("action", "action is required"));
+    }
+    if domain == SPEC.name {
+        return Err(ToolError::invalid_field("domain", "jobs cannot start other jobs"));
+    }
+    super::registry::find_step(super::DOMAINS, &domain, &action)?;
+    let tool_payload = match &payload["payload"] {
+        Value::Null => json!({}),
+        v => v.clone(),
+    };
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let cancel = Arc::new(AtomicBool::new(false));
+    let job = Job {
+        id: id.clone(),
+        domain: domain.clone(),
+        action: action.clone(),
+        status: JobStatus::Running,
+        progress: Value::Null,
+        result: None,
+        error: None,
+        started_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        finished_at: None,
+        cancel: cancel.clone(),
+    };
+    let snapshot = job.to_json();
+    jobs().push(job);
+
+    let thread_id = id.clone();
+    let via_api = super::api::serving();
+    thread::Builder::new()
+        .name(format!("job-{domain}.{action}"))
+        .spawn(move || {
+            CURRENT.with(|c| {
+                *c.borrow_mut() = Some(JobContext { id: thread_id.clone(), cancel, last_event: None })
+            });
+            let outcome = super::api::serve_as(via_api, || super::execute_tool(&domain, &action, &tool_payload));
+            CURRENT.with(|c| *c.borrow_mut() = None);
+            finish(&thread_id, outcome);
+        })
+        .map_err(|e| {
+            jobs().retain(|j| j.id != id);
+            format!("spawn job thread failed: {e}")
+        })?;
+    Ok(snapshot)
+}
+
+fn finish(id: &str, outcome: ToolResult) {
+    let event = {
+        let mut jobs = jobs();
+        let Some(job) = jobs.iter_mut().find(|j| j.id == id) else {
+            return;
+        };
+        match outcome {
+            Ok(data) => {
+                job.status = JobStatus::Succeeded;
+                job.result = Some(data);
+            }
+            Err(err) => {
+                job.status = if err == ToolError::Cancelled {
+                    JobStatus::Cancelled
+                } else {
+                    JobStatus::Failed
+                };
+                job.error = Some(json!({
+                    "code": err.code(),
+                    "message": err.message(),
+                    "details": err.details(),
+                }));
+            }
+        }
+        job.finished_at = Some(Local::now().format("%Y-%m-%d %H:%M:%S").to_string());
+        let event = job.to_json();
+        prune_finished(&mut jobs);
+        event
+    };
+    events::emit(EVENT_FINISHED, &event);
+}
+
+fn prune_finished(jobs: &mut Vec<Job>) {
+    let finished = jobs.iter().filter(|j| j.status != JobStatus::Running).count();
+    let mut excess = finished.saturating_sub(MAX_FINISHED_JOBS);
+    jobs.retain(|j| {
+        if excess > 0 && j.status != JobStatus::Running {
+            excess -= 1;
+            return false;
+        }
+        true
+    });
+}
+
+fn job_id(payload: &Value) -> ToolResult<&str> {
+    match payload["jobId"].as_str().unwrap_or_default() {
+        "" => Err(ToolError::invalid_field("jobId", "jobId is required")),
+        id => Ok(id),
+    }
+}
+
+fn job_cancel(payload: &Value) -> ToolResult {
+    let id = job_id(payload)?;
+    let jobs = jobs();
+    let job = jobs
+        .iter()
+        .find(|j| j.id == id)
+        .ok_or_else(|| ToolError::not_found(format!("job not found: {id}")))?;
+    let cancelled = job.status == JobStatus::Running;
+    if cancelled {
+        job.cancel.store(true, Ordering::Relaxed);
+    }
+    Ok(json!({ "jobId": id, "cancelled": cancelled }))
+}
+
+fn job_status(payload: &Value) -> ToolResult {
+    let id = job_id(payload)?;
+    jobs()
+        .iter()
+        .find(|j| j.id == id)
+        .map(Job::to_json)
+        .ok_or_else(|| ToolError::not_found(format!("job not found: {id}")))
+}
+
+fn job_list() -> ToolResult {
+    Ok(Value::Array(jobs().iter().map(Job::to_json).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wait_finished(id: &str) -> Value {
+        for _ in 0..200 {
+            let status = job_status(&json!({ "jobId": id })).expect("status");
+            if status["status"] != "running" {
+                return status;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("job {id} did not finish");
+    }
+
+    #[test]
+    fn job_runs_action_and_keeps_result() {
+        let started = job_start(&json!({ "domain": "gen", "action": "uuid" })).expect("start");
+        let id = started["jobId"].as_str().expect("jobId").to_string();
+        let finished = wait_finished(&id);
+        assert_eq!(finished["status"], "succeeded");
+        assert!(finished["result"].is_object() || finished["result"].is_string());
+    }
+
+    #[test]
+    fn start_rejects_unknown_action_and_nested_jobs() {
+        let err = job_start(&json!({ "domain": "gen", "action": "nope" })).expect_err("should fail");
+        assert_eq!(err.code(), "UNSUPPORTED_ACTION");
+        let err = job_start(&json!({ "domain": "jobs", "action": "list" })).expect_err("should fail");
+        assert_eq!(err.code(), "INVALID_PAYLOAD");
+    }
+
+    #[test]
+    fn cancelled_split_stops_and_removes_its_parts() {
+        let dir = std::env::temp_dir().join(format!("lazycat-split-{}", uuid::Uuid::new_v4()));
+        let parts = dir.join("parts");
+        std::fs::create_dir_all(&dir).expect("dir");
+        let source = dir.join("big.bin");
+        std::fs::write(&source, vec![7u8; 32 * 1024 * 1024]).expect("source");
+
+        let started = job_start(&json!({
+            "domain": "file",
+            "action": "split",
+            "payload": { "sourcePath": source, "outputDir": parts, "chunkSizeMb": 1 },
+        }))
+        .expect("start");
+        let id = started["jobId"].as_str().expect("jobId").to_string();
+        assert_eq!(job_cancel(&json!({ "jobId": id })).expect("cancel")["cancelled"], true);
+
+        let finished = wait_finished(&id);
+        assert_eq!(finished["status"], "cancelled");
+        let left = std::fs::read_dir(&parts).map(|d| d.count()).unwrap_or(0);
+        assert_eq!(left, 0, "partial parts left behind");
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn progress_outside_job_is_noop() {
+        assert!(report_progress(json!({ "step": "x" })).is_ok());
+    }
+}
@@ -1,7 +1,22 @@
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use serde_json::{json, Value};
 
-pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
+use super::error::{ToolError, ToolResult};
+use super::registry::{ActionSpec, DomainSpec, FieldSpec};
+
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "jwt",
+    description: "JSON Web Token inspection",
+    execute,
+    actions: &[ActionSpec::new(
+        "decode",
+        "Decode header and payload without verifying the signature",
+        &[FieldSpec::required("token", "string", "compact JWT")],
+        "object: { header, payload, signature, expired?, exp_readable? }",
+    )],
+};
+
+pub fn execute(action: &str, payload: &Value) -> ToolResult {
     match action {
         "decode" => {
             let token = payload["token"]
@@ -11,20 +26,20 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
 
             let parts: Vec<&str> = token.split('.').collect();
             if parts.len() != 3 {
-                return Err("Invalid JWT: expected 3 parts separated by '.'".to_string());
+                return Err(ToolError::invalid_field("token", "Invalid JWT: expected 3 parts separated by '.'"));
             }
 
             let header_bytes = URL_SAFE_NO_PAD
                 .decode(parts[0])
-                .map_err(|e| format!("Failed to decode header: {e}"))?;
+                .map_err(|e| ToolError::parse(format!("Failed to decode header: {e}")))?;
             let header: Value = serde_json::from_slice(&header_bytes)
-                .map_err(|e| format!("Failed to parse header JSON: {e}"))?;
+                .map_err(|e| ToolError::json("Failed to parse header JSON", &e))?;
 
             let payload_bytes = URL_SAFE_NO_PAD
                 .decode(parts[1])
-                .map_err(|e| format!("Failed to decode payload: {e}"))?;
+                .map_err(|e| ToolError::parse(format!("Failed to decode payload: {e}")))?;
             let payload_val: Value = serde_json::from_slice(&payload_bytes)
-                .map_err(|e| format!("Failed to parse payload JSON: {e}"))?;
+                .map_err(|e| ToolError::json("Failed to parse payload JSON", &e))?;
 
             let signature = hex::encode(
                 URL_SAFE_NO_PAD
@@ -52,6 +67,6 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
 
             Ok(result)
         }
-        _ => Err(format!("unsupported jwt action: {action}")),
+        _ => Err(ToolError::unsupported("jwt", action)),
     }
 }
@@ -0,0 +1,180 @@
+//! Maintenance of the live database: integrity checks, VACUUM/ANALYZE, a
+//! full rebuild of the snippet search index and size statistics.
+//!
+//! Everything runs on a pooled connection from `db_conn`, so it works on an
+//! encrypted database once it is unlocked.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rusqlite::Connection;
+use serde_json::{json, Value};
+
+use super::error::{ToolError, ToolResult};
+use super::helpers::{db_conn, db_path};
+use super::registry::{ActionSpec, DomainSpec, FieldSpec};
+use super::snippets;
+
+/// Problems past this many are not reported by `check`.
+const MAX_MESSAGES: i64 = 100;
+
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "maintenance",
+    description: "Database integrity checks, VACUUM/ANALYZE, search index rebuild and statistics",
+    execute,
+    actions: &[
+        ActionSpec::new(
+            "check",
+            "Run PRAGMA integrity_check, or the faster quick_check",
+            &[FieldSpec::optional("quick", "boolean", "run quick_check instead (default false)")],
+            "object: { ok, quick, messages }",
+        ),
+        ActionSpec::new(
+            "vacuum",
+            "Rebuild the database file to reclaim free pages",
+            &[],
+            "object: { ok, sizeBefore, sizeAfter }",
+        ),
+        ActionSpec::new("analyze", "Refresh the query planner statistics", &[], "object: { ok }"),
+        ActionSpec::new(
+            "rebuild_fts",
+            "Rebuild the snippet search index from entries, fragments and tags",
+            &[],
+            "object: { ok, entries }",
+        ),
+        ActionSpec::new(
+            "stats",
+            "Row counts per table and database/WAL file sizes",
+            &[],
+            "object: { path, databaseSize, walSize, pageSize, pageCount, freePages, tables: [{ name, rows }] }",
+        ),
+    ],
+};
+
+pub fn execute(action: &str, payload: &Value) -> ToolResult {
+    let conn = db_conn()?;
+    match action {
+        "check" => {
+            let quick = payload["quick"].as_bool().unwrap_or(false);
+            let messages = check(&conn, quick)?;
+            Ok(json!({
+                "ok": messages.len() == 1 && messages[0] == "ok",
+                "quick": quick,
+                "messages": messages,
+            }))
+        }
+        "vacuum" => {
+            let path = db_path()?;
+            let size_before = file_size(&path) + file_size(&wal_path(&path));
+            vacuum(&conn)?;
+            Ok(json!({
+                "ok": true,
+                "sizeBefore": size_before,
+                "sizeAfter": file_size(&path) + file_size(&wal_path(&path)),
+            }))
+        }
+        "analyze" => {
+            conn.execute_batch("ANALYZE")
+                .map_err(|e| ToolError::Execution(format!("analyze failed: {e}")))?;
+            Ok(json!({ "ok": true }))
+        }
+        "rebuild_fts" => Ok(json!({ "ok": true, "entries": rebuild_fts(&conn)? })),
+        "stats" => stats(&conn, &db_path()?),
+        _ => Err(ToolError::unsupported("maintenance", action)),
+    }
+}
+
+fn check(conn: &Connection, quick: bool) -> ToolResult<Vec<String>> {
+    let pragma = if quick { "quick_check" } else { "integrity_check" };
+    conn.prepare(&format!("PRAGMA {pragma}({MAX_MESSAGES})"))
+        .and_then(|mut stmt| stmt.query_map([], |row| row.get(0))?.collect())
+        .map_err(|e| ToolError::Execution(format!("{pragma} failed: {e}")))
+}
+
+/// VACUUM writes through the WAL, so the log is truncated afterwards to
+/// actually give the space back.
+fn vacuum(conn: &Connection) -> ToolResult<()> {
+    conn.execute_batch("VACUUM")
+        .map_err(|e| ToolError::Execution(format!("vacuum failed: {e}")))?;
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))
+        .map_err(|e| ToolError::Execution(format!("checkpoint failed: {e}")))
+}
+
+/// Rebuilds `snippet_fts` in one transaction, so searches never see a
+/// half-filled index. Returns the number of indexed entries.
+fn rebuild_fts(conn: &Connection) -> ToolResult<i64> {
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("begin transaction failed: {e}"))?;
+    snippets::rebuild_fts(&tx)?;
+    let entries: i64 = tx
+        .query_row("SELECT count(*) FROM snippet_entries", [], |r| r.get(0))
+        .map_err(|e| format!("count entries failed: {e}"))?;
+    tx.commit().map_err(|e| format!("commit failed: {e}"))?;
+    Ok(entries)
+}
+
+fn stats(conn: &Connection, path: &Path) -> ToolResult {
+    let names: Vec<String> = conn
+        .prepare("SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name")
+        .and_then(|mut stmt| stmt.query_map([], |row| row.get(0))?.collect())
+        .map_err(|e| format!("list tables failed: {e}"))?;
+    let mut tables = Vec::with_capacity(names.len());
+    for name in names {
+        let rows: i64 = conn
+            .query_row(&format!("SELECT count(*) FROM \"{}\"", name.replace('"', "\"\"")), [], |r| r.get(0))
+            .map_err(|e| format!("count rows of {name} failed: {e}"))?;
+        tables.push(json!({ "name": name, "rows": rows }));
+    }
+    let pragma = |name: &str| -> ToolResult<i64> {
+        conn.query_row(&format!("PRAGMA {name}"), [], |r| r.get(0))
+            .map_err(|e| ToolError::Execution(format!("read {name} failed: {e}")))
+    };
+    Ok(json!({
+        "path": path.to_string_lossy(),
+        "databaseSize": file_size(path),
+        "walSize": file_size(&wal_path(path)),
+        "pageSize": pragma("page_size")?,
+        "pageCount": pragma("page_count")?,
+        "freePages": pragma("freelist_count")?,
+        "tables": tables,
+    }))
+}
+
+fn wal_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push("-wal");
+    name.into()
+}
+
+fn file_size(path: &Path) -> u64 {
+    fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::migrations::migrate;
+
+    #[test]
+    fn rebuilds_a_stale_search_index() {
+        let conn = Connection::open_in_memory().expect("open");
+        migrate(&conn).expect("migrate");
+        conn.execute_batch(
+            "INSERT INTO snippet_entries (id, title, description) VALUES (1, 'curl upload', 'multipart form');
+             INSERT INTO snippet_entry_tags (entry_id, tag) VALUES (1, 'http');
+             DELETE FROM snippet_fts;",
+        )
+        .expect("seed");
+        let hits = |term: &str| -> i64 {
+            conn.query_row("SELECT count(*) FROM snippet_fts WHERE snippet_fts MATCH ?1", [term], |r| r.get(0))
+                .expect("search")
+        };
+        assert_eq!(hits("multipart"), 0);
+
+        assert_eq!(rebuild_fts(&conn).expect("rebuild"), 1);
+        assert_eq!(hits("multipart"), 1);
+        assert_eq!(hits("http"), 1);
+        assert_eq!(check(&conn, true).expect("check"), vec!["ok".to_string()]);
+    }
+}
@@ -0,0 +1,523 @@
+//! Full-text index over the offline manual HTML.
+//!
+//! The index lives in its own `manual-index.sqlite` next to the main database:
+//! it is derived data that can be rebuilt at any time, and keeping it apart
+//! keeps the main database (and its backups) small. FTS5 uses the trigram
+//! tokenizer so that Chinese text, which has no word separators, is
+//! searchable by substring.
+
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::UNIX_EPOCH;
+
+use super::error::{ToolError, ToolResult};
+use super::helpers::get_data_dir;
+use super::jobs;
+use super::manual_site::Site;
+
+/// Bump to drop and rebuild existing indexes after a schema or extraction change.
+const INDEX_SCHEMA_VERSION: i64 = 1;
+/// Body text kept per page; long reference pages are truncated.
+const MAX_BODY_CHARS: usize = 60_000;
+/// Pages written per transaction (and per progress report).
+const BATCH_SIZE: usize = 200;
+/// Trigram matching needs at least this many characters per term.
+const MIN_TERM_CHARS: usize = 3;
+
+static INDEXING: AtomicBool = AtomicBool::new(false);
+/// Set when the installed manuals change; a run in progress goes again.
+static STALE: AtomicBool = AtomicBool::new(false);
+
+pub fn is_indexing() -> bool {
+    INDEXING.load(Ordering::Relaxed)
+}
+
+fn open_index() -> ToolResult<Connection> {
+    let path = get_data_dir()?.join("manual-index.sqlite");
+    let conn = Connection::open(&path)
+        .map_err(|e| ToolError::Execution(format!("open manual index failed: {e}")))?;
+    let version: i64 = conn
+        .query_row("PRAGMA user_version", [], |r| r.get(0))
+        .map_err(|e| e.to_string())?;
+    if version != INDEX_SCHEMA_VERSION {
+        conn.execute_batch(&format!(
+            "DROP TABLE IF EXISTS pages;
+             DROP TABLE IF EXISTS files;
+             CREATE TABLE files (
+                 manual TEXT NOT NULL,
+                 path TEXT NOT NULL,
+                 mtime INTEGER NOT NULL,
+                 size INTEGER NOT NULL,
+                 page_id INTEGER NOT NULL,
+                 PRIMARY KEY (manual, path)
+             );
+             CREATE VIRTUAL TABLE pages USING fts5(
+                 manual UNINDEXED, path UNINDEXED, title, headings, body,
+                 tokenize = 'trigram'
+             );
+             PRAGMA user_version = {INDEX_SCHEMA_VERSION};"
+        ))
+        .map_err(|e| ToolError::Execution(format!("create manual index failed: {e}")))?;
+    }
+    Ok(conn)
+}
+
+// ── HTML extraction ──────────────────────────────────────────
+
+#[derive(Debug, Default, PartialEq)]
+pub struct PageText {
+    pub title: String,
+    pub headings: String,
+    pub body: String,
+}
+
+/// Elements whose content is never page text.
+const SKIPPED_ELEMENTS: &[&str] = &["script", "style", "noscript", "template", "svg", "head"];
+/// Elements that do not break words (`<code>map</code>()` stays `map()`).
+const INLINE_ELEMENTS: &[&str] = &[
+    "a", "abbr", "b", "code", "em", "i", "kbd", "mark", "small", "span", "strong", "sub", "sup",
+];
+
+/// Position of the ASCII `needle` at or after `from`, ignoring ASCII case.
+/// Compares in place: it runs once per skipped element, so copying the rest
+/// of the page each time would be quadratic.
+fn find_ci(haystack: &str, needle: &str, from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .as_bytes()
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+        .map(|i| i + from)
+}
+
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let end = rest.bytes().take(12).position(|b| b == b';');
+        let decoded = end.and_then(|end| {
+            let entity = &rest[1..end];
+            let ch = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .and_then(|h| u32::from_str_radix(h, 16).ok())
+                    .or_else(|| entity.strip_prefix('#').and_then(|d| d.parse().ok()))
+                    .and_then(char::from_u32),
+            };
+            ch.map(|c| (c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Append `text` with whitespace collapsed, keeping word boundaries at its edges.
+fn push_text(buf: &mut String, text: &str) {
+    // VitePress heading anchors are zero-width spaces.
+    let text = &text.replace('\u{200b}', "");
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if words.is_empty() {
+        if !text.is_empty() && !buf.is_empty() && !buf.ends_with(' ') {
+            buf.push(' ');
+        }
+        return;
+    }
+    if text.starts_with(char::is_whitespace) && !buf.is_empty() && !buf.ends_with(' ') {
+        buf.push(' ');
+    }
+    buf.push_str(&words.join(" "));
+    if text.ends_with(char::is_whitespace) {
+        buf.push(' ');
+    }
+}
+
+/// Pull the title, h1–h3 headings and visible text out of a page. When the
+/// page has a `<main>` element only its content counts as body, which keeps
+/// navigation and sidebars repeated on every page out of the index.
+pub fn extract_text(html: &str) -> PageText {
+    let has_main = find_ci(html, "<main", 0).is_some();
+    let mut page = PageText::default();
+    let (mut in_title, mut heading_depth, mut main_depth) = (false, 0usize, 0usize);
+    let mut pos = 0;
+    while pos < html.len() {
+        let Some(lt) = html[pos..].find('<').map(|i| i + pos) else {
+            break;
+        };
+        if lt > pos {
+            let text = decode_entities(&html[pos..lt]);
+            if in_title {
+                push_text(&mut page.title, &text);
+            } else {
+                if heading_depth > 0 && (!has_main || main_depth > 0) {
+                    push_text(&mut page.headings, &text);
+                }
+                if !has_main || main_depth > 0 {
+                    push_text(&mut page.body, &text);
+                }
+            }
+        }
+        if html[lt..].starts_with("<!--") {
+            pos = html[lt..].find("-->").map_or(html.len(), |i| lt + i + 3);
+            continue;
+        }
+        let Some(gt) = html[lt..].find('>').map(|i| i + lt) else {
+            break;
+        };
+        let tag = &html[lt + 1..gt];
+        let closing = tag.starts_with('/');
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        pos = gt + 1;
+        match name.as_str() {
+            // <title> sits in <head>, which is otherwise skipped.
+            "head" if !closing => {
+                let end = find_ci(html, "</head", pos).unwrap_or(html.len());
+                if let Some(start) = find_ci(html, "<title", pos).filter(|&s| s < end) {
+                    if let Some(open_end) = html[start..end].find('>') {
+                        let text_start = start + open_end + 1;
+                        let text_end = find_ci(html, "</title", text_start).unwrap_or(end).min(end);
+                        push_text(&mut page.title, &decode_entities(&html[text_start..text_end]));
+                    }
+                }
+                pos = html[end..].find('>').map_or(html.len(), |i| end + i + 1);
+            }
+            n if !closing && SKIPPED_ELEMENTS.contains(&n) && !tag.ends_with('/') => {
+                let end = find_ci(html, &format!("</{n}"), pos).unwrap_or(html.len());
+                pos = html[end..].find('>').map_or(html.len(), |i| end + i + 1);
+            }
+            "title" => in_title = !closing,
+            "h1" | "h2" | "h3" => {
+                heading_depth = if closing { heading_depth.saturating_sub(1) } else { heading_depth + 1 };
+                if closing && (!has_main || main_depth > 0) && !page.headings.is_empty() {
+                    page.headings = format!("{} · ", page.headings.trim_end());
+                }
+            }
+            "main" => main_depth = if closing { main_depth.saturating_sub(1) } else { main_depth + 1 },
+            _ => {}
+        }
+        // Block boundaries separate words even without whitespace in the source.
+        if !INLINE_ELEMENTS.contains(&name.as_str()) && !page.body.is_empty() && !page.body.ends_with(' ') {
+            page.body.push(' ');
+        }
+    }
+    page.title = page.title.trim().to_string();
+    page.headings = page.headings.trim().trim_end_matches('·').trim_end().to_string();
+    page.body = page.body.trim().to_string();
+    if let Some((cut, _)) = page.body.char_indices().nth(MAX_BODY_CHARS) {
+        page.body.truncate(cut);
+    }
+    page
+}
+
+// ── Indexing ─────────────────────────────────────────────────
+
+/// Notes that the installed manuals changed. Returns whether a run is in
+/// progress, which then indexes again before it finishes.
+pub fn mark_stale() -> bool {
+    STALE.store(true, Ordering::SeqCst);
+    INDEXING.load(Ordering::SeqCst)
+}
+
+/// Bring the index up to date with `load_sites()` (manual id → site):
+/// changed pages are re-extracted, removed pages and manuals dropped. Runs
+/// again while `mark_stale` was called meanwhile. Reports progress and stops
+/// early when the running job is cancelled.
+pub fn index(load_sites: impl Fn() -> Vec<(String, Site)>) -> ToolResult {
+    if INDEXING.swap(true, Ordering::SeqCst) {
+        STALE.store(true, Ordering::SeqCst);
+        return Err(ToolError::Execution(
+            "manual index is already being built; it will include the current manuals".into(),
+        ));
+    }
+    loop {
+        STALE.store(false, Ordering::SeqCst);
+        let outcome = index_sites(&load_sites());
+        if outcome.is_ok() && STALE.load(Ordering::SeqCst) {
+            continue;
+        }
+        INDEXING.store(false, Ordering::SeqCst);
+        // A change noted between the check above and the release would
+        // otherwise be missed until the next run.
+        if outcome.is_err() || !STALE.load(Ordering::SeqCst) || INDEXING.swap(true, Ordering::SeqCst) {
+            return outcome;
+        }
+    }
+}
+
+fn index_sites(sites: &[(String, Site)]) -> ToolResult {
+    let mut conn = open_index()?;
+    let db_err = |e: rusqlite::Error| ToolError::Execution(format!("manual index write failed: {e}"));
+
+    // Manuals that are no longer installed.
+    let known: Vec<String> = {
+        let mut stmt = conn.prepare("SELECT DISTINCT manual FROM files").map_err(db_err)?;
+        let rows = stmt.query_map([], |r| r.get(0)).map_err(db_err)?;
+        rows.filter_map(Result::ok).collect()
+    };
+    for manual in known.iter().filter(|m| !sites.iter().any(|(id, _)| id == *m)) {
+        let tx = conn.transaction().map_err(db_err)?;
+        tx.execute("DELETE FROM pages WHERE rowid IN (SELECT page_id FROM files WHERE manual = ?1)", [manual])
+            .map_err(db_err)?;
+        tx.execute("DELETE FROM files WHERE manual = ?1", [manual]).map_err(db_err)?;
+        tx.commit().map_err(db_err)?;
+    }
+
+    let (mut indexed, mut unchanged, mut removed) = (0usize, 0usize, 0usize);
+    for (manual, site) in sites {
+        let pages = site.html_pages();
+        let total = pages.len();
+
+        let mut stale: Vec<String> = {
+            let mut stmt = conn.prepare("SELECT path FROM files WHERE manual = ?1").map_err(db_err)?;
+            let rows = stmt.query_map([manual], |r| r.get(0)).map_err(db_err)?;
+            rows.filter_map(Result::ok).collect()
+        };
+
+        for (batch_no, batch) in pages.chunks(BATCH_SIZE).enumerate() {
+            jobs::report_progress(json!({
+                "manual": manual,
+                "done": batch_no * BATCH_SIZE,
+                "total": total,
+            }))?;
+            let tx = conn.transaction().map_err(db_err)?;
+            for key in batch {
+                stale.retain(|p| p != key);
+                let Some((size, modified)) = site.stat(key) else { continue };
+                let mtime = modified
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |d| d.as_secs() as i64);
+                let size = size as i64;
+                let existing: Option<(i64, i64, i64)> = tx
+                    .query_row(
+                        "SELECT mtime, size, page_id FROM files WHERE manual = ?1 AND path = ?2",
+                        params![manual, key],
+                        |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+                    )
+                    .optional()
+                    .map_err(db_err)?;
+                if let Some((old_mtime, old_size, page_id)) = existing {
+                    if old_mtime == mtime && old_size == size {
+                        unchanged += 1;
+                        continue;
+                    }
+                    tx.execute("DELETE FROM pages WHERE rowid = ?1", [page_id]).map_err(db_err)?;
+                }
+                let Ok(bytes) = site.read(key) else { continue };
+                let text = extract_text(&String::from_utf8_lossy(&bytes));
+                tx.execute(
+                    "INSERT INTO pages (manual, path, title, headings, body) VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![manual, key, text.title, text.headings, text.body],
+                )
+                .map_err(db_err)?;
+                let page_id = tx.last_insert_rowid();
+                tx.execute(
+                    "INSERT OR REPLACE INTO files (manual, path, mtime, size, page_id) VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![manual, key, mtime, size, page_id],
+                )
+                .map_err(db_err)?;
+                indexed += 1;
+            }
+            tx.commit().map_err(db_err)?;
+        }
+
+        let tx = conn.transaction().map_err(db_err)?;
+        for path in &stale {
+            tx.execute(
+                "DELETE FROM pages WHERE rowid IN (SELECT page_id FROM files WHERE manual = ?1 AND path = ?2)",
+                params![manual, path],
+            )
+            .map_err(db_err)?;
+            tx.execute("DELETE FROM files WHERE manual = ?1 AND path = ?2", params![manual, path])
+                .map_err(db_err)?;
+        }
+        tx.commit().map_err(db_err)?;
+        removed += stale.len();
+        jobs::report_progress(json!({ "manual": manual, "done": total, "total": total }))?;
+    }
+
+    let pages: i64 = conn
+        .query_row("SELECT count(*) FROM files", [], |r| r.get(0))
+        .map_err(db_err)?;
+    Ok(json!({ "indexed": indexed, "unchanged": unchanged, "removed": removed, "pages": pages }))
+}
+
+// ── Search ───────────────────────────────────────────────────
+
+#[derive(Debug, PartialEq)]
+pub struct SearchQuery {
+    /// FTS5 MATCH expression for terms long enough for trigram matching.
+    pub fts: Option<String>,
+    /// Shorter terms, matched with LIKE against title and headings.
+    pub short_terms: Vec<String>,
+}
+
+/// Split free text into quoted FTS terms (ANDed) and short LIKE terms.
+pub fn parse_query(query: &str) -> SearchQuery {
+    let mut long = Vec::new();
+    let mut short_terms = Vec::new();
+    for term in query.split_whitespace() {
+        if term.chars().count() >= MIN_TERM_CHARS {
+            long.push(format!("\"{}\"", term.replace('"', "\"\"")));
+        } else {
+            short_terms.push(term.to_string());
+        }
+    }
+    SearchQuery {
+        fts: (!long.is_empty()).then(|| long.join(" AND ")),
+        short_terms,
+    }
+}
+
+fn like_pattern(term: &str) -> String {
+    let escaped = term.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    format!("%{escaped}%")
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    pub manual: String,
+    /// Page path relative to the manual root, `/`-separated.
+    pub path: String,
+    pub title: String,
+    /// Body excerpt with matches wrapped in `<mark>`.
+    pub snippet: String,
+    /// Higher is better.
+    pub score: f64,
+}
+
+impl SearchHit {
+    /// `base_url` is the manual server origin, when it is running.
+    pub fn to_json(&self, base_url: Option<&str>) -> Value {
+        let url = base_url.map(|base| {
+            let encoded: Vec<_> = self.path.split('/').map(|s| urlencoding::encode(s).into_owned()).collect();
+            format!("{base}/manual/{}/{}", self.manual, encoded.join("/"))
+        });
+        json!({
+            "manual": self.manual,
+            "path": self.path,
+            "title": self.title,
+            "snippet": self.snippet,
+            "score": self.score,
+            "url": url,
+        })
+    }
+}
+
+/// Ranked hits; title matches weigh most, then headings, then body text.
+pub fn search(query: &str, manual: Option<&str>, limit: usize) -> ToolResult<Vec<SearchHit>> {
+    let parsed = parse_query(query);
+    if parsed.fts.is_none() && parsed.short_terms.is_empty() {
+        return Err(ToolError::invalid_field("query", "query must not be empty"));
+    }
+    let conn = open_index()?;
+    let mut args: Vec<String> = Vec::new();
+    let mut filters: Vec<String> = Vec::new();
+    if let Some(fts) = &parsed.fts {
+        args.push(fts.clone());
+        filters.push(format!("pages MATCH ?{}", args.len()));
+    }
+    for term in &parsed.short_terms {
+        args.push(like_pattern(term));
+        let n = args.len();
+        filters.push(format!("(title LIKE ?{n} ESCAPE '\\' OR headings LIKE ?{n} ESCAPE '\\')"));
+    }
+    if let Some(manual) = manual {
+        args.push(manual.to_string());
+        filters.push(format!("manual = ?{}", args.len()));
+    }
+    let (snippet, rank) = if parsed.fts.is_some() {
+        (
+            "snippet(pages, 4, '<mark>', '</mark>', '…', 16)",
+            "bm25(pages, 0.0, 0.0, 10.0, 4.0, 1.0)",
+        )
+    } else {
+        // No relevance score without FTS; prefer pages whose title matches.
+        ("substr(body, 1, 160)", "CASE WHEN title LIKE ?1 ESCAPE '\\' THEN -2.0 ELSE -1.0 END")
+    };
+    let sql = format!(
+        "SELECT manual, path, title, {snippet}, {rank} AS rank FROM pages
+         WHERE {} ORDER BY rank, length(title) LIMIT {limit}",
+        filters.join(" AND ")
+    );
+    let mut stmt = conn
+        .prepare(&sql)
+        .map_err(|e| ToolError::Execution(format!("manual search failed: {e}")))?;
+    let rows = stmt
+        .query_map(params_from_iter(args.iter()), |r| {
+            Ok(SearchHit {
+                manual: r.get(0)?,
+                path: r.get(1)?,
+                title: r.get(2)?,
+                snippet: r.get(3)?,
+                score: -r.get::<_, f64>(4)?,
+            })
+        })
+        .map_err(|e| ToolError::Execution(format!("manual search failed: {e}")))?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| ToolError::Execution(format!("manual search failed: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_title_headings_and_main_text() {
+        let html = r#"<!doctype html><html><head><title>Array &amp; Co</title>
+            <script>var nav = "skip";</script></head>
+            <body><nav>Sidebar link</nav>
+            <main><h1>Array.prototype.map()</h1><p>Creates a <b>new</b>&nbsp;array.</p>
+            <!-- comment --><h2>语法</h2><pre>map(fn)</pre></main></body></html>"#;
+        let page = extract_text(html);
+        assert_eq!(page.title, "Array & Co");
+        assert_eq!(page.headings, "Array.prototype.map() · 语法");
+        assert!(page.body.contains("Creates a new array."), "{}", page.body);
+        assert!(page.body.contains("map(fn)"));
+        assert!(!page.body.contains("Sidebar"));
+        assert!(!page.body.contains("skip"));
+    }
+
+    #[test]
+    fn finds_tags_ignoring_case() {
+        let html = "<p>é</p><SCRIPT>x</Script><script>";
+        assert_eq!(find_ci(html, "</script", 0), Some(18));
+        assert_eq!(find_ci(html, "<script", 10), Some(27));
+        assert_eq!(find_ci(html, "<style", 0), None);
+        assert_eq!(find_ci(html, "<p", html.len() + 1), None);
+    }
+
+    #[test]
+    fn splits_query_into_fts_and_short_terms() {
+        let q = parse_query(r#"响应式 v-model a"b go"#);
+        assert_eq!(q.fts.as_deref(), Some(r#""响应式" AND "v-model" AND "a""b""#));
+        assert_eq!(q.short_terms, vec!["go".to_string()]);
+        assert_eq!(like_pattern("a_%"), "%a\\_\\%%");
+    }
+}
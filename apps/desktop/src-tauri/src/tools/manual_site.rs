@@ -0,0 +1,350 @@
+//! File access for manual packages, whether unpacked folders or zip archives.
+//!
+//! Archives are opened once: the central directory is read into an
+//! in-memory map of entry names, and pages are inflated on demand. Range
+//! requests on stored (uncompressed) entries seek straight into the archive
+//! file; compressed entries are inflated once and the last one is cached.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::SystemTime;
+
+use super::error::{ToolError, ToolResult};
+
+pub const MANIFEST_FILE: &str = "manual.json";
+
+/// Where `manual.json` sits inside a zip: at the root, or inside a single
+/// top-level folder. Returns the prefix to strip from entry names.
+pub fn archive_prefix<'a>(names: impl Iterator<Item = &'a str>) -> Option<String> {
+    let mut best: Option<String> = None;
+    for name in names {
+        if let Some(prefix) = name.strip_suffix(MANIFEST_FILE) {
+            if (prefix.is_empty() || prefix.ends_with('/'))
+                && prefix.matches('/').count() <= 1
+                && best.as_ref().is_none_or(|b| prefix.len() < b.len())
+            {
+                best = Some(prefix.to_string());
+            }
+        }
+    }
+    best
+}
+
+/// Entries are never inflated past this size, whatever their header claims,
+/// so a forged size or a zip bomb cannot exhaust memory.
+const MAX_ENTRY_BYTES: u64 = 256 * 1024 * 1024;
+/// Compressed entries up to this size are kept after a range read, so media
+/// seeking inflates them once instead of on every request.
+const RANGE_CACHE_LIMIT: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy)]
+struct ArchiveEntry {
+    index: usize,
+    size: u64,
+    /// Offset of the data in the archive file, for stored entries.
+    stored_at: Option<u64>,
+}
+
+/// A zip manual with its entry table kept in memory.
+pub struct ManualArchive {
+    path: PathBuf,
+    modified: SystemTime,
+    entries: HashMap<String, ArchiveEntry>,
+    dirs: HashSet<String>,
+    /// `None` once closed.
+    zip: Mutex<Option<zip::ZipArchive<File>>>,
+    /// Set by `close`; stored range reads hold it shared while their own
+    /// file handle is open.
+    closed: RwLock<bool>,
+    /// The last compressed entry inflated for a range read.
+    inflated: Mutex<Option<(usize, Arc<Vec<u8>>)>>,
+}
+
+impl ManualArchive {
+    pub fn open(path: &Path) -> ToolResult<Self> {
+        let file = File::open(path).map_err(|e| ToolError::io("open manual archive failed", e))?;
+        let modified = file
+            .metadata()
+            .and_then(|m| m.modified())
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        let mut zip = zip::ZipArchive::new(file)
+            .map_err(|e| ToolError::invalid(format!("{} is not a valid zip archive: {e}", path.display())))?;
+        let prefix = archive_prefix(zip.file_names())
+            .ok_or_else(|| ToolError::invalid(format!("archive has no {MANIFEST_FILE} at its root")))?;
+
+        let mut entries = HashMap::new();
+        let mut dirs = HashSet::new();
+        for index in 0..zip.len() {
+            let entry = zip
+                .by_index_raw(index)
+                .map_err(|e| ToolError::Execution(format!("read zip entry failed: {e}")))?;
+            // enclosed_name rejects absolute paths and `..` components.
+            let Some(name) = entry.enclosed_name() else { continue };
+            let Ok(rel) = name.strip_prefix(&prefix) else { continue };
+            let key = rel_key(rel);
+            if key.is_empty() {
+                continue;
+            }
+            let mut parent = Path::new(&key).parent();
+            while let Some(dir) = parent.filter(|p| !p.as_os_str().is_empty()) {
+                dirs.insert(rel_key(dir));
+                parent = dir.parent();
+            }
+            if entry.is_dir() {
+                dirs.insert(key);
+            } else {
+                let stored = entry.compression() == zip::CompressionMethod::Stored && !entry.encrypted();
+                let stored_at = stored.then(|| entry.data_start());
+                entries.insert(key, ArchiveEntry { index, size: entry.size(), stored_at });
+            }
+        }
+        Ok(ManualArchive {
+            path: path.to_path_buf(),
+            modified,
+            entries,
+            dirs,
+            zip: Mutex::new(Some(zip)),
+            closed: RwLock::new(false),
+            inflated: Mutex::new(None),
+        })
+    }
+
+    /// Releases every handle on the archive file, waiting for reads in
+    /// progress, so it can be replaced or deleted (Windows refuses while a
+    /// handle is open). Later reads fail; the entry table stays usable.
+    pub fn close(&self) {
+        let mut is_closed = self.closed.write().unwrap_or_else(|e| e.into_inner());
+        *is_closed = true;
+        *self.zip.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        *self.inflated.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    fn entry(&self, rel: &str) -> io::Result<ArchiveEntry> {
+        self.entries
+            .get(rel)
+            .copied()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, rel.to_string()))
+    }
+
+    fn read(&self, rel: &str) -> io::Result<Vec<u8>> {
+        let entry = self.entry(rel)?;
+        let too_large = || io::Error::new(io::ErrorKind::InvalidData, format!("{rel} is larger than the entry size limit"));
+        if entry.size > MAX_ENTRY_BYTES {
+            return Err(too_large());
+        }
+        let mut zip = self.zip.lock().unwrap_or_else(|e| e.into_inner());
+        let file = zip.as_mut().ok_or_else(closed)?.by_index(entry.index).map_err(io::Error::other)?;
+        let mut buf = Vec::new();
+        file.take(MAX_ENTRY_BYTES + 1).read_to_end(&mut buf)?;
+        if buf.len() as u64 > MAX_ENTRY_BYTES {
+            return Err(too_large());
+        }
+        Ok(buf)
+    }
+
+    /// Stored entries are read through their own file handle, so they do not
+    /// wait on the shared archive lock.
+    fn read_range(&self, rel: &str, start: u64, len: u64) -> io::Result<Vec<u8>> {
+        let entry = self.entry(rel)?;
+        let start = start.min(entry.size);
+        let len = len.min(entry.size - start);
+        if let Some(offset) = entry.stored_at {
+            let is_closed = self.closed.read().unwrap_or_else(|e| e.into_inner());
+            if *is_closed {
+                return Err(closed());
+            }
+            let mut file = File::open(&self.path)?;
+            file.seek(SeekFrom::Start(offset + start))?;
+            let mut buf = Vec::new();
+            file.take(len).read_to_end(&mut buf)?;
+            return Ok(buf);
+        }
+        // The header size is not trusted: slice within what was actually inflated.
+        let slice = |data: &[u8]| {
+            let start = (start as usize).min(data.len());
+            data[start..(start + len as usize).min(data.len())].to_vec()
+        };
+        if entry.size > RANGE_CACHE_LIMIT {
+            return Ok(slice(&self.read(rel)?));
+        }
+        let cached = self.inflated.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        let data = match cached {
+            Some((index, data)) if index == entry.index => data,
+            _ => {
+                let data = Arc::new(self.read(rel)?);
+                *self.inflated.lock().unwrap_or_else(|e| e.into_inner()) = Some((entry.index, data.clone()));
+                data
+            }
+        };
+        Ok(slice(&data))
+    }
+}
+
+fn closed() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "manual archive was closed")
+}
+
+/// `/`-separated relative path, as used for lookups and index keys.
+pub fn rel_key(rel: &Path) -> String {
+    rel.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Files of one manual. `rel` paths are `/`-separated and relative to the
+/// package root; callers are expected to have rejected `..` already.
+#[derive(Clone)]
+pub enum Site {
+    Dir(PathBuf),
+    Archive(Arc<ManualArchive>),
+}
+
+impl Site {
+    /// A package folder, or a `.zip` file holding one.
+    pub fn open(path: &Path) -> ToolResult<Site> {
+        if path.is_dir() {
+            Ok(Site::Dir(path.to_path_buf()))
+        } else {
+            Ok(Site::Archive(Arc::new(ManualArchive::open(path)?)))
+        }
+    }
+
+    /// Releases the archive file, see `ManualArchive::close`.
+    pub fn close(&self) {
+        if let Site::Archive(archive) = self {
+            archive.close();
+        }
+    }
+
+    /// The folder or archive file backing this site.
+    pub fn location(&self) -> &Path {
+        match self {
+            Site::Dir(root) => root,
+            Site::Archive(archive) => &archive.path,
+        }
+    }
+
+    pub fn is_file(&self, rel: &str) -> bool {
+        match self {
+            Site::Dir(root) => root.join(rel).is_file(),
+            Site::Archive(archive) => archive.entries.contains_key(rel),
+        }
+    }
+
+    pub fn is_dir(&self, rel: &str) -> bool {
+        match self {
+            Site::Dir(root) => root.join(rel).is_dir(),
+            Site::Archive(archive) => rel.is_empty() || archive.dirs.contains(rel.trim_end_matches('/')),
+        }
+    }
+
+    /// Size and modification time; archive entries share the archive's mtime.
+    pub fn stat(&self, rel: &str) -> Option<(u64, SystemTime)> {
+        match self {
+            Site::Dir(root) => {
+                let meta = fs::metadata(root.join(rel)).ok().filter(|m| m.is_file())?;
+                Some((meta.len(), meta.modified().unwrap_or(SystemTime::UNIX_EPOCH)))
+            }
+            Site::Archive(archive) => archive.entries.get(rel).map(|e| (e.size, archive.modified)),
+        }
+    }
+
+    pub fn read(&self, rel: &str) -> io::Result<Vec<u8>> {
+        match self {
+            Site::Dir(root) => fs::read(root.join(rel)),
+            Site::Archive(archive) => archive.read(rel),
+        }
+    }
+
+    /// `len` bytes from `start`, clamped to the end of the file.
+    pub fn read_range(&self, rel: &str, start: u64, len: u64) -> io::Result<Vec<u8>> {
+        match self {
+            Site::Dir(root) => {
+                let mut file = File::open(root.join(rel))?;
+                file.seek(SeekFrom::Start(start))?;
+                let mut buf = Vec::with_capacity(len as usize);
+                file.take(len).read_to_end(&mut buf)?;
+                Ok(buf)
+            }
+            Site::Archive(archive) => archive.read_range(rel, start, len),
+        }
+    }
+
+    /// HTML pages of the site (except `404.html`), sorted.
+    pub fn html_pages(&self) -> Vec<String> {
+        let is_page = |key: &str| {
+            (key.ends_with(".html") || key.ends_with(".htm")) && key != "404.html"
+        };
+        let mut pages = match self {
+            Site::Dir(root) => {
+                let mut found = Vec::new();
+                collect_files(root, root, &mut found);
+                found.into_iter().filter(|k| is_page(k)).collect()
+            }
+            Site::Archive(archive) => archive.entries.keys().filter(|k| is_page(k)).cloned().collect::<Vec<_>>(),
+        };
+        pages.sort();
+        pages
+    }
+}
+
+fn collect_files(root: &Path, dir: &Path, out: &mut Vec<String>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(kind) = entry.file_type() else { continue };
+        if kind.is_dir() {
+            collect_files(root, &path, out);
+        } else if kind.is_file() {
+            if let Ok(rel) = path.strip_prefix(root) {
+                out.push(rel_key(rel));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn archive_strips_single_top_level_folder() {
+        let path = std::env::temp_dir().join(format!("lazycat-site-{}.zip", uuid::Uuid::new_v4()));
+        {
+            let mut writer = zip::ZipWriter::new(File::create(&path).expect("create"));
+            let options = zip::write::SimpleFileOptions::default();
+            for (name, body) in [
+                ("pkg/manual.json", "{}"),
+                ("pkg/guide/intro.html", "<p>intro</p>"),
+                ("pkg/docs/manual.json", "{}"),
+            ] {
+                writer.start_file(name, options).expect("start");
+                writer.write_all(body.as_bytes()).expect("write");
+            }
+            let stored = options.compression_method(zip::CompressionMethod::Stored);
+            writer.start_file("pkg/media/clip.bin", stored).expect("start");
+            writer.write_all(b"0123456789").expect("write");
+            writer.finish().expect("finish");
+        }
+        let site = Site::open(&path).expect("open");
+        assert!(site.is_file("guide/intro.html"));
+        assert!(site.is_dir("guide"));
+        assert!(!site.is_file("pkg/manual.json"));
+        assert_eq!(site.read("guide/intro.html").expect("read"), b"<p>intro</p>");
+        assert_eq!(site.read_range("guide/intro.html", 3, 5).expect("range"), b"intro");
+        assert_eq!(site.read_range("guide/intro.html", 0, 3).expect("cached range"), b"<p>");
+        assert_eq!(site.read_range("guide/intro.html", 8, 100).expect("clamped"), b"</p>");
+        assert_eq!(site.read_range("media/clip.bin", 2, 3).expect("stored range"), b"234");
+        assert_eq!(site.read_range("media/clip.bin", 8, 100).expect("stored clamped"), b"89");
+        assert!(site.read_range("media/clip.bin", 20, 1).expect("past end").is_empty());
+        assert_eq!(site.html_pages(), vec!["guide/intro.html".to_string()]);
+        let _ = fs::remove_file(path);
+    }
+}
@@ -1,26 +1,351 @@
+//! Offline manual packages.
+//!
+//! A package is a folder or a zip archive with a `manual.json` manifest at
+//! its root next to the site files. Bundled packages live in `MANUALS_DIR`;
+//! imported ones are copied into `<data_dir>/manuals` (`<id>/` or `<id>.zip`,
+//! served straight from the archive) and take precedence over a bundled
+//! package with the same id, which is how bundled manuals are updated.
+
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use std::collections::HashMap;
-use std::sync::OnceLock;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+
+use super::error::{ToolError, ToolResult};
+use super::helpers::{copy_dir_recursive, get_data_dir};
+use super::jobs;
+use super::manual_index;
+use super::manual_site::{Site, MANIFEST_FILE};
+use super::registry::{ActionSpec, DomainSpec, FieldSpec};
+
+/// Port of the manual HTTP server, once it is running.
+pub static MANUAL_SERVER_PORT: OnceLock<u16> = OnceLock::new();
+/// Bundled packages (`resources/manuals`), one subdirectory each.
+pub static MANUALS_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Installed packages, loaded on first use and reset by import/remove.
+static PACKAGES: RwLock<Option<Vec<ManualPackage>>> = RwLock::new(None);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManualManifest {
+    pub id: String,
+    pub name: String,
+    /// Start page, relative to the package root.
+    #[serde(default = "default_home")]
+    pub home: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+fn default_home() -> String {
+    "/".to_string()
+}
 
-pub static MANUAL_SERVERS: OnceLock<HashMap<String, u16>> = OnceLock::new();
+#[derive(Clone)]
+pub struct ManualPackage {
+    pub manifest: ManualManifest,
+    pub site: Site,
+    pub bundled: bool,
+}
+
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "manuals",
+    description: "Offline manuals served over local HTTP",
+    execute,
+    actions: &[
+        ActionSpec::new(
+            "list",
+            "List installed manuals",
+            &[],
+            "array: [{ id, name, version, description, home, url, source }]",
+        ),
+        ActionSpec::new(
+            "import",
+            "Install or update a manual package from a zip file or folder",
+            &[FieldSpec::required("path", "string", "zip file or folder containing manual.json")],
+            "object: { id, name, version, previousVersion }",
+        ),
+        ActionSpec::new(
+            "remove",
+            "Remove an imported manual package",
+            &[FieldSpec::required("id", "string", "manual id")],
+            "object: { id, removed, bundledFallback }",
+        ),
+        ActionSpec::new(
+            "search",
+            "Full-text search across the offline manuals",
+            &[
+                FieldSpec::required("query", "string", "search terms, all must match"),
+                FieldSpec::optional("manual", "string", "restrict to one manual id"),
+                FieldSpec::optional("limit", "integer", "max hits, default 20"),
+            ],
+            "object: { hits: [{ manual, manualName, path, title, snippet, score, url }], indexing }",
+        ),
+        ActionSpec::new(
+            "index",
+            "Update the search index (run as a job for progress)",
+            &[],
+            "object: { indexed, unchanged, removed, pages }",
+        ),
+    ],
+};
 
-pub fn execute(action: &str, _payload: &Value) -> Result<Value, String> {
+pub fn execute(action: &str, payload: &Value) -> ToolResult {
     match action {
-        "list" => {
-            let servers = MANUAL_SERVERS.get();
-            let mut list = Vec::new();
-            let known = [
-                ("vue3",         "Vue 3 开发手册",       "/guide/introduction.html"),
-                ("element-plus", "Element Plus 组件库",  "/zh-CN/component/overview"),
-                ("mdn-js",       "MDN JavaScript 手册",  "/zh-CN/docs/Web/JavaScript/"),
-            ];
-            for (id, name, home) in known {
-                if let Some(port) = servers.and_then(|m| m.get(id)) {
-                    list.push(json!({"id": id, "name": name, "url": format!("http://127.0.0.1:{port}{home}")}));
+        "list" => Ok(json!(packages().iter().map(package_json).collect::<Vec<_>>())),
+        "import" => import(payload["path"].as_str().unwrap_or_default()),
+        "remove" => remove(payload["id"].as_str().unwrap_or_default()),
+        "search" => {
+            let query = payload["query"].as_str().unwrap_or_default();
+            let manual = payload["manual"].as_str().filter(|m| !m.is_empty());
+            let limit = payload["limit"].as_u64().unwrap_or(20).clamp(1, 100) as usize;
+            let base_url = MANUAL_SERVER_PORT.get().map(|port| format!("http://127.0.0.1:{port}"));
+            let installed = packages();
+            let hits: Vec<Value> = manual_index::search(query, manual, limit)?
+                .into_iter()
+                .map(|hit| {
+                    let name = installed
+                        .iter()
+                        .find(|p| p.manifest.id == hit.manual)
+                        .map_or(hit.manual.as_str(), |p| p.manifest.name.as_str());
+                    let mut value = hit.to_json(base_url.as_deref());
+                    value["manualName"] = json!(name);
+                    value
+                })
+                .collect();
+            Ok(json!({ "hits": hits, "indexing": manual_index::is_indexing() }))
+        }
+        "index" => manual_index::index(|| packages().into_iter().map(|p| (p.manifest.id, p.site)).collect()),
+        _ => Err(ToolError::unsupported("manuals", action)),
+    }
+}
+
+fn package_json(package: &ManualPackage) -> Value {
+    let m = &package.manifest;
+    let home = if m.home.starts_with('/') { m.home.clone() } else { format!("/{}", m.home) };
+    let url = MANUAL_SERVER_PORT
+        .get()
+        .map(|port| format!("http://127.0.0.1:{port}/manual/{}{home}", m.id));
+    json!({
+        "id": m.id,
+        "name": m.name,
+        "version": m.version,
+        "description": m.description,
+        "home": home,
+        "url": url,
+        "source": if package.bundled { "bundled" } else { "user" },
+    })
+}
+
+// ── Registry ─────────────────────────────────────────────────
+
+fn user_manuals_dir() -> ToolResult<PathBuf> {
+    Ok(get_data_dir()?.join("manuals"))
+}
+
+/// Ids become URL path segments and directory names.
+fn validate_id(id: &str) -> ToolResult<()> {
+    let valid = !id.is_empty()
+        && id.len() <= 64
+        && id.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(ToolError::invalid_field(
+            "id",
+            format!("invalid manual id '{id}': use lowercase letters, digits, '-', '_' or '.'"),
+        ))
+    }
+}
+
+fn parse_manifest(text: &str) -> ToolResult<ManualManifest> {
+    let manifest: ManualManifest = serde_json::from_str(text).map_err(|e| ToolError::json(MANIFEST_FILE, &e))?;
+    validate_id(&manifest.id)?;
+    if manifest.name.trim().is_empty() || manifest.version.trim().is_empty() {
+        return Err(ToolError::invalid(format!("{MANIFEST_FILE} needs a non-empty name and version")));
+    }
+    Ok(manifest)
+}
+
+fn read_manifest(site: &Site) -> ToolResult<ManualManifest> {
+    let bytes = site
+        .read(MANIFEST_FILE)
+        .map_err(|e| ToolError::io(&format!("read {MANIFEST_FILE} failed"), e))?;
+    parse_manifest(&String::from_utf8_lossy(&bytes))
+}
+
+fn is_archive(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case("zip"))
+}
+
+/// Packages directly under `dir`; entries without a valid manifest are skipped.
+fn scan(dir: &Path, bundled: bool) -> Vec<ManualPackage> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut found = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        // Dot entries are imports still being copied.
+        if entry.file_name().to_string_lossy().starts_with('.') || !(path.is_dir() || is_archive(&path)) {
+            continue;
+        }
+        match Site::open(&path).and_then(|site| Ok((read_manifest(&site)?, site))) {
+            Ok((manifest, site)) => found.push(ManualPackage { manifest, site, bundled }),
+            Err(e) => eprintln!("skipping manual {}: {}", path.display(), e.message()),
+        }
+    }
+    found
+}
+
+fn load_packages() -> Vec<ManualPackage> {
+    let mut by_id: BTreeMap<String, ManualPackage> = BTreeMap::new();
+    let bundled = MANUALS_DIR.get().map(|dir| scan(dir, true)).unwrap_or_default();
+    let user = user_manuals_dir().map(|dir| scan(&dir, false)).unwrap_or_default();
+    for package in bundled.into_iter().chain(user) {
+        by_id.insert(package.manifest.id.clone(), package);
+    }
+    let mut list: Vec<_> = by_id.into_values().collect();
+    list.sort_by(|a, b| a.manifest.name.cmp(&b.manifest.name));
+    list
+}
+
+/// Installed packages, user packages shadowing bundled ones with the same id.
+pub fn packages() -> Vec<ManualPackage> {
+    if let Some(list) = PACKAGES.read().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        return list.clone();
+    }
+    // Loaded under the write lock, so it cannot interleave with an import or
+    // removal and cache (or reopen) files that are being replaced.
+    let mut cache = PACKAGES.write().unwrap_or_else(|e| e.into_inner());
+    cache.get_or_insert_with(load_packages).clone()
+}
+
+/// Takes the installed packages out of the cache and closes the archives of
+/// `id`, so the files behind them can be replaced or deleted even while the
+/// manual server or the index job still hold clones. The cache is reloaded
+/// on next use.
+fn release(cache: &mut Option<Vec<ManualPackage>>, id: &str) -> Vec<ManualPackage> {
+    let installed = cache.take().unwrap_or_else(load_packages);
+    for package in installed.iter().filter(|p| p.manifest.id == id) {
+        package.site.close();
+    }
+    installed
+}
+
+/// Manual id → site, as mounted by the manual server.
+pub fn sites() -> BTreeMap<String, Site> {
+    packages().into_iter().map(|p| (p.manifest.id, p.site)).collect()
+}
+
+fn packages_changed() {
+    *PACKAGES.write().unwrap_or_else(|e| e.into_inner()) = None;
+    // Keep search in step with the installed set. A run already in progress
+    // indexes again before it finishes; otherwise start one.
+    if manual_index::mark_stale() {
+        return;
+    }
+    if let Err(e) = jobs::execute("start", &json!({ "domain": "manuals", "action": "index" })) {
+        eprintln!("start manual index failed: {}", e.message());
+    }
+}
+
+// ── Import / remove ──────────────────────────────────────────
+
+fn import(path: &str) -> ToolResult {
+    if path.is_empty() {
+        return Err(ToolError::invalid_field("path", "path is required"));
+    }
+    let source = PathBuf::from(path);
+    if !source.exists() {
+        return Err(ToolError::not_found(format!("{path} does not exist")));
+    }
+    // Validate before copying anything.
+    let manifest = read_manifest(&Site::open(&source)?)?;
+    let dir = user_manuals_dir()?;
+    fs::create_dir_all(&dir).map_err(|e| ToolError::io("create manuals directory failed", e))?;
+    let archive = !source.is_dir();
+    let staging = dir.join(format!(".import-{}", uuid::Uuid::new_v4()));
+    let copied = if archive {
+        fs::copy(&source, &staging).map(|_| ()).map_err(|e| ToolError::io("copy manual package failed", e))
+    } else {
+        copy_dir_recursive(&source, &staging).map_err(ToolError::from)
+    };
+    if let Err(e) = copied {
+        let _ = remove_path(&staging);
+        return Err(e);
+    }
+
+    let previous = {
+        let mut cache = PACKAGES.write().unwrap_or_else(|e| e.into_inner());
+        let installed = release(&mut cache, &manifest.id);
+        let previous = installed.into_iter().find(|p| p.manifest.id == manifest.id).map(|p| p.manifest.version);
+        let installed = (|| {
+            for old in [dir.join(&manifest.id), dir.join(format!("{}.zip", manifest.id))] {
+                if old.exists() {
+                    remove_path(&old).map_err(|e| ToolError::io("remove previous version failed", e))?;
                 }
             }
-            Ok(json!(list))
+            let target = if archive { dir.join(format!("{}.zip", manifest.id)) } else { dir.join(&manifest.id) };
+            fs::rename(&staging, &target).map_err(|e| ToolError::io("install manual failed", e))
+        })();
+        if let Err(e) = installed {
+            let _ = remove_path(&staging);
+            return Err(e);
+        }
+        previous
+    };
+    packages_changed();
+    Ok(json!({
+        "id": manifest.id,
+        "name": manifest.name,
+        "version": manifest.version,
+        "previousVersion": previous,
+    }))
+}
+
+fn remove(id: &str) -> ToolResult {
+    validate_id(id)?;
+    {
+        let mut cache = PACKAGES.write().unwrap_or_else(|e| e.into_inner());
+        let installed = cache.get_or_insert_with(load_packages);
+        let Some(package) = installed.iter().find(|p| p.manifest.id == id) else {
+            return Err(ToolError::not_found(format!("manual '{id}' is not installed")));
+        };
+        if package.bundled {
+            return Err(ToolError::PermissionDenied(format!("manual '{id}' ships with the app and cannot be removed")));
         }
-        _ => Err(format!("unsupported manuals action: {action}")),
+        let location = package.site.location().to_path_buf();
+        release(&mut cache, id);
+        remove_path(&location).map_err(|e| ToolError::io("remove manual failed", e))?;
+    }
+    packages_changed();
+    let fallback = packages().into_iter().any(|p| p.manifest.id == id);
+    Ok(json!({ "id": id, "removed": true, "bundledFallback": fallback }))
+}
+
+fn remove_path(path: &Path) -> std::io::Result<()> {
+    if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_requires_safe_id_and_version() {
+        let ok = parse_manifest(r#"{"id":"vue3","name":"Vue 3","version":"3.5"}"#).expect("valid");
+        assert_eq!(ok.home, "/");
+        let bad_id = parse_manifest(r#"{"id":"../x","name":"x","version":"1"}"#).expect_err("bad id");
+        assert_eq!(bad_id.code(), "INVALID_PAYLOAD");
+        assert!(parse_manifest(r#"{"id":"x","name":"x","version":" "}"#).is_err());
     }
 }
@@ -0,0 +1,531 @@
+//! Versioned schema migrations for `lazycat.sqlite`.
+//!
+//! Every step declares `up` and `down` SQL and runs in its own transaction,
+//! so a failing step leaves the database at the previous version instead of
+//! half-applied. A `pre-migration` backup is taken before an existing
+//! database is upgraded or rolled back. Applied steps are recorded in
+//! `schema_migrations`; `schema_version` still holds the current version for
+//! older builds and for backup checks. Rolling back is meant for going back
+//! to an older build: the running app closes the database until restart.
+
+use std::sync::atomic::{AtomicI64, Ordering};
+
+use rusqlite::{params, Connection};
+use serde_json::{json, Value};
+
+use super::backup;
+use super::error::{ToolError, ToolResult};
+use super::helpers::{close_db, db_conn};
+use super::registry::{ActionSpec, DomainSpec, FieldSpec};
+
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub up: &'static str,
+    pub down: &'static str,
+}
+
+/// In version order, without gaps.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "hosts_profiles table",
+        up: "CREATE TABLE IF NOT EXISTS hosts_profiles (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE,
+                content TEXT NOT NULL,
+                enabled INTEGER NOT NULL DEFAULT 0,
+                updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            );",
+        down: "DROP TABLE hosts_profiles;",
+    },
+    Migration {
+        version: 2,
+        name: "user_settings table",
+        up: "CREATE TABLE IF NOT EXISTS user_settings (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL,
+                updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            );",
+        down: "DROP TABLE user_settings;",
+    },
+    Migration {
+        version: 3,
+        name: "hosts_profiles sort_order",
+        up: "ALTER TABLE hosts_profiles ADD COLUMN sort_order INTEGER NOT NULL DEFAULT 0;
+            UPDATE hosts_profiles SET sort_order = id;",
+        down: "ALTER TABLE hosts_profiles DROP COLUMN sort_order;",
+    },
+    Migration {
+        version: 4,
+        name: "snippet_folders table",
+        up: "CREATE TABLE IF NOT EXISTS snippet_folders (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                parent_id INTEGER DEFAULT NULL,
+                sort_order INTEGER NOT NULL DEFAULT 0,
+                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (parent_id) REFERENCES snippet_folders(id) ON DELETE CASCADE
+            );",
+        down: "DROP TABLE snippet_folders;",
+    },
+    Migration {
+        version: 5,
+        name: "snippets table",
+        up: "CREATE TABLE IF NOT EXISTS snippets (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                title TEXT NOT NULL,
+                description TEXT NOT NULL DEFAULT '',
+                folder_id INTEGER DEFAULT NULL,
+                is_favorite INTEGER NOT NULL DEFAULT 0,
+                sort_order INTEGER NOT NULL DEFAULT 0,
+                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (folder_id) REFERENCES snippet_folders(id) ON DELETE SET NULL
+            );",
+        down: "DROP TABLE snippets;",
+    },
+    Migration {
+        version: 6,
+        name: "snippet_fragments table",
+        up: "CREATE TABLE IF NOT EXISTS snippet_fragments (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                snippet_id INTEGER NOT NULL,
+                label TEXT NOT NULL DEFAULT 'main',
+                language TEXT NOT NULL DEFAULT 'plaintext',
+                code TEXT NOT NULL DEFAULT '',
+                sort_order INTEGER NOT NULL DEFAULT 0,
+                FOREIGN KEY (snippet_id) REFERENCES snippets(id) ON DELETE CASCADE
+            );",
+        down: "DROP TABLE snippet_fragments;",
+    },
+    Migration {
+        version: 7,
+        name: "snippet_tags table",
+        up: "CREATE TABLE IF NOT EXISTS snippet_tags (
+                snippet_id INTEGER NOT NULL,
+                tag TEXT NOT NULL,
+                PRIMARY KEY (snippet_id, tag),
+                FOREIGN KEY (snippet_id) REFERENCES snippets(id) ON DELETE CASCADE
+            );
+            CREATE INDEX IF NOT EXISTS idx_snippet_tags_tag ON snippet_tags(tag);",
+        down: "DROP TABLE snippet_tags;",
+    },
+    // Rolling back below 8 drops every snippet; only the pre-migration
+    // snapshot taken by the rollback action brings them back.
+    Migration {
+        version: 8,
+        name: "snippets workspace v2",
+        up: "CREATE TABLE IF NOT EXISTS snippet_folders_v2 (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                parent_id INTEGER DEFAULT NULL,
+                sort_order INTEGER NOT NULL DEFAULT 0,
+                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (parent_id) REFERENCES snippet_folders_v2(id) ON DELETE CASCADE
+            );
+            CREATE TABLE IF NOT EXISTS snippet_entries (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                title TEXT NOT NULL,
+                description TEXT NOT NULL DEFAULT '',
+                folder_id INTEGER DEFAULT NULL,
+                is_favorite INTEGER NOT NULL DEFAULT 0,
+                primary_language TEXT NOT NULL DEFAULT 'plaintext',
+                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                last_used_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                use_count INTEGER NOT NULL DEFAULT 0,
+                FOREIGN KEY (folder_id) REFERENCES snippet_folders_v2(id) ON DELETE SET NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_entries_last_used_at ON snippet_entries(last_used_at DESC);
+            CREATE INDEX IF NOT EXISTS idx_entries_updated_at ON snippet_entries(updated_at DESC);
+            CREATE TABLE IF NOT EXISTS snippet_fragments_v2 (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                entry_id INTEGER NOT NULL,
+                label TEXT NOT NULL DEFAULT 'main',
+                language TEXT NOT NULL DEFAULT 'plaintext',
+                code TEXT NOT NULL DEFAULT '',
+                sort_order INTEGER NOT NULL DEFAULT 0,
+                FOREIGN KEY (entry_id) REFERENCES snippet_entries(id) ON DELETE CASCADE
+            );
+            CREATE INDEX IF NOT EXISTS idx_fragments_v2_entry_sort ON snippet_fragments_v2(entry_id, sort_order);
+            CREATE TABLE IF NOT EXISTS snippet_entry_tags (
+                entry_id INTEGER NOT NULL,
+                tag TEXT NOT NULL,
+                PRIMARY KEY (entry_id, tag),
+                FOREIGN KEY (entry_id) REFERENCES snippet_entries(id) ON DELETE CASCADE
+            );
+            CREATE INDEX IF NOT EXISTS idx_entry_tags_tag ON snippet_entry_tags(tag);
+            CREATE VIRTUAL TABLE IF NOT EXISTS snippet_fts USING fts5(
+                entry_id UNINDEXED,
+                title,
+                description,
+                tags_text,
+                code_text
+            );",
+        down: "DROP TABLE IF EXISTS snippet_fts;
+            DROP TABLE snippet_entry_tags;
+            DROP TABLE snippet_fragments_v2;
+            DROP TABLE snippet_entries;
+            DROP TABLE snippet_folders_v2;",
+    },
+    Migration {
+        version: 9,
+        name: "saved tool pipelines",
+        up: "CREATE TABLE IF NOT EXISTS pipelines (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE,
+                description TEXT NOT NULL DEFAULT '',
+                steps TEXT NOT NULL,
+                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            );",
+        down: "DROP TABLE pipelines;",
+    },
+    Migration {
+        version: 10,
+        name: "tool_execute history",
+        up: "CREATE TABLE IF NOT EXISTS tool_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                domain TEXT NOT NULL,
+                action TEXT NOT NULL,
+                payload TEXT NOT NULL DEFAULT '',
+                ok INTEGER NOT NULL DEFAULT 1,
+                result_summary TEXT NOT NULL DEFAULT '',
+                error_code TEXT,
+                duration_ms INTEGER NOT NULL DEFAULT 0,
+                pinned INTEGER NOT NULL DEFAULT 0,
+                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            );
+            CREATE INDEX IF NOT EXISTS idx_tool_history_created ON tool_history(created_at DESC);
+            CREATE INDEX IF NOT EXISTS idx_tool_history_domain ON tool_history(domain, action);",
+        down: "DROP TABLE tool_history;",
+    },
+    Migration {
+        version: 11,
+        name: "hosts_profiles subscription source and refresh state",
+        up: "ALTER TABLE hosts_profiles ADD COLUMN source TEXT DEFAULT NULL;
+            ALTER TABLE hosts_profiles ADD COLUMN refresh_cron TEXT DEFAULT NULL;
+            ALTER TABLE hosts_profiles ADD COLUMN last_refreshed_at TEXT DEFAULT NULL;
+            ALTER TABLE hosts_profiles ADD COLUMN last_attempt_at TEXT DEFAULT NULL;
+            ALTER TABLE hosts_profiles ADD COLUMN last_error TEXT DEFAULT NULL;",
+        down: "ALTER TABLE hosts_profiles DROP COLUMN last_error;
+            ALTER TABLE hosts_profiles DROP COLUMN last_attempt_at;
+            ALTER TABLE hosts_profiles DROP COLUMN last_refreshed_at;
+            ALTER TABLE hosts_profiles DROP COLUMN refresh_cron;
+            ALTER TABLE hosts_profiles DROP COLUMN source;",
+    },
+    Migration {
+        version: 12,
+        name: "home_top_limit stored as an integer",
+        // Older builds stored a JSON string ("\"12\""); values that are not a
+        // plain integer in range are dropped and fall back to the default.
+        up: "UPDATE user_settings SET value = json_extract(value, '$')
+                WHERE key = 'home_top_limit' AND json_valid(value) AND json_type(value) = 'text';
+            DELETE FROM user_settings WHERE key = 'home_top_limit'
+                AND (value GLOB '*[^0-9]*' OR value = '' OR CAST(value AS INTEGER) NOT BETWEEN 1 AND 50
+                    OR CAST(CAST(value AS INTEGER) AS TEXT) <> value);",
+        down: "UPDATE user_settings SET value = json_quote(value) WHERE key = 'home_top_limit';",
+    },
+];
+
+/// Version the migrations bring a database to.
+pub const SCHEMA_VERSION: i64 = MIGRATIONS[MIGRATIONS.len() - 1].version;
+
+/// Version the database was rolled back to in this process, or -1. This
+/// build expects the latest schema, so the database stays closed until restart.
+static ROLLED_BACK_TO: AtomicI64 = AtomicI64::new(-1);
+
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "migrations",
+    description: "Database schema version, migration history and rollback",
+    execute,
+    actions: &[
+        ActionSpec::new(
+            "status",
+            "Current schema version with applied and pending migrations",
+            &[],
+            "object: { version, latest, applied: [{ version, name, appliedAt }], pending: [{ version, name }] }",
+        ),
+        ActionSpec::new(
+            "rollback",
+            "Undo migrations down to a version before installing an older build. Dropped tables lose their data (below 8: all snippets) \
+             except in the snapshot taken first. The database stays closed until restart; restarting this build applies the migrations again",
+            &[FieldSpec::required("version", "integer", "target schema version")],
+            "object: { ok, version, rolledBack, snapshot, restartRequired }",
+        ),
+    ],
+};
+
+pub fn execute(action: &str, payload: &Value) -> ToolResult {
+    match action {
+        "status" => {
+            let conn = db_conn()?;
+            status(&conn)
+        }
+        "rollback" => {
+            let target = payload["version"]
+                .as_i64()
+                .ok_or_else(|| ToolError::invalid_field("version", "version must be an integer"))?;
+            let conn = db_conn()?;
+            let current = current_version(&conn);
+            if !(0..current).contains(&target) {
+                return Err(ToolError::invalid_field(
+                    "version",
+                    format!("target version must be between 0 and {}", current - 1),
+                ));
+            }
+            let snapshot = backup::create_from(&conn, backup::KIND_PRE_MIGRATION)?;
+            let rolled_back = rollback(&conn, target)?;
+            drop(conn);
+            ROLLED_BACK_TO.store(target, Ordering::SeqCst);
+            close_db();
+            Ok(json!({
+                "ok": true,
+                "version": target,
+                "rolledBack": rolled_back,
+                "snapshot": snapshot.file_name().map(|n| n.to_string_lossy().to_string()),
+                "restartRequired": true,
+            }))
+        }
+        _ => Err(ToolError::unsupported("migrations", action)),
+    }
+}
+
+pub fn current_version(conn: &Connection) -> i64 {
+    let exists: bool = conn
+        .query_row(
+            "SELECT count(*) > 0 FROM sqlite_master WHERE type='table' AND name='schema_version'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap_or(false);
+    if !exists {
+        return 0;
+    }
+    conn.query_row("SELECT COALESCE(MAX(version), 0) FROM schema_version", [], |row| {
+        row.get(0)
+    })
+    .unwrap_or(0)
+}
+
+fn set_version(conn: &Connection, version: i64) -> Result<(), String> {
+    conn.execute_batch("CREATE TABLE IF NOT EXISTS schema_version (version INTEGER NOT NULL);")
+        .map_err(|e| format!("create schema_version table failed: {e}"))?;
+    conn.execute("DELETE FROM schema_version", [])
+        .map_err(|e| format!("clear schema_version failed: {e}"))?;
+    conn.execute("INSERT INTO schema_version (version) VALUES (?1)", params![version])
+        .map_err(|e| format!("set schema_version failed: {e}"))?;
+    Ok(())
+}
+
+/// Creates the history table. Databases migrated before it existed get a
+/// row per applied version with an unknown (null) time.
+fn ensure_history(conn: &Connection, steps: &[Migration]) -> Result<(), String> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TEXT DEFAULT NULL
+        );",
+    )
+    .map_err(|e| format!("create schema_migrations table failed: {e}"))?;
+    let recorded: i64 = conn
+        .query_row("SELECT count(*) FROM schema_migrations", [], |r| r.get(0))
+        .map_err(|e| format!("query schema_migrations failed: {e}"))?;
+    if recorded == 0 {
+        let current = current_version(conn);
+        for step in steps.iter().filter(|m| m.version <= current) {
+            conn.execute(
+                "INSERT INTO schema_migrations(version, name) VALUES(?1, ?2)",
+                params![step.version, step.name],
+            )
+            .map_err(|e| format!("backfill schema_migrations failed: {e}"))?;
+        }
+    }
+    Ok(())
+}
+
+/// Fails once the `rollback` action has run: the tables this build needs may
+/// be gone, and reopening would silently migrate them back.
+pub fn ensure_not_rolled_back() -> Result<(), String> {
+    match ROLLED_BACK_TO.load(Ordering::SeqCst) {
+        -1 => Ok(()),
+        version => Err(format!(
+            "database schema was rolled back to version {version}; restart the app or install the older build"
+        )),
+    }
+}
+
+/// Brings `conn` up to [`SCHEMA_VERSION`]; `db_conn` runs this once, before the
+/// first connection is handed out.
+pub fn migrate(conn: &Connection) -> Result<(), String> {
+    let current = current_version(conn);
+    // Keep a copy of the old schema in case a migration goes wrong. A failed
+    // backup is logged rather than keeping the app from opening its data.
+    if current > 0 && current < SCHEMA_VERSION {
+        if let Err(e) = backup::create_from(conn, backup::KIND_PRE_MIGRATION) {
+            eprintln!("pre-migration backup failed: {}", e.message());
+        }
+    }
+    apply_pending(conn, MIGRATIONS)
+}
+
+fn apply_pending(conn: &Connection, steps: &[Migration]) -> Result<(), String> {
+    ensure_history(conn, steps)?;
+    let current = current_version(conn);
+    for step in steps.iter().filter(|m| m.version > current) {
+        let tx = conn
+            .unchecked_transaction()
+            .map_err(|e| format!("migration {} begin tx failed: {e}", step.version))?;
+        tx.execute_batch(step.up)
+            .map_err(|e| format!("migration {} ({}) failed: {e}", step.version, step.name))?;
+        tx.execute(
+            "INSERT OR REPLACE INTO schema_migrations(version, name, applied_at) VALUES(?1, ?2, CURRENT_TIMESTAMP)",
+            params![step.version, step.name],
+        )
+        .map_err(|e| format!("record migration {} failed: {e}", step.version))?;
+        set_version(&tx, step.version)?;
+        tx.commit()
+            .map_err(|e| format!("migration {} commit failed: {e}", step.version))?;
+    }
+    Ok(())
+}
+
+/// Runs the `down` SQL of every applied migration above `target`, newest
+/// first, each in its own transaction. Returns the versions undone. Data in
+/// dropped tables and columns is lost.
+pub fn rollback(conn: &Connection, target: i64) -> Result<Vec<i64>, String> {
+    ensure_history(conn, MIGRATIONS)?;
+    let current = current_version(conn);
+    let mut undone = Vec::new();
+    for step in MIGRATIONS.iter().rev().filter(|m| m.version > target && m.version <= current) {
+        let tx = conn
+            .unchecked_transaction()
+            .map_err(|e| format!("rollback {} begin tx failed: {e}", step.version))?;
+        tx.execute_batch(step.down)
+            .map_err(|e| format!("rollback {} ({}) failed: {e}", step.version, step.name))?;
+        tx.execute("DELETE FROM schema_migrations WHERE version = ?1", params![step.version])
+            .map_err(|e| format!("record rollback {} failed: {e}", step.version))?;
+        set_version(&tx, step.version - 1)?;
+        tx.commit()
+            .map_err(|e| format!("rollback {} commit failed: {e}", step.version))?;
+        undone.push(step.version);
+    }
+    Ok(undone)
+}
+
+fn status(conn: &Connection) -> ToolResult {
+    let version = current_version(conn);
+    let mut stmt = conn
+        .prepare("SELECT version, name, applied_at FROM schema_migrations ORDER BY version")
+        .map_err(|e| format!("prepare query failed: {e}"))?;
+    let applied = stmt
+        .query_map([], |row| {
+            Ok(json!({
+                "version": row.get::<_, i64>(0)?,
+                "name": row.get::<_, String>(1)?,
+                "appliedAt": row.get::<_, Option<String>>(2)?,
+            }))
+        })
+        .map_err(|e| format!("query schema_migrations failed: {e}"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    let pending: Vec<Value> = MIGRATIONS
+        .iter()
+        .filter(|m| m.version > version)
+        .map(|m| json!({ "version": m.version, "name": m.name }))
+        .collect();
+    Ok(json!({
+        "version": version,
+        "latest": SCHEMA_VERSION,
+        "applied": applied,
+        "pending": pending,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tables(conn: &Connection) -> Vec<String> {
+        let mut stmt = conn
+            .prepare("SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name")
+            .expect("prepare");
+        stmt.query_map([], |r| r.get(0)).expect("query").map(|r| r.expect("row")).collect()
+    }
+
+    #[test]
+    fn migrates_up_rolls_back_and_up_again() {
+        let conn = Connection::open_in_memory().expect("open");
+        migrate(&conn).expect("migrate");
+        assert_eq!(current_version(&conn), SCHEMA_VERSION);
+        let report = status(&conn).expect("status");
+        assert_eq!(report["applied"].as_array().unwrap().len(), MIGRATIONS.len());
+        assert!(report["pending"].as_array().unwrap().is_empty());
+
+        assert_eq!(rollback(&conn, 8).expect("rollback"), vec![12, 11, 10, 9]);
+        assert_eq!(current_version(&conn), 8);
+        assert!(!tables(&conn).contains(&"tool_history".to_string()));
+        assert!(conn.prepare("SELECT source FROM hosts_profiles").is_err());
+
+        rollback(&conn, 0).expect("rollback all");
+        assert_eq!(tables(&conn), vec!["schema_migrations", "schema_version"]);
+
+        migrate(&conn).expect("migrate again");
+        assert_eq!(current_version(&conn), SCHEMA_VERSION);
+        conn.execute("INSERT INTO hosts_profiles(name, content, source) VALUES ('a', '', 'x')", [])
+            .expect("latest schema");
+    }
+
+    #[test]
+    fn converts_home_top_limit_to_an_integer() {
+        let conn = Connection::open_in_memory().expect("open");
+        apply_pending(&conn, &MIGRATIONS[..11]).expect("v11");
+        let limit = |stored: &str| -> Option<String> {
+            conn.execute_batch("DELETE FROM user_settings; DELETE FROM schema_migrations WHERE version = 12;")
+                .expect("reset");
+            set_version(&conn, 11).expect("version");
+            conn.execute("INSERT INTO user_settings(key, value) VALUES('home_top_limit', ?1)", [stored])
+                .expect("insert");
+            apply_pending(&conn, MIGRATIONS).expect("v12");
+            conn.query_row("SELECT value FROM user_settings WHERE key = 'home_top_limit'", [], |r| r.get(0))
+                .ok()
+        };
+        assert_eq!(limit("\"6\"").as_deref(), Some("6"));
+        assert_eq!(limit("12").as_deref(), Some("12"));
+        assert_eq!(limit("\"many\""), None);
+        assert_eq!(limit("\"0\""), None);
+        assert_eq!(limit("007"), None);
+    }
+
+    #[test]
+    fn failed_step_keeps_previous_version() {
+        let steps = [
+            Migration { version: 1, name: "one", up: "CREATE TABLE one (x);", down: "DROP TABLE one;" },
+            Migration { version: 2, name: "two", up: "CREATE TABLE two (x); CREATE TABLE broken (", down: "" },
+        ];
+        let conn = Connection::open_in_memory().expect("open");
+        assert!(apply_pending(&conn, &steps).is_err());
+        assert_eq!(current_version(&conn), 1);
+        assert!(!tables(&conn).contains(&"two".to_string()));
+    }
+
+    #[test]
+    fn backfills_history_for_older_databases() {
+        let conn = Connection::open_in_memory().expect("open");
+        set_version(&conn, 2).expect("version");
+        conn.execute_batch(MIGRATIONS[0].up).expect("one");
+        conn.execute_batch(MIGRATIONS[1].up).expect("two");
+        apply_pending(&conn, &MIGRATIONS[..3]).expect("apply");
+        let times: Vec<Option<String>> = conn
+            .prepare("SELECT applied_at FROM schema_migrations ORDER BY version")
+            .expect("prepare")
+            .query_map([], |r| r.get(0))
+            .expect("query")
+            .map(|r| r.expect("row"))
+            .collect();
+        assert_eq!(times.len(), 3);
+        assert!(times[0].is_none() && times[1].is_none() && times[2].is_some());
+    }
+}
@@ -1,4 +1,12 @@
 pub mod helpers;
+pub mod error;
+pub mod registry;
+pub mod events;
+pub mod jobs;
+pub mod protocol;
+pub mod api;
+pub mod pipeline;
+pub mod history;
 pub mod encode;
 pub mod convert;
 pub mod text;
@@ -15,8 +23,20 @@ pub mod port;
 pub mod file;
 pub mod image;
 pub mod hosts;
+pub mod hosts_file;
+pub mod hosts_merge;
+pub mod hosts_subscription;
+pub mod hosts_verify;
 pub mod manuals;
+pub mod manual_index;
+pub mod manual_site;
 pub mod settings;
+pub mod settings_schema;
+pub mod workspace;
+pub mod backup;
+pub mod encryption;
+pub mod migrations;
+pub mod maintenance;
 pub mod hotkey;
 pub mod jwt;
 pub mod schema;
@@ -26,32 +46,50 @@ pub mod snippets;
 
 use serde_json::Value;
 
-pub fn execute_tool(domain: &str, action: &str, payload: &Value) -> Result<Value, String> {
-    match domain {
-        "encode"   => encode::execute(action, payload),
-        "convert"  => convert::execute(action, payload),
-        "text"     => text::execute(action, payload),
-        "time"     => time::execute(action, payload),
-        "gen"      => gen::execute(action, payload),
-        "regex"    => regex::execute(action, payload),
-        "cron"     => cron::execute(action, payload),
-        "crypto"   => crypto::execute(action, payload),
-        "format"   => format::execute(action, payload),
-        "network"  => network::execute(action, payload),
-        "dns"      => dns::execute(action, payload),
-        "env"      => env::execute(action, payload),
-        "port"     => port::execute(action, payload),
-        "file"     => file::execute(action, payload),
-        "image"    => image::execute(action, payload),
-        "hosts"    => hosts::execute(action, payload),
-        "manuals"  => manuals::execute(action, payload),
-        "settings" => settings::execute(action, payload),
-        "hotkey"   => hotkey::execute(action, payload),
-        "jwt"      => jwt::execute(action, payload),
-        "schema"   => schema::execute(action, payload),
-        "mybatis"  => mybatis::execute(action, payload),
-        "nginx"    => nginx::execute(action, payload),
-        "snippets" => snippets::execute(action, payload),
-        _ => Err(format!("unsupported command: {domain}.{action}")),
-    }
+pub use error::{ToolError, ToolResult};
+use registry::DomainSpec;
+
+/// Every tool domain reachable through `tool_execute`, in catalog order.
+pub static DOMAINS: &[&DomainSpec] = &[
+    &encode::SPEC,
+    &convert::SPEC,
+    &text::SPEC,
+    &time::SPEC,
+    &gen::SPEC,
+    &regex::SPEC,
+    &cron::SPEC,
+    &crypto::SPEC,
+    &format::SPEC,
+    &network::SPEC,
+    &dns::SPEC,
+    &env::SPEC,
+    &port::SPEC,
+    &file::SPEC,
+    &image::SPEC,
+    &hosts::SPEC,
+    &manuals::SPEC,
+    &settings::SPEC,
+    &backup::SPEC,
+    &encryption::SPEC,
+    &migrations::SPEC,
+    &maintenance::SPEC,
+    &hotkey::SPEC,
+    &jwt::SPEC,
+    &schema::SPEC,
+    &mybatis::SPEC,
+    &nginx::SPEC,
+    &snippets::SPEC,
+    &pipeline::SPEC,
+    &history::SPEC,
+    &jobs::SPEC,
+    &api::SPEC,
+];
+
+pub fn execute_tool(domain: &str, action: &str, payload: &Value) -> ToolResult {
+    registry::dispatch(DOMAINS, domain, action, payload)
+}
+
+/// Catalog of domains, actions and payload fields for `tool_describe`.
+pub fn describe_tools(domain: Option<&str>) -> ToolResult {
+    registry::describe(DOMAINS, domain)
 }
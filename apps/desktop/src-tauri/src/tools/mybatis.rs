@@ -3,6 +3,9 @@ use roxmltree::{Document, Node};
 use serde_json::{json, Value};
 use std::borrow::Cow;
 
+use super::error::{ToolError, ToolResult};
+use super::registry::{ActionSpec, DomainSpec, FieldSpec};
+
 #[derive(Debug)]
 struct RenderContext {
     params: Value,
@@ -11,22 +14,43 @@ struct RenderContext {
     safe_substitution: bool,
 }
 
-pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
+const SQL_TEMPLATE: FieldSpec = FieldSpec::required("sqlTemplate", "string", "MyBatis XML or SQL template");
+
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "mybatis",
+    description: "MyBatis dynamic SQL rendering",
+    execute,
+    actions: &[
+        ActionSpec::new(
+            "render",
+            "Render a template with parameters",
+            &[
+                SQL_TEMPLATE,
+                FieldSpec::optional("params", "string", "parameters as JSON text, default {}"),
+                FieldSpec::optional("safeSubstitution", "boolean", "reject unsafe ${} values, default true"),
+            ],
+            "object: rendered SQL and warnings",
+        ),
+        ActionSpec::new("lint", "Check a template for common mistakes", &[SQL_TEMPLATE], "object: issues"),
+    ],
+};
+
+pub fn execute(action: &str, payload: &Value) -> ToolResult {
     match action {
         "render" => render(payload),
         "lint" => lint(payload),
-        _ => Err(format!("unsupported mybatis action: {action}")),
+        _ => Err(ToolError::unsupported("mybatis", action)),
     }
 }
 
-fn render(payload: &Value) -> Result<Value, String> {
+fn render(payload: &Value) -> ToolResult {
     let sql_template = payload["sqlTemplate"].as_str().unwrap_or_default();
     if sql_template.trim().is_empty() {
-        return Err("sqlTemplate is empty".into());
+        return Err(ToolError::invalid_field("sqlTemplate", "sqlTemplate is empty"));
     }
     let params_raw = payload["params"].as_str().unwrap_or("{}");
     let params: Value =
-        serde_json::from_str(params_raw).map_err(|e| format!("invalid params json: {e}"))?;
+        serde_json::from_str(params_raw).map_err(|e| ToolError::json("invalid params json", &e))?;
     let safe_substitution = payload["safeSubstitution"].as_bool().unwrap_or(true);
 
     let mut ctx = RenderContext {
@@ -51,10 +75,10 @@ fn render(payload: &Value) -> Result<Value, String> {
     }))
 }
 
-fn lint(payload: &Value) -> Result<Value, String> {
+fn lint(payload: &Value) -> ToolResult {
     let sql_template = payload["sqlTemplate"].as_str().unwrap_or_default();
     if sql_template.trim().is_empty() {
-        return Err("sqlTemplate is empty".into());
+        return Err(ToolError::invalid_field("sqlTemplate", "sqlTemplate is empty"));
     }
     let mut issues = Vec::new();
     let mut stack = Vec::new();
@@ -105,7 +129,7 @@ fn lint(payload: &Value) -> Result<Value, String> {
     Ok(json!({ "issues": issues }))
 }
 
-fn render_xml_template(template: &str, ctx: &mut RenderContext) -> Result<String, String> {
+fn render_xml_template(template: &str, ctx: &mut RenderContext) -> ToolResult<String> {
     let wrapped = if template.trim_start().starts_with('<') {
         if template.contains("<script") {
             template.to_string()
@@ -115,9 +139,13 @@ fn render_xml_template(template: &str, ctx: &mut RenderContext) -> Result<String
     } else {
         format!("<script>{template}</script>")
     };
-    let doc = Document::parse(&wrapped).map_err(|e| format!("invalid mybatis xml: {e}"))?;
+    let doc = Document::parse(&wrapped).map_err(|e| ToolError::Parse {
+        message: format!("invalid mybatis xml: {e}"),
+        line: Some(e.pos().row as usize),
+        column: Some(e.pos().col as usize),
+    })?;
     let root = doc.root_element();
-    render_children(root, ctx, None)
+    Ok(render_children(root, ctx, None)?)
 }
 
 fn render_children(
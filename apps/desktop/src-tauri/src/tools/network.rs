@@ -2,7 +2,37 @@ use serde_json::{json, Value};
 use std::net::{SocketAddr, TcpStream};
 use std::time::{Duration, Instant};
 
-pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
+use super::error::{ToolError, ToolResult};
+use super::registry::{ActionSpec, DomainSpec, FieldSpec};
+
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "network",
+    description: "TCP and HTTP reachability checks",
+    execute,
+    actions: &[
+        ActionSpec::new(
+            "tcp_test",
+            "Open a TCP connection to host:port",
+            &[
+                FieldSpec::optional("host", "string", "IP address, default 127.0.0.1"),
+                FieldSpec::optional("port", "integer", "default 80"),
+                FieldSpec::optional("timeoutMs", "integer", "default 2000"),
+            ],
+            "object: { host, port, reachable, latencyMs, error }",
+        ),
+        ActionSpec::new(
+            "http_test",
+            "Send a HEAD request",
+            &[
+                FieldSpec::optional("url", "string", "default http://127.0.0.1"),
+                FieldSpec::optional("timeoutMs", "integer", "default 5000"),
+            ],
+            "object: { url, reachable, statusCode, latencyMs, error }",
+        ),
+    ],
+};
+
+pub fn execute(action: &str, payload: &Value) -> ToolResult {
     match action {
         "tcp_test" => {
             let host = payload["host"].as_str().unwrap_or("127.0.0.1");
@@ -11,7 +41,7 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
             let started = Instant::now();
             let addr: SocketAddr = format!("{host}:{port}")
                 .parse()
-                .map_err(|e| format!("invalid address: {e}"))?;
+                .map_err(|e| ToolError::invalid_field("host", format!("invalid address: {e}")))?;
             let result = TcpStream::connect_timeout(&addr, Duration::from_millis(timeout_ms));
             Ok(json!({
                 "host": host,
@@ -52,6 +82,6 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
                 }))
             }
         }
-        _ => Err(format!("unsupported network action: {action}")),
+        _ => Err(ToolError::unsupported("network", action)),
     }
 }
@@ -1,14 +1,59 @@
 use serde_json::{json, Value};
 
-pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
+use super::error::{ToolError, ToolResult};
+use super::registry::{ActionSpec, DomainSpec, FieldSpec};
+
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "nginx",
+    description: "Nginx server block generation and linting",
+    execute,
+    actions: &[
+        ActionSpec::new(
+            "generate",
+            "Generate a server block for a static site with an API proxy",
+            &[
+                FieldSpec::required("serverName", "string", "server_name"),
+                FieldSpec::required("root", "string", "static root directory"),
+                FieldSpec::optional("apiPrefix", "string", "proxied location, default /api/"),
+                FieldSpec::optional("apiUpstream", "string", "proxy_pass target"),
+                FieldSpec::optional("listen", "integer", "listen port, default 80"),
+                FieldSpec::optional("index", "string", "default index.html"),
+                FieldSpec::optional("clientMaxBodySize", "string", "default 20m"),
+                FieldSpec::optional("enableSpaFallback", "boolean", "default true"),
+                FieldSpec::optional("enableGzip", "boolean", "default true"),
+                FieldSpec::optional("enableHttps", "boolean", "default false"),
+                FieldSpec::optional("enableHttp2", "boolean", "default false"),
+                FieldSpec::optional("enableWebsocket", "boolean", "default false"),
+                FieldSpec::optional("enableAccessLog", "boolean", "default true"),
+                FieldSpec::optional("generateAccessLog", "boolean", "default true"),
+                FieldSpec::optional("generateErrorLog", "boolean", "default true"),
+                FieldSpec::optional("accessLogPath", "string", "access_log path"),
+                FieldSpec::optional("accessLogFormat", "string", "default main"),
+                FieldSpec::optional("errorLogPath", "string", "error_log path"),
+                FieldSpec::optional("errorLogLevel", "string", "default warn"),
+                FieldSpec::optional("sslCert", "string", "certificate path when HTTPS is enabled"),
+                FieldSpec::optional("sslKey", "string", "key path when HTTPS is enabled"),
+            ],
+            "object: config and warnings",
+        ),
+        ActionSpec::new(
+            "lint",
+            "Check an nginx config for common mistakes",
+            &[FieldSpec::required("config", "string", "nginx config text")],
+            "object: issues",
+        ),
+    ],
+};
+
+pub fn execute(action: &str, payload: &Value) -> ToolResult {
     match action {
         "generate" => generate(payload),
         "lint" => lint(payload),
-        _ => Err(format!("unsupported nginx action: {action}")),
+        _ => Err(ToolError::unsupported("nginx", action)),
     }
 }
 
-fn generate(payload: &Value) -> Result<Value, String> {
+fn generate(payload: &Value) -> ToolResult {
     let server_name = payload["serverName"].as_str().unwrap_or_default().trim();
     let root = payload["root"].as_str().unwrap_or_default().trim();
     let api_prefix = normalize_api_prefix(payload["apiPrefix"].as_str().unwrap_or("/api/"));
@@ -41,19 +86,19 @@ fn generate(payload: &Value) -> Result<Value, String> {
     let ssl_key = payload["sslKey"].as_str().unwrap_or_default().trim();
 
     if server_name.is_empty() {
-        return Err("serverName is required".into());
+        return Err(ToolError::invalid_field("serverName", "serverName is required"));
     }
     if root.is_empty() {
-        return Err("root is required".into());
+        return Err(ToolError::invalid_field("root", "root is required"));
     }
     if api_upstream.is_empty() {
-        return Err("apiUpstream is required".into());
+        return Err(ToolError::invalid_field("apiUpstream", "apiUpstream is required"));
     }
     if listen < 1 || listen > 65535 {
-        return Err("listen port must be 1..65535".into());
+        return Err(ToolError::invalid_field("listen", "listen port must be 1..65535"));
     }
     if enable_https && (ssl_cert.is_empty() || ssl_key.is_empty()) {
-        return Err("sslCert and sslKey are required when HTTPS is enabled".into());
+        return Err(ToolError::invalid("sslCert and sslKey are required when HTTPS is enabled"));
     }
 
     let mut lines = Vec::new();
@@ -159,10 +204,10 @@ fn generate(payload: &Value) -> Result<Value, String> {
     }))
 }
 
-fn lint(payload: &Value) -> Result<Value, String> {
+fn lint(payload: &Value) -> ToolResult {
     let config = payload["config"].as_str().unwrap_or_default();
     if config.trim().is_empty() {
-        return Err("config is empty".into());
+        return Err(ToolError::invalid_field("config", "config is empty"));
     }
 
     let mut issues = Vec::new();
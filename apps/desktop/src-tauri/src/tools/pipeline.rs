@@ -0,0 +1,337 @@
+//! Chains of tool actions where each step's output feeds the next payload.
+//!
+//! A step is `{ domain, action, payloadTemplate? }`. String values in the
+//! template may contain `{{path}}` bindings resolved against
+//! `{ input, prev, steps }`: `input` is the pipeline input, `prev` the previous
+//! step's output (the input for the first step) and `steps.N` the output of
+//! step N. Paths descend with dots, e.g. `{{prev.payload.exp}}`. A string that
+//! is exactly one binding takes the bound value as-is (keeping numbers and
+//! objects); otherwise the value is spliced in as text. Without a template the
+//! payload is `{ "input": "{{prev}}" }`.
+
+use rusqlite::{params, Connection};
+use serde_json::{json, Map, Value};
+use std::time::Instant;
+
+use super::error::{ToolError, ToolResult};
+use super::helpers::db_conn;
+use super::jobs::report_progress;
+use super::registry::{self, ActionSpec, DomainSpec, FieldSpec};
+
+const NAME: FieldSpec = FieldSpec::required("name", "string", "pipeline name");
+
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "pipeline",
+    description: "Chain tool actions, binding each output into the next payload",
+    execute,
+    actions: &[
+        ActionSpec::new(
+            "run",
+            "Run inline steps or a saved pipeline on an input",
+            &[
+                FieldSpec::optional("steps", "array", "[{ domain, action, payloadTemplate? }]"),
+                FieldSpec::optional("name", "string", "saved pipeline to run instead of steps"),
+                FieldSpec::optional("input", "json", "pipeline input, any JSON value"),
+            ],
+            "object: { output, steps: [{ domain, action, output, durationMs }] }",
+        ),
+        ActionSpec::new(
+            "save",
+            "Create or replace a named pipeline",
+            &[
+                NAME,
+                FieldSpec::required("steps", "array", "[{ domain, action, payloadTemplate? }]"),
+                FieldSpec::optional("description", "string", "free text"),
+            ],
+            "object: pipeline",
+        ),
+        ActionSpec::new("list", "Saved pipelines", &[], "array of pipeline"),
+        ActionSpec::new("get", "One saved pipeline", &[NAME], "object: { id, name, description, steps, createdAt, updatedAt }"),
+        ActionSpec::new("delete", "Delete a saved pipeline", &[NAME], "object: { ok }"),
+    ],
+};
+
+pub fn execute(action: &str, payload: &Value) -> ToolResult {
+    match action {
+        "run" => pipeline_run(payload),
+        "save" => pipeline_save(payload),
+        "list" => pipeline_list(),
+        "get" => pipeline_get(payload),
+        "delete" => pipeline_delete(payload),
+        _ => Err(ToolError::unsupported("pipeline", action)),
+    }
+}
+
+/// Check step shape and that every step targets a declared action.
+fn validate_steps(steps: &Value) -> ToolResult<&Vec<Value>> {
+    let steps = steps
+        .as_array()
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| ToolError::invalid_field("steps", "steps must be a non-empty array"))?;
+    for (idx, step) in steps.iter().enumerate() {
+        let domain = step["domain"].as_str().unwrap_or_default();
+        let action = step["action"].as_str().unwrap_or_default();
+        if domain.is_empty() || action.is_empty() {
+            return Err(ToolError::invalid_field("steps", format!("step {idx}: domain and action are required")));
+        }
+        if domain == SPEC.name {
+            return Err(ToolError::invalid_field("steps", format!("step {idx}: pipelines cannot nest")));
+        }
+        registry::find_step(super::DOMAINS, domain, action).map_err(|e| e.context(&format!("step {idx}")))?;
+        let template = &step["payloadTemplate"];
+        if !(template.is_null() || template.is_object()) {
+            return Err(ToolError::invalid_field("steps", format!("step {idx}: payloadTemplate must be an object")));
+        }
+    }
+    Ok(steps)
+}
+
+fn lookup<'a>(ctx: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(ctx, |value, segment| match value {
+        Value::Object(map) => map.get(segment),
+        Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+        _ => None,
+    })
+}
+
+fn resolve(ctx: &Value, path: &str) -> Result<Value, String> {
+    lookup(ctx, path.trim())
+        .cloned()
+        .ok_or_else(|| format!("unknown binding {{{{{}}}}}", path.trim()))
+}
+
+fn render_string(text: &str, ctx: &Value) -> Result<Value, String> {
+    let trimmed = text.trim();
+    if let Some(path) = trimmed.strip_prefix("{{").and_then(|t| t.strip_suffix("}}")) {
+        if !path.contains("{{") && !path.contains("}}") {
+            return resolve(ctx, path);
+        }
+    }
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        match resolve(ctx, &rest[start + 2..start + 2 + len])? {
+            Value::String(s) => out.push_str(&s),
+            other => out.push_str(&other.to_string()),
+        }
+        rest = &rest[start + 2 + len + 2..];
+    }
+    out.push_str(rest);
+    Ok(Value::String(out))
+}
+
+/// Substitute `{{path}}` bindings throughout a payload template.
+fn render(template: &Value, ctx: &Value) -> Result<Value, String> {
+    match template {
+        Value::String(s) => render_string(s, ctx),
+        Value::Array(items) => items.iter().map(|v| render(v, ctx)).collect::<Result<_, _>>().map(Value::Array),
+        Value::Object(map) => {
+            let mut out = Map::new();
+            for (k, v) in map {
+                out.insert(k.clone(), render(v, ctx)?);
+            }
+            Ok(Value::Object(out))
+        }
+        other => Ok(other.clone()),
+    }
+}
+
+fn run_steps(steps: &[Value], input: Value) -> ToolResult {
+    let default_template = json!({ "input": "{{prev}}" });
+    let mut ctx = json!({ "input": input, "prev": input, "steps": [] });
+    let mut trace = Vec::with_capacity(steps.len());
+    for (idx, step) in steps.iter().enumerate() {
+        let domain = step["domain"].as_str().unwrap_or_default();
+        let action = step["action"].as_str().unwrap_or_default();
+        let label = format!("step {idx} ({domain}.{action})");
+        report_progress(json!({
+            "step": idx,
+            "totalSteps": steps.len(),
+            "domain": domain,
+            "action": action,
+        }))?;
+        let template = match &step["payloadTemplate"] {
+            Value::Null => &default_template,
+            t => t,
+        };
+        let payload = render(template, &ctx)
+            .map_err(|e| ToolError::invalid_field("payloadTemplate", format!("{label}: {e}")))?;
+        super::api::check_step(domain, action, &payload).map_err(|e| e.context(&label))?;
+        let started = Instant::now();
+        let output = super::execute_tool(domain, action, &payload).map_err(|e| e.context(&label))?;
+        trace.push(json!({
+            "domain": domain,
+            "action": action,
+            "output": output,
+            "durationMs": started.elapsed().as_millis() as u64,
+        }));
+        if let Some(outputs) = ctx["steps"].as_array_mut() {
+            outputs.push(output.clone());
+        }
+        ctx["prev"] = output;
+    }
+    Ok(json!({ "output": ctx["prev"], "steps": trace }))
+}
+
+fn pipeline_run(payload: &Value) -> ToolResult {
+    let input = payload.get("input").cloned().unwrap_or(Value::Null);
+    let name = payload["name"].as_str().unwrap_or_default();
+    if !name.is_empty() {
+        let saved = pipeline_get(payload)?;
+        let steps = validate_steps(&saved["steps"]).map_err(|e| e.context(&format!("pipeline {name}")))?;
+        return run_steps(steps, input);
+    }
+    run_steps(validate_steps(&payload["steps"])?, input)
+}
+
+fn require_name(payload: &Value) -> ToolResult<&str> {
+    match payload["name"].as_str().map(str::trim).unwrap_or_default() {
+        "" => Err(ToolError::invalid_field("name", "pipeline name is required")),
+        name => Ok(name),
+    }
+}
+
+fn pipeline_save(payload: &Value) -> ToolResult {
+    let name = require_name(payload)?;
+    let steps = validate_steps(&payload["steps"])?;
+    let description = payload["description"].as_str().unwrap_or_default();
+    let steps_json = serde_json::to_string(steps).map_err(|e| format!("serialize steps failed: {e}"))?;
+    let conn = db_conn()?;
+    conn.execute(
+        "INSERT INTO pipelines(name, description, steps, updated_at) VALUES(?1, ?2, ?3, CURRENT_TIMESTAMP)
+         ON CONFLICT(name) DO UPDATE SET description=excluded.description, steps=excluded.steps, updated_at=CURRENT_TIMESTAMP",
+        params![name, description, steps_json],
+    )
+    .map_err(|e| format!("save pipeline failed: {e}"))?;
+    pipeline_get(&json!({ "name": name }))
+}
+
+/// `(domain, action, payload)` of a call and of every step it wraps when it
+/// starts a job or runs or saves a pipeline (inline or saved by name), so a
+/// policy on a domain also covers calls that reach it indirectly.
+/// Saved pipelines are looked up in `conn` when one is given.
+pub fn reached_calls(conn: Option<&Connection>, domain: &str, action: &str, payload: &Value, out: &mut Vec<(String, String, Value)>) {
+    out.push((domain.to_string(), action.to_string(), payload.clone()));
+    // Jobs and pipelines refuse to nest themselves; this only bounds bad input.
+    if out.len() > 64 {
+        return;
+    }
+    match (domain, action) {
+        ("jobs", "start") => {
+            let inner = payload["domain"].as_str().unwrap_or_default();
+            let inner_action = payload["action"].as_str().unwrap_or_default();
+            reached_calls(conn, inner, inner_action, &payload["payload"], out);
+        }
+        ("pipeline", "run" | "save") => {
+            let saved = match (&payload["steps"], payload["name"].as_str()) {
+                (Value::Null, Some(name)) if action == "run" => conn
+                    .and_then(|conn| {
+                        conn.query_row("SELECT steps FROM pipelines WHERE name = ?1", params![name], |r| r.get::<_, String>(0))
+                            .ok()
+                    })
+                    .and_then(|text| serde_json::from_str(&text).ok()),
+                _ => None,
+            };
+            let steps = saved.as_ref().unwrap_or(&payload["steps"]);
+            for step in steps.as_array().into_iter().flatten() {
+                let inner = step["domain"].as_str().unwrap_or_default();
+                let inner_action = step["action"].as_str().unwrap_or_default();
+                reached_calls(conn, inner, inner_action, &step["payloadTemplate"], out);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn row_to_json(row: &rusqlite::Row) -> rusqlite::Result<Value> {
+    let steps: String = row.get(3)?;
+    Ok(json!({
+        "id": row.get::<_, i64>(0)?,
+        "name": row.get::<_, String>(1)?,
+        "description": row.get::<_, String>(2)?,
+        "steps": serde_json::from_str::<Value>(&steps).unwrap_or(Value::Array(Vec::new())),
+        "createdAt": row.get::<_, String>(4)?,
+        "updatedAt": row.get::<_, String>(5)?,
+    }))
+}
+
+const SELECT_COLUMNS: &str = "SELECT id, name, description, steps, created_at, updated_at FROM pipelines";
+
+fn pipeline_list() -> ToolResult {
+    let conn = db_conn()?;
+    let mut stmt = conn
+        .prepare(&format!("{SELECT_COLUMNS} ORDER BY name"))
+        .map_err(|e| format!("prepare query failed: {e}"))?;
+    let rows = stmt
+        .query_map([], row_to_json)
+        .map_err(|e| format!("query pipelines failed: {e}"))?;
+    let mut list = Vec::new();
+    for row in rows {
+        list.push(row.map_err(|e| e.to_string())?);
+    }
+    Ok(Value::Array(list))
+}
+
+fn pipeline_get(payload: &Value) -> ToolResult {
+    let name = require_name(payload)?;
+    let conn = db_conn()?;
+    conn.query_row(&format!("{SELECT_COLUMNS} WHERE name = ?1"), params![name], row_to_json)
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => ToolError::not_found(format!("pipeline not found: {name}")),
+            e => ToolError::from(format!("query pipeline failed: {e}")),
+        })
+}
+
+fn pipeline_delete(payload: &Value) -> ToolResult {
+    let name = require_name(payload)?;
+    let conn = db_conn()?;
+    let n = conn
+        .execute("DELETE FROM pipelines WHERE name = ?1", params![name])
+        .map_err(|e| format!("delete pipeline failed: {e}"))?;
+    if n == 0 {
+        return Err(ToolError::not_found(format!("pipeline not found: {name}")));
+    }
+    Ok(json!({ "ok": true }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_binding_keeps_type_and_text_binding_interpolates() {
+        let ctx = json!({ "input": "x", "prev": { "payload": { "exp": 1700000000 } }, "steps": [] });
+        let out = render(&json!({ "input": "{{prev.payload.exp}}", "label": "exp={{ prev.payload.exp }}" }), &ctx)
+            .expect("render");
+        assert_eq!(out["input"], 1700000000);
+        assert_eq!(out["label"], "exp=1700000000");
+        assert!(render(&json!("{{prev.missing}}"), &ctx).is_err());
+    }
+
+    #[test]
+    fn runs_decode_format_yaml_chain() {
+        let steps = json!([
+            { "domain": "encode", "action": "base64_decode" },
+            { "domain": "format", "action": "json" },
+            { "domain": "convert", "action": "json_to_yaml" },
+        ]);
+        let input = json!("eyJhIjoxfQ=="); // {"a":1}
+        let out = run_steps(validate_steps(&steps).expect("steps"), input).expect("run");
+        assert_eq!(out["output"], "a: 1\n");
+        assert_eq!(out["steps"].as_array().map(Vec::len), Some(3));
+    }
+
+    #[test]
+    fn step_errors_name_the_step() {
+        let steps = json!([{ "domain": "format", "action": "json" }]);
+        let err = run_steps(validate_steps(&steps).expect("steps"), json!("{oops")).expect_err("should fail");
+        assert_eq!(err.code(), "PARSE_ERROR");
+        assert!(err.message().starts_with("step 0 (format.json)"));
+        let nested = json!([{ "domain": "pipeline", "action": "run" }]);
+        assert!(validate_steps(&nested).is_err());
+    }
+}
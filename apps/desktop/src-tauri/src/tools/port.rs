@@ -1,7 +1,16 @@
+//! Port usage inspection. Windows reads `netstat -ano` and `tasklist`; Linux
+//! reads `/proc/net/{tcp,udp}{,6}` and maps socket inodes to processes via
+//! `/proc/<pid>/fd`, falling back to `ss` when `/proc/net` is unavailable.
+//! Both fill the same structures, with Linux states renamed to the Windows
+//! spelling (`LISTENING`, `FIN_WAIT_1`, ...) so the UI sees one vocabulary.
+//! Other platforms (macOS included) have no backend and report an error.
+
 use serde::Serialize;
 use serde_json::{json, Value};
 use std::collections::{BTreeSet, HashMap};
-use std::process::Command;
+
+use super::error::{ToolError, ToolResult};
+use super::registry::{ActionSpec, DomainSpec, FieldSpec};
 
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -23,6 +32,7 @@ struct PortProcessSummary {
     connection_count: usize,
 }
 
+#[cfg(any(windows, test))]
 fn parse_netstat_entries(raw: &str) -> Vec<PortUsageEntry> {
     let mut out = Vec::new();
     for line in raw.lines() {
@@ -74,35 +84,434 @@ fn parse_netstat_entries(raw: &str) -> Vec<PortUsageEntry> {
     out
 }
 
-fn list_process_names() -> HashMap<u32, String> {
-    let mut out = HashMap::new();
-    let output = match Command::new("tasklist").args(["/FO", "CSV", "/NH"]).output() {
-        Ok(v) => v,
-        Err(_) => return out,
+#[cfg(windows)]
+mod windows {
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+    use std::process::Command;
+
+    use super::{parse_netstat_entries, PortUsageEntry};
+    use crate::tools::error::{ToolError, ToolResult};
+
+    fn list_process_names() -> HashMap<u32, String> {
+        let mut out = HashMap::new();
+        let output = match Command::new("tasklist").args(["/FO", "CSV", "/NH"]).output() {
+            Ok(v) => v,
+            Err(_) => return out,
+        };
+        let text = String::from_utf8_lossy(&output.stdout).to_string();
+        let mut rdr = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(text.as_bytes());
+        for rec in rdr.records().flatten() {
+            if rec.len() < 2 {
+                continue;
+            }
+            let name = rec.get(0).unwrap_or("UNKNOWN").trim().to_string();
+            let pid = rec
+                .get(1)
+                .unwrap_or_default()
+                .replace(',', "")
+                .trim()
+                .parse::<u32>()
+                .unwrap_or(0);
+            if pid > 0 && !name.is_empty() {
+                out.insert(pid, name);
+            }
+        }
+        out
+    }
+
+    pub fn collect_entries() -> ToolResult<Vec<PortUsageEntry>> {
+        let output = Command::new("netstat")
+            .arg("-ano")
+            .output()
+            .map_err(|e| format!("netstat failed: {e}"))?;
+        let text = String::from_utf8_lossy(&output.stdout).to_string();
+        let mut entries = parse_netstat_entries(&text);
+        let proc_names = list_process_names();
+        for item in &mut entries {
+            item.process_name = proc_names
+                .get(&item.pid)
+                .cloned()
+                .unwrap_or_else(|| "UNKNOWN".to_string());
+        }
+        Ok(entries)
+    }
+
+    pub fn process_detail(pid: u32) -> ToolResult {
+        let output = Command::new("powershell")
+            .args([
+                "-NoProfile",
+                "-Command",
+                &format!(
+                    "Get-CimInstance Win32_Process -Filter \"ProcessId = {pid}\" | Select-Object ProcessId,Name,ExecutablePath,CommandLine,CreationDate | ConvertTo-Json -Compress"
+                ),
+            ])
+            .output()
+            .map_err(|e| format!("query process detail failed: {e}"))?;
+        let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if stdout.is_empty() || stdout == "null" {
+            return Err(ToolError::not_found(format!("process not found: {pid}")));
+        }
+        let parsed: Value =
+            serde_json::from_str(&stdout).map_err(|e| format!("parse process detail failed: {e}"))?;
+        Ok(json!({
+            "pid": parsed["ProcessId"].as_u64().unwrap_or(pid as u64),
+            "name": parsed["Name"].as_str().unwrap_or("UNKNOWN"),
+            "path": parsed["ExecutablePath"].as_str().unwrap_or(""),
+            "commandLine": parsed["CommandLine"].as_str().unwrap_or(""),
+            "startTime": parsed["CreationDate"].as_str().unwrap_or(""),
+        }))
+    }
+
+    pub fn kill(pid: u32, force: bool) -> ToolResult {
+        let mut cmd = Command::new("taskkill");
+        cmd.args(["/PID", &pid.to_string()]);
+        if force {
+            cmd.arg("/F");
+        }
+        let output = cmd
+            .output()
+            .map_err(|e| format!("taskkill failed to start: {e}"))?;
+        if output.status.success() {
+            return Ok(json!({ "ok": true, "pid": pid }));
+        }
+        let err = String::from_utf8_lossy(&output.stderr).to_string();
+        let out = String::from_utf8_lossy(&output.stdout).to_string();
+        Err(ToolError::Execution(format!(
+            "taskkill failed: {}",
+            if !err.trim().is_empty() { err.trim() } else { out.trim() }
+        )))
+    }
+}
+
+// ── Linux ────────────────────────────────────────────────────
+
+/// `/proc/net/tcp` state codes, spelled the way Windows netstat does.
+#[cfg(any(target_os = "linux", test))]
+fn proc_tcp_state(code: &str) -> &'static str {
+    match code {
+        "01" => "ESTABLISHED",
+        "02" => "SYN_SENT",
+        "03" => "SYN_RECEIVED",
+        "04" => "FIN_WAIT_1",
+        "05" => "FIN_WAIT_2",
+        "06" => "TIME_WAIT",
+        "07" => "CLOSED",
+        "08" => "CLOSE_WAIT",
+        "09" => "LAST_ACK",
+        "0A" => "LISTENING",
+        "0B" => "CLOSING",
+        _ => "UNKNOWN",
+    }
+}
+
+/// Decode `0100007F:1F90` (IPv4) or a 32-digit IPv6 address; the kernel
+/// prints each 32-bit word in host (little-endian) byte order.
+#[cfg(any(target_os = "linux", test))]
+fn decode_proc_address(raw: &str) -> Option<String> {
+    let (addr, port) = raw.split_once(':')?;
+    let port = u16::from_str_radix(port, 16).ok()?;
+    let mut bytes = Vec::with_capacity(16);
+    for chunk in addr.as_bytes().chunks(8) {
+        let word = u32::from_str_radix(std::str::from_utf8(chunk).ok()?, 16).ok()?;
+        bytes.extend_from_slice(&word.to_le_bytes());
+    }
+    match bytes.len() {
+        4 => Some(format!("{}.{}.{}.{}:{port}", bytes[0], bytes[1], bytes[2], bytes[3])),
+        16 => {
+            let octets: [u8; 16] = bytes.try_into().ok()?;
+            Some(format!("[{}]:{port}", std::net::Ipv6Addr::from(octets)))
+        }
+        _ => None,
+    }
+}
+
+/// Parse one `/proc/net/{tcp,udp}{,6}` table into entries keyed by socket inode.
+#[cfg(any(target_os = "linux", test))]
+fn parse_proc_net(raw: &str, protocol: &str) -> Vec<(u64, PortUsageEntry)> {
+    let mut out = Vec::new();
+    for line in raw.lines().skip(1) {
+        let parts = line.split_whitespace().collect::<Vec<_>>();
+        if parts.len() < 10 {
+            continue;
+        }
+        let (Some(local), Some(remote)) = (decode_proc_address(parts[1]), decode_proc_address(parts[2])) else {
+            continue;
+        };
+        let inode = parts[9].parse::<u64>().unwrap_or(0);
+        let (remote_address, state) = if protocol == "TCP" {
+            (remote, Some(proc_tcp_state(parts[3]).to_string()))
+        } else if parts[2].trim_start_matches('0').starts_with(':') && parts[2].ends_with(":0000") {
+            // Unconnected UDP socket; netstat shows the peer as `*:*`.
+            ("*:*".to_string(), None)
+        } else {
+            (remote, None)
+        };
+        out.push((
+            inode,
+            PortUsageEntry {
+                protocol: protocol.to_string(),
+                local_address: local,
+                remote_address,
+                state,
+                pid: 0,
+                process_name: String::new(),
+            },
+        ));
+    }
+    out
+}
+
+/// Spell `ss` states the way Windows netstat does.
+#[cfg(any(target_os = "linux", test))]
+fn ss_state(state: &str) -> Option<String> {
+    let mapped = match state {
+        "UNCONN" => return None,
+        "LISTEN" => "LISTENING",
+        "ESTAB" => "ESTABLISHED",
+        "SYN-RECV" => "SYN_RECEIVED",
+        "FIN-WAIT-1" => "FIN_WAIT_1",
+        "FIN-WAIT-2" => "FIN_WAIT_2",
+        "CLOSE" | "UNCONNECTED" => "CLOSED",
+        other => return Some(other.replace('-', "_")),
     };
-    let text = String::from_utf8_lossy(&output.stdout).to_string();
-    let mut rdr = csv::ReaderBuilder::new()
-        .has_headers(false)
-        .from_reader(text.as_bytes());
-    for rec in rdr.records().flatten() {
-        if rec.len() < 2 {
+    Some(mapped.to_string())
+}
+
+/// Parse `ss -tunapH` output (`Netid State Recv-Q Send-Q Local Peer Process`).
+#[cfg(any(target_os = "linux", test))]
+fn parse_ss_entries(raw: &str) -> Vec<PortUsageEntry> {
+    let mut out = Vec::new();
+    for line in raw.lines() {
+        let parts = line.split_whitespace().collect::<Vec<_>>();
+        if parts.len() < 6 {
             continue;
         }
-        let name = rec.get(0).unwrap_or("UNKNOWN").trim().to_string();
-        let pid = rec
-            .get(1)
-            .unwrap_or_default()
-            .replace(',', "")
-            .trim()
-            .parse::<u32>()
+        let protocol = match parts[0] {
+            "tcp" => "TCP",
+            "udp" => "UDP",
+            _ => continue,
+        };
+        let process = parts[6..].join(" ");
+        let pid = process
+            .split("pid=")
+            .nth(1)
+            .and_then(|rest| rest.split(|c: char| !c.is_ascii_digit()).next())
+            .and_then(|pid| pid.parse::<u32>().ok())
             .unwrap_or(0);
-        if pid > 0 && !name.is_empty() {
-            out.insert(pid, name);
-        }
+        let process_name = process
+            .split("((\"")
+            .nth(1)
+            .and_then(|rest| rest.split('"').next())
+            .unwrap_or_default()
+            .to_string();
+        let state = if protocol == "TCP" { ss_state(parts[1]) } else { None };
+        let remote = parts[5];
+        let remote_address = if protocol == "UDP" && remote.ends_with(":*") { "*:*" } else { remote };
+        out.push(PortUsageEntry {
+            protocol: protocol.to_string(),
+            local_address: parts[4].to_string(),
+            remote_address: remote_address.to_string(),
+            state,
+            pid,
+            process_name,
+        });
     }
     out
 }
 
+#[cfg(target_os = "linux")]
+mod linux {
+    use chrono::{Local, TimeZone};
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::process::Command;
+
+    use super::{parse_proc_net, parse_ss_entries, PortUsageEntry};
+    use crate::tools::error::{ToolError, ToolResult};
+
+    /// `USER_HZ`, the unit of `/proc/<pid>/stat` times; 100 on every
+    /// mainstream architecture.
+    const CLOCK_TICKS: u64 = 100;
+
+    fn pids() -> Vec<u32> {
+        fs::read_dir("/proc")
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter_map(|e| e.file_name().to_str()?.parse::<u32>().ok())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn process_name(pid: u32) -> Option<String> {
+        fs::read_to_string(format!("/proc/{pid}/comm"))
+            .ok()
+            .map(|s| s.trim().to_string())
+    }
+
+    /// Socket inode → (pid, name). Other users' processes are only visible
+    /// with enough privileges; their sockets stay unattributed.
+    fn socket_owners() -> HashMap<u64, (u32, String)> {
+        let mut owners = HashMap::new();
+        for pid in pids() {
+            let Ok(fds) = fs::read_dir(format!("/proc/{pid}/fd")) else { continue };
+            let mut name = None;
+            for fd in fds.flatten() {
+                let Ok(target) = fs::read_link(fd.path()) else { continue };
+                let target = target.to_string_lossy();
+                let Some(inode) = target
+                    .strip_prefix("socket:[")
+                    .and_then(|rest| rest.strip_suffix(']'))
+                    .and_then(|n| n.parse::<u64>().ok())
+                else {
+                    continue;
+                };
+                let name = name.get_or_insert_with(|| process_name(pid).unwrap_or_default());
+                owners.entry(inode).or_insert_with(|| (pid, name.clone()));
+            }
+        }
+        owners
+    }
+
+    pub fn collect_entries() -> ToolResult<Vec<PortUsageEntry>> {
+        let tables = [
+            ("/proc/net/tcp", "TCP"),
+            ("/proc/net/tcp6", "TCP"),
+            ("/proc/net/udp", "UDP"),
+            ("/proc/net/udp6", "UDP"),
+        ];
+        let mut sockets = Vec::new();
+        let mut readable = false;
+        for (path, protocol) in tables {
+            if let Ok(raw) = fs::read_to_string(path) {
+                readable = true;
+                sockets.extend(parse_proc_net(&raw, protocol));
+            }
+        }
+        if !readable {
+            return ss_entries();
+        }
+        let owners = socket_owners();
+        Ok(sockets
+            .into_iter()
+            .map(|(inode, mut entry)| {
+                match owners.get(&inode) {
+                    Some((pid, name)) => {
+                        entry.pid = *pid;
+                        entry.process_name = name.clone();
+                    }
+                    None => entry.process_name = "UNKNOWN".to_string(),
+                }
+                entry
+            })
+            .collect())
+    }
+
+    fn ss_entries() -> ToolResult<Vec<PortUsageEntry>> {
+        let output = Command::new("ss")
+            .arg("-tunapH")
+            .output()
+            .map_err(|e| format!("/proc/net is unavailable and ss failed: {e}"))?;
+        if !output.status.success() {
+            let err = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            return Err(ToolError::Execution(format!("/proc/net is unavailable and ss failed: {err}")));
+        }
+        let mut entries = parse_ss_entries(&String::from_utf8_lossy(&output.stdout));
+        for entry in &mut entries {
+            if entry.process_name.is_empty() {
+                entry.process_name = "UNKNOWN".to_string();
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Wall-clock start of a process from its `stat` start time (ticks since boot).
+    fn start_time(pid: u32) -> Option<String> {
+        let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+        // Fields after the parenthesised command name; starttime is field 22 overall.
+        let rest = &stat[stat.rfind(')')? + 2..];
+        let ticks: u64 = rest.split_whitespace().nth(19)?.parse().ok()?;
+        let btime: i64 = fs::read_to_string("/proc/stat")
+            .ok()?
+            .lines()
+            .find_map(|l| l.strip_prefix("btime ")?.trim().parse().ok())?;
+        let started = btime + (ticks / CLOCK_TICKS) as i64;
+        Local.timestamp_opt(started, 0).single().map(|t| t.to_rfc3339())
+    }
+
+    pub fn process_detail(pid: u32) -> ToolResult {
+        let Some(name) = process_name(pid) else {
+            return Err(ToolError::not_found(format!("process not found: {pid}")));
+        };
+        let path = fs::read_link(format!("/proc/{pid}/exe"))
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let command_line = fs::read(format!("/proc/{pid}/cmdline"))
+            .map(|raw| {
+                raw.split(|b| *b == 0)
+                    .filter(|arg| !arg.is_empty())
+                    .map(|arg| String::from_utf8_lossy(arg).into_owned())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .unwrap_or_default();
+        Ok(json!({
+            "pid": pid,
+            "name": name,
+            "path": path,
+            "commandLine": command_line,
+            "startTime": start_time(pid).unwrap_or_default(),
+        }))
+    }
+
+    pub fn kill(pid: u32, force: bool) -> ToolResult {
+        let signal = if force { "KILL" } else { "TERM" };
+        let output = Command::new("kill")
+            .args(["-s", signal, &pid.to_string()])
+            .output()
+            .map_err(|e| format!("kill failed to start: {e}"))?;
+        if output.status.success() {
+            return Ok(json!({ "ok": true, "pid": pid }));
+        }
+        let err = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        if err.contains("Operation not permitted") {
+            return Err(ToolError::PermissionDenied(format!("kill {pid}: {err}")));
+        }
+        Err(ToolError::Execution(format!("kill failed: {err}")))
+    }
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+mod unsupported {
+    use crate::tools::error::{ToolError, ToolResult};
+
+    use super::PortUsageEntry;
+
+    pub fn collect_entries() -> ToolResult<Vec<PortUsageEntry>> {
+        Err(ToolError::unsupported("port", "usage"))
+    }
+
+    pub fn process_detail(_pid: u32) -> ToolResult {
+        Err(ToolError::unsupported("port", "process_detail"))
+    }
+
+    pub fn kill(_pid: u32, _force: bool) -> ToolResult {
+        Err(ToolError::unsupported("port", "kill"))
+    }
+}
+
+#[cfg(target_os = "linux")]
+use linux as platform;
+#[cfg(not(any(windows, target_os = "linux")))]
+use unsupported as platform;
+#[cfg(windows)]
+use windows as platform;
+
 fn extract_port(local_address: &str) -> Option<String> {
     let port = local_address.rsplit(':').next().unwrap_or_default().trim();
     if port.is_empty() || port == "*" {
@@ -150,22 +559,38 @@ fn build_process_summaries(entries: &[PortUsageEntry]) -> Vec<PortProcessSummary
     out
 }
 
-pub fn execute(action: &str, _payload: &Value) -> Result<Value, String> {
+const PID: FieldSpec = FieldSpec::required("pid", "integer", "process id");
+
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "port",
+    description: "Port usage inspection and process control",
+    execute,
+    actions: &[
+        ActionSpec::new(
+            "usage",
+            "List TCP/UDP sockets with owning processes",
+            &[],
+            "object: { summary, stateCounts, processSummaries, connections }",
+        ),
+        ActionSpec::new(
+            "process_detail",
+            "Path, command line and start time of a process",
+            &[PID],
+            "object: { pid, name, path, commandLine, startTime }",
+        ),
+        ActionSpec::new(
+            "kill",
+            "Terminate a process",
+            &[PID, FieldSpec::optional("force", "boolean", "force kill, default true")],
+            "object: { ok, pid }",
+        ),
+    ],
+};
+
+pub fn execute(action: &str, _payload: &Value) -> ToolResult {
     match action {
         "usage" => {
-            let output = Command::new("netstat")
-                .arg("-ano")
-                .output()
-                .map_err(|e| format!("netstat failed: {e}"))?;
-            let text = String::from_utf8_lossy(&output.stdout).to_string();
-            let mut entries = parse_netstat_entries(&text);
-            let proc_names = list_process_names();
-            for item in &mut entries {
-                item.process_name = proc_names
-                    .get(&item.pid)
-                    .cloned()
-                    .unwrap_or_else(|| "UNKNOWN".to_string());
-            }
+            let entries = platform::collect_entries()?;
             let mut state_counts: HashMap<String, usize> = HashMap::new();
             let mut tcp_count = 0usize;
             let mut udp_count = 0usize;
@@ -193,61 +618,80 @@ pub fn execute(action: &str, _payload: &Value) -> Result<Value, String> {
         }
         "process_detail" => process_detail(_payload),
         "kill" => kill_process(_payload),
-        _ => Err(format!("unsupported port action: {action}")),
-    }
-}
-
-fn process_detail(payload: &Value) -> Result<Value, String> {
-    let pid = payload["pid"].as_u64().unwrap_or(0) as u32;
-    if pid == 0 {
-        return Err("invalid pid".into());
-    }
-    let output = Command::new("powershell")
-        .args([
-            "-NoProfile",
-            "-Command",
-            &format!(
-                "Get-CimInstance Win32_Process -Filter \"ProcessId = {pid}\" | Select-Object ProcessId,Name,ExecutablePath,CommandLine,CreationDate | ConvertTo-Json -Compress"
-            ),
-        ])
-        .output()
-        .map_err(|e| format!("query process detail failed: {e}"))?;
-    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
-    if stdout.is_empty() || stdout == "null" {
-        return Err(format!("process not found: {pid}"));
-    }
-    let parsed: Value =
-        serde_json::from_str(&stdout).map_err(|e| format!("parse process detail failed: {e}"))?;
-    Ok(json!({
-        "pid": parsed["ProcessId"].as_u64().unwrap_or(pid as u64),
-        "name": parsed["Name"].as_str().unwrap_or("UNKNOWN"),
-        "path": parsed["ExecutablePath"].as_str().unwrap_or(""),
-        "commandLine": parsed["CommandLine"].as_str().unwrap_or(""),
-        "startTime": parsed["CreationDate"].as_str().unwrap_or(""),
-    }))
-}
-
-fn kill_process(payload: &Value) -> Result<Value, String> {
-    let pid = payload["pid"].as_u64().unwrap_or(0) as u32;
+        _ => Err(ToolError::unsupported("port", action)),
+    }
+}
+/// A positive pid that fits `pid_t`. Anything else is rejected rather than
+/// truncated: `kill` treats negative pids as process groups and -1 as every
+/// process.
+fn pid_field(payload: &Value) -> ToolResult<u32> {
+    payload["pid"]
+        .as_i64()
+        .and_then(|n| i32::try_from(n).ok())
+        .filter(|n| *n > 0)
+        .map(|n| n as u32)
+        .ok_or_else(|| ToolError::invalid_field("pid", "pid must be a positive integer up to 2147483647"))
+}
+
+fn process_detail(payload: &Value) -> ToolResult {
+    platform::process_detail(pid_field(payload)?)
+}
+
+fn kill_process(payload: &Value) -> ToolResult {
+    let pid = pid_field(payload)?;
     let force = payload["force"].as_bool().unwrap_or(true);
-    if pid == 0 {
-        return Err("invalid pid".into());
-    }
-    let mut cmd = Command::new("taskkill");
-    cmd.args(["/PID", &pid.to_string()]);
-    if force {
-        cmd.arg("/F");
-    }
-    let output = cmd
-        .output()
-        .map_err(|e| format!("taskkill failed to start: {e}"))?;
-    if output.status.success() {
-        return Ok(json!({ "ok": true, "pid": pid }));
-    }
-    let err = String::from_utf8_lossy(&output.stderr).to_string();
-    let out = String::from_utf8_lossy(&output.stdout).to_string();
-    Err(format!(
-        "taskkill failed: {}",
-        if !err.trim().is_empty() { err.trim() } else { out.trim() }
-    ))
+    platform::kill(pid, force)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_proc_net_tables() {
+        let tcp = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode\n\
+                   \x20  0: 0100007F:1F90 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 4242 1 0 100 0 0 10 0\n\
+                   \x20  1: 0100007F:1F90 0100007F:D431 01 00000000:00000000 00:00000000 00000000  1000        0 4243 1 0 20 4 30 10 -1";
+        let entries = parse_proc_net(tcp, "TCP");
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].0, 4242);
+        assert_eq!(entries[0].1.local_address, "127.0.0.1:8080");
+        assert_eq!(entries[0].1.state.as_deref(), Some("LISTENING"));
+        assert_eq!(entries[1].1.remote_address, "127.0.0.1:54321");
+
+        let udp6 = "header\n   0: 00000000000000000000000001000000:0035 00000000000000000000000000000000:0000 07 00000000:00000000 00:00000000 00000000   101        0 777 2 0 0";
+        let entries = parse_proc_net(udp6, "UDP");
+        assert_eq!(entries[0].1.local_address, "[::1]:53");
+        assert_eq!(entries[0].1.remote_address, "*:*");
+        assert_eq!(entries[0].1.state, None);
+    }
+
+    #[test]
+    fn parses_ss_output_and_summarises() {
+        let raw = "tcp   LISTEN 0      4096   127.0.0.1:5432   0.0.0.0:*   users:((\"postgres\",pid=812,fd=7))\n\
+                   udp   UNCONN 0      0      0.0.0.0:5353     0.0.0.0:*   users:((\"avahi\",pid=90,fd=12))\n\
+                   tcp   ESTAB  0      0      10.0.0.2:41000   1.1.1.1:443";
+        let entries = parse_ss_entries(raw);
+        assert_eq!(entries.len(), 3);
+        assert_eq!((entries[0].pid, entries[0].process_name.as_str()), (812, "postgres"));
+        assert_eq!(entries[1].remote_address, "*:*");
+        assert_eq!(entries[2].state.as_deref(), Some("ESTABLISHED"));
+        let summaries = build_process_summaries(&entries);
+        let postgres = summaries.iter().find(|s| s.pid == 812).expect("postgres");
+        assert_eq!(postgres.listening_ports, vec!["5432".to_string()]);
+        let avahi = summaries.iter().find(|s| s.pid == 90).expect("avahi");
+        assert_eq!(avahi.listening_ports, vec!["5353".to_string()]);
+
+        let netstat = parse_netstat_entries("  TCP    0.0.0.0:135    0.0.0.0:0    LISTENING    1012");
+        assert_eq!(netstat[0].state.as_deref(), Some("LISTENING"));
+    }
+
+    #[test]
+    fn rejects_pids_outside_pid_t() {
+        assert_eq!(pid_field(&json!({ "pid": 812 })).expect("pid"), 812);
+        for pid in [json!(0), json!(-1), json!(4294967297u64), json!(2147483648u64), json!(1.5), json!("1")] {
+            let err = kill_process(&json!({ "pid": pid })).expect_err("rejected");
+            assert_eq!(err.code(), "INVALID_PAYLOAD", "{pid}");
+        }
+    }
 }
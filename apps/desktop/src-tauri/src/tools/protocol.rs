@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Instant;
+
+use super::error::{ToolError, ToolResult};
+
+/// Request envelope shared by `tool_execute` and the local HTTP API.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct ToolRequest {
+    pub request_id: String,
+    pub domain: String,
+    pub action: String,
+    #[serde(default)]
+    pub payload: Value,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct ToolErrorBody {
+    pub code: String,
+    pub message: String,
+    pub details: Option<Value>,
+}
+
+impl From<&ToolError> for ToolErrorBody {
+    fn from(err: &ToolError) -> Self {
+        ToolErrorBody {
+            code: err.code().to_string(),
+            message: err.message(),
+            details: err.details(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct ToolMeta {
+    pub duration_ms: u128,
+    pub warnings: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct ToolResponse {
+    pub request_id: String,
+    pub ok: bool,
+    pub data: Option<Value>,
+    pub error: Option<ToolErrorBody>,
+    pub meta: ToolMeta,
+}
+
+/// Run one request through the registry, record it in history, and wrap the
+/// outcome in a response.
+pub fn execute_request(request: ToolRequest) -> ToolResponse {
+    let start = Instant::now();
+    let outcome = super::execute_tool(&request.domain, &request.action, &request.payload);
+    super::history::record(&request.domain, &request.action, &request.payload, &outcome, start);
+    respond(request.request_id, outcome, start)
+}
+
+/// Wrap an already computed outcome; `start` is when handling began.
+pub fn respond(request_id: String, outcome: ToolResult, start: Instant) -> ToolResponse {
+    let (data, error) = match outcome {
+        Ok(data) => (Some(data), None),
+        Err(err) => (None, Some(ToolErrorBody::from(&err))),
+    };
+    ToolResponse {
+        request_id,
+        ok: error.is_none(),
+        data,
+        error,
+        meta: ToolMeta {
+            duration_ms: start.elapsed().as_millis(),
+            warnings: None,
+        },
+    }
+}
@@ -3,6 +3,9 @@ use serde_json::{json, Value};
 use std::path::PathBuf;
 use std::sync::OnceLock;
 
+use super::error::{ToolError, ToolResult};
+use super::registry::{ActionSpec, DomainSpec, FieldSpec};
+
 pub static REGEX_TEMPLATES_DIR: OnceLock<PathBuf> = OnceLock::new();
 
 /// Build a regex pattern string with inline flags from the user-provided flags.
@@ -28,7 +31,43 @@ fn byte_offset_to_char_offset(input: &str, byte_offset: usize) -> usize {
     input[..byte_offset].chars().count()
 }
 
-pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "regex",
+    description: "Regular expression testing and template library",
+    execute,
+    actions: &[
+        ActionSpec::new(
+            "test",
+            "List all matches with capture groups (character offsets)",
+            &[
+                FieldSpec::required("pattern", "string", "regular expression"),
+                FieldSpec::optional("flags", "string", "any of i, m, s, x; g is implied"),
+                FieldSpec::required("input", "string", "text to search"),
+            ],
+            "array: [{ index, end, match, groups }]",
+        ),
+        ActionSpec::new(
+            "replace",
+            "Replace all matches",
+            &[
+                FieldSpec::required("pattern", "string", "regular expression"),
+                FieldSpec::optional("flags", "string", "any of i, m, s, x"),
+                FieldSpec::required("input", "string", "text to search"),
+                FieldSpec::required("replacement", "string", "replacement, supports $1 / ${name}"),
+            ],
+            "string: replaced text",
+        ),
+        ActionSpec::new(
+            "generate",
+            "Look up a template expression by id",
+            &[FieldSpec::optional("kind", "string", "template id, default email")],
+            "string: expression",
+        ),
+        ActionSpec::new("templates", "List bundled regex templates", &[], "array: template objects"),
+    ],
+};
+
+pub fn execute(action: &str, payload: &Value) -> ToolResult {
     match action {
         "test" => {
             let pattern = payload["pattern"].as_str().unwrap_or_default();
@@ -40,7 +79,7 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
             }
 
             let full_pattern = build_pattern_with_flags(pattern, flags);
-            let re = Regex::new(&full_pattern).map_err(|e| format!("regex invalid: {e}"))?;
+            let re = Regex::new(&full_pattern).map_err(|e| ToolError::parse(format!("regex invalid: {e}")))?;
 
             let mut results = Vec::new();
             for caps in re.captures_iter(input) {
@@ -97,7 +136,7 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
             }
 
             let full_pattern = build_pattern_with_flags(pattern, flags);
-            let re = Regex::new(&full_pattern).map_err(|e| format!("regex invalid: {e}"))?;
+            let re = Regex::new(&full_pattern).map_err(|e| ToolError::parse(format!("regex invalid: {e}")))?;
             let result = re.replace_all(input, replacement);
             Ok(json!(result.as_ref()))
         }
@@ -126,7 +165,7 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
             Ok(Value::Array(templates))
         }
 
-        _ => Err(format!("unsupported regex action: {action}")),
+        _ => Err(ToolError::unsupported("regex", action)),
     }
 }
 
@@ -0,0 +1,175 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::error::{ToolError, ToolResult};
+
+/// Handler shared by every action of a domain: `(action, payload) -> result`.
+pub type DomainHandler = fn(&str, &Value) -> ToolResult;
+
+/// One payload field accepted by an action.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldSpec {
+    pub name: &'static str,
+    /// JSON type of the field: string | integer | number | boolean | array | object,
+    /// or json for any JSON value
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub required: bool,
+    pub description: &'static str,
+}
+
+impl FieldSpec {
+    pub const fn required(name: &'static str, kind: &'static str, description: &'static str) -> Self {
+        Self { name, kind, required: true, description }
+    }
+
+    pub const fn optional(name: &'static str, kind: &'static str, description: &'static str) -> Self {
+        Self { name, kind, required: false, description }
+    }
+}
+
+/// One action of a domain, with its payload fields and a short result description.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub payload: &'static [FieldSpec],
+    pub result: &'static str,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub aliases: &'static [&'static str],
+}
+
+impl ActionSpec {
+    pub const fn new(
+        name: &'static str,
+        description: &'static str,
+        payload: &'static [FieldSpec],
+        result: &'static str,
+    ) -> Self {
+        Self { name, description, payload, result, aliases: &[] }
+    }
+
+    /// Extra action names that dispatch to the same handler (kept for older callers).
+    pub const fn aliases(mut self, aliases: &'static [&'static str]) -> Self {
+        self.aliases = aliases;
+        self
+    }
+
+    pub fn matches(&self, action: &str) -> bool {
+        self.name == action || self.aliases.contains(&action)
+    }
+}
+
+/// A tool domain: its declared actions plus the handler they dispatch to.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DomainSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub actions: &'static [ActionSpec],
+    #[serde(skip)]
+    pub execute: DomainHandler,
+}
+
+impl DomainSpec {
+    pub fn action(&self, action: &str) -> Option<&ActionSpec> {
+        self.actions.iter().find(|a| a.matches(action))
+    }
+}
+
+/// Look up a domain spec by name in the given registry.
+pub fn find<'a>(domains: &[&'a DomainSpec], domain: &str) -> Option<&'a DomainSpec> {
+    domains.iter().copied().find(|d| d.name == domain)
+}
+
+/// Domains that only run as direct calls, never as a job or pipeline step:
+/// the API manages its own token and server, which a wrapped call reaching it
+/// through the loopback API must not be able to change.
+const DIRECT_ONLY: &[&str] = &["api"];
+
+/// Resolve a step wrapped by another domain (a job or a pipeline step). Like
+/// `dispatch`, only declared actions are accepted.
+pub fn find_step<'a>(domains: &[&'a DomainSpec], domain: &str, action: &str) -> ToolResult<&'a DomainSpec> {
+    let spec = find(domains, domain)
+        .filter(|d| !DIRECT_ONLY.contains(&d.name))
+        .ok_or_else(|| ToolError::unsupported(domain, action))?;
+    if spec.action(action).is_none() {
+        return Err(ToolError::unsupported(domain, action));
+    }
+    Ok(spec)
+}
+
+/// Dispatch through the registry. Only declared actions reach the domain handler.
+pub fn dispatch(domains: &[&DomainSpec], domain: &str, action: &str, payload: &Value) -> ToolResult {
+    let spec = find(domains, domain).ok_or_else(|| ToolError::unsupported(domain, action))?;
+    if spec.action(action).is_none() {
+        return Err(ToolError::unsupported(domain, action));
+    }
+    (spec.execute)(action, payload)
+}
+
+/// Machine-readable catalog of all domains, or of a single one when `domain` is given.
+pub fn describe(domains: &[&DomainSpec], domain: Option<&str>) -> ToolResult {
+    match domain.filter(|d| !d.is_empty()) {
+        Some(name) => {
+            let spec = find(domains, name).ok_or_else(|| ToolError::not_found(format!("unknown domain: {name}")))?;
+            Ok(serde_json::to_value(spec).map_err(|e| format!("serialize domain spec failed: {e}"))?)
+        }
+        None => {
+            let list = domains
+                .iter()
+                .map(|d| serde_json::to_value(d).map_err(|e| format!("serialize domain spec failed: {e}")))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(json!({ "domains": list }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn echo(action: &str, payload: &Value) -> ToolResult {
+        Ok(json!({ "action": action, "payload": payload }))
+    }
+
+    const ECHO: DomainSpec = DomainSpec {
+        name: "echo",
+        description: "test domain",
+        actions: &[
+            ActionSpec::new("say", "echo back", &[FieldSpec::required("input", "string", "text")], "object")
+                .aliases(&["speak"]),
+        ],
+        execute: echo,
+    };
+
+    #[test]
+    fn dispatch_declared_action_and_alias() {
+        let domains = [&ECHO];
+        let out = dispatch(&domains, "echo", "say", &json!({"input": "hi"})).expect("dispatch");
+        assert_eq!(out["action"], "say");
+        assert!(dispatch(&domains, "echo", "speak", &json!({})).is_ok());
+    }
+
+    #[test]
+    fn dispatch_undeclared_action_should_fail() {
+        let domains = [&ECHO];
+        let err = dispatch(&domains, "echo", "shout", &json!({})).expect_err("should fail");
+        assert_eq!(err.code(), "UNSUPPORTED_ACTION");
+        assert_eq!(err.message(), "unsupported command: echo.shout");
+        assert!(dispatch(&domains, "nope", "say", &json!({})).is_err());
+    }
+
+    #[test]
+    fn describe_lists_fields() {
+        let domains = [&ECHO];
+        let catalog = describe(&domains, None).expect("describe");
+        let field = &catalog["domains"][0]["actions"][0]["payload"][0];
+        assert_eq!(field["name"], "input");
+        assert_eq!(field["type"], "string");
+        assert_eq!(field["required"], true);
+        assert_eq!(catalog["domains"][0]["actions"][0]["aliases"][0], "speak");
+    }
+}
@@ -1,31 +1,57 @@
 use jsonschema::JSONSchema;
 use serde_json::{json, Map, Value};
 
-pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
+use super::error::{ToolError, ToolResult};
+use super::registry::{ActionSpec, DomainSpec, FieldSpec};
+
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "schema",
+    description: "JSON Schema validation and example generation",
+    execute,
+    actions: &[
+        ActionSpec::new(
+            "validate",
+            "Validate a JSON document against a schema",
+            &[
+                FieldSpec::required("schema", "string", "JSON Schema text"),
+                FieldSpec::required("document", "string", "JSON document text"),
+            ],
+            "object: { valid, errors }",
+        ),
+        ActionSpec::new(
+            "generate_example",
+            "Generate an example document from a schema",
+            &[FieldSpec::required("schema", "string", "JSON Schema text")],
+            "object: { example, warnings }",
+        ),
+    ],
+};
+
+pub fn execute(action: &str, payload: &Value) -> ToolResult {
     match action {
         "validate" => validate(payload),
         "generate_example" => generate_example(payload),
-        _ => Err(format!("unsupported schema action: {action}")),
+        _ => Err(ToolError::unsupported("schema", action)),
     }
 }
 
-fn validate(payload: &Value) -> Result<Value, String> {
+fn validate(payload: &Value) -> ToolResult {
     let schema_str = payload["schema"].as_str().unwrap_or_default();
     let document_str = payload["document"].as_str().unwrap_or_default();
     if schema_str.trim().is_empty() {
-        return Err("schema is empty".into());
+        return Err(ToolError::invalid_field("schema", "schema is empty"));
     }
     if document_str.trim().is_empty() {
-        return Err("document is empty".into());
+        return Err(ToolError::invalid_field("document", "document is empty"));
     }
 
     let schema: Value =
-        serde_json::from_str(schema_str).map_err(|e| format!("invalid schema json: {e}"))?;
+        serde_json::from_str(schema_str).map_err(|e| ToolError::json("invalid schema json", &e))?;
     let document: Value =
-        serde_json::from_str(document_str).map_err(|e| format!("invalid document json: {e}"))?;
+        serde_json::from_str(document_str).map_err(|e| ToolError::json("invalid document json", &e))?;
 
     let compiled =
-        JSONSchema::compile(&schema).map_err(|e| format!("schema compile failed: {e}"))?;
+        JSONSchema::compile(&schema).map_err(|e| ToolError::invalid_field("schema", format!("schema compile failed: {e}")))?;
 
     let validation = compiled.validate(&document);
     match validation {
@@ -51,13 +77,13 @@ fn validate(payload: &Value) -> Result<Value, String> {
     }
 }
 
-fn generate_example(payload: &Value) -> Result<Value, String> {
+fn generate_example(payload: &Value) -> ToolResult {
     let schema_str = payload["schema"].as_str().unwrap_or_default();
     if schema_str.trim().is_empty() {
-        return Err("schema is empty".into());
+        return Err(ToolError::invalid_field("schema", "schema is empty"));
     }
     let schema: Value =
-        serde_json::from_str(schema_str).map_err(|e| format!("invalid schema json: {e}"))?;
+        serde_json::from_str(schema_str).map_err(|e| ToolError::json("invalid schema json", &e))?;
     let mut warnings = Vec::new();
     let example = example_from_schema(&schema, &mut warnings);
     Ok(json!({
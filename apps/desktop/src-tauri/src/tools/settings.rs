@@ -3,29 +3,103 @@ use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::fs;
 
-use super::helpers::{db_conn, get_data_dir, get_base_dir, get_config_path};
+use super::helpers::{close_db, copy_dir_recursive, db_conn, exclusive_db, get_data_dir, get_base_dir, get_config_path};
+use super::error::{ToolError, ToolResult};
+use super::events;
+use super::registry::{ActionSpec, DomainSpec, FieldSpec};
+use super::settings_schema;
+use super::workspace;
 
-pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
+/// Raised with `{ key, value }` after a setting is written, and with a null
+/// key after an import replaced settings wholesale.
+pub const EVENT_CHANGED: &str = "settings-changed";
+
+const PATH: FieldSpec = FieldSpec::required("path", "string", "file or directory path");
+const MODE: FieldSpec = FieldSpec::optional("mode", "string", "merge | overwrite, default merge");
+const SECTIONS: FieldSpec = FieldSpec::optional(
+    "sections",
+    "array",
+    "settings | hosts | snippets | pipelines | history, default all",
+);
+
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "settings",
+    description: "User settings, data directory and workspace export",
+    execute,
+    actions: &[
+        ActionSpec::new(
+            "get",
+            "Read one setting, falling back to its declared default",
+            &[FieldSpec::required("key", "string", "setting key")],
+            "object: { key, value, isDefault } or null",
+        ),
+        ActionSpec::new(
+            "set",
+            "Validate and write one declared setting",
+            &[
+                FieldSpec::required("key", "string", "setting key, see schema"),
+                FieldSpec::required("value", "string", "setting value; booleans, integers and JSON may also be passed as JSON"),
+            ],
+            "object: { ok, value }",
+        ),
+        ActionSpec::new("get_all", "Read all settings, with defaults for unset keys", &[], "object: key -> value"),
+        ActionSpec::new(
+            "schema",
+            "Declared settings with type, default and allowed values",
+            &[],
+            "array: [{ key, type, default, description, min?, max?, values? }]",
+        ),
+        ActionSpec::new("export", "Export a workspace archive", &[SECTIONS], "object: workspace archive"),
+        ActionSpec::new(
+            "import",
+            "Import a workspace archive",
+            &[FieldSpec::required("data", "string", "archive JSON"), MODE, SECTIONS],
+            "object: { ok, version, imported, skipped: [{ key, reason }] }",
+        ),
+        ActionSpec::new("export_to_file", "Export a workspace archive to a JSON file", &[PATH, SECTIONS], "object: { ok }"),
+        ActionSpec::new(
+            "import_from_file",
+            "Import a workspace archive from a JSON file",
+            &[PATH, MODE, SECTIONS],
+            "object: { ok, version, imported, skipped: [{ key, reason }] }",
+        ),
+        ActionSpec::new("get_data_dir", "Current data directory", &[], "object: { dataDir, baseDir, configPath, isCustom }"),
+        ActionSpec::new("set_data_dir", "Move data to a new directory", &[PATH], "object: { ok, restartRequired }"),
+        ActionSpec::new("reset_data_dir", "Revert to the default data directory", &[], "object: { ok, restartRequired }"),
+    ],
+};
+
+pub fn execute(action: &str, payload: &Value) -> ToolResult {
     match action {
         "get" => settings_get(payload),
         "set" => settings_set(payload),
         "get_all" => settings_get_all(),
-        "export" => settings_export(),
+        "schema" => Ok(settings_schema::describe()),
+        "export" => settings_export(payload),
         "import" => settings_import(payload),
         "export_to_file" => export_to_file(payload),
         "import_from_file" => import_from_file(payload),
         "get_data_dir" => action_get_data_dir(),
         "set_data_dir" => action_set_data_dir(payload),
         "reset_data_dir" => action_reset_data_dir(),
-        _ => Err(format!("unsupported settings action: {action}")),
+        _ => Err(ToolError::unsupported("settings", action)),
     }
 }
 
-fn settings_get(payload: &Value) -> Result<Value, String> {
+fn settings_get(payload: &Value) -> ToolResult {
     let key = payload["key"].as_str().unwrap_or_default();
     if key.is_empty() {
-        return Err("settings key is empty".into());
+        return Err(ToolError::invalid_field("key", "settings key is empty"));
     }
+    match (read_setting(key)?, settings_schema::find(key)) {
+        (Some(v), _) => Ok(json!({ "key": key, "value": v, "isDefault": false })),
+        (None, Some(spec)) => Ok(json!({ "key": key, "value": spec.default, "isDefault": true })),
+        (None, None) => Ok(Value::Null),
+    }
+}
+
+/// Read one stored setting; `None` when the key was never set.
+pub fn read_setting(key: &str) -> Result<Option<String>, String> {
     let conn = db_conn()?;
     let result: Option<String> = conn
         .query_row(
@@ -34,18 +108,11 @@ fn settings_get(payload: &Value) -> Result<Value, String> {
             |row| row.get(0),
         )
         .ok();
-    match result {
-        Some(v) => Ok(json!({ "key": key, "value": v })),
-        None => Ok(Value::Null),
-    }
+    Ok(result)
 }
 
-fn settings_set(payload: &Value) -> Result<Value, String> {
-    let key = payload["key"].as_str().unwrap_or_default();
-    let value = payload["value"].as_str().unwrap_or_default();
-    if key.is_empty() {
-        return Err("settings key is empty".into());
-    }
+/// Insert or replace one setting.
+fn write_setting(key: &str, value: &str) -> Result<(), String> {
     let conn = db_conn()?;
     conn.execute(
         "INSERT INTO user_settings(key, value, updated_at) VALUES(?1, ?2, CURRENT_TIMESTAMP)
@@ -53,10 +120,31 @@ fn settings_set(payload: &Value) -> Result<Value, String> {
         params![key, value],
     )
     .map_err(|e| format!("save setting failed: {e}"))?;
-    Ok(json!({ "ok": true }))
+    Ok(())
 }
 
-fn settings_get_all() -> Result<Value, String> {
+/// Validates `value` against the declared setting, writes it and announces
+/// the change. Returns the stored text.
+pub fn store_setting(key: &str, value: &Value) -> ToolResult<String> {
+    let spec = settings_schema::find(key)
+        .ok_or_else(|| ToolError::invalid_field("key", format!("unknown setting: {key}")))?;
+    let value = settings_schema::validate(spec, value)?;
+    write_setting(key, &value)?;
+    events::emit(EVENT_CHANGED, &json!({ "key": key, "value": value }));
+    Ok(value)
+}
+
+fn settings_set(payload: &Value) -> ToolResult {
+    let key = payload["key"].as_str().unwrap_or_default();
+    if key.is_empty() {
+        return Err(ToolError::invalid_field("key", "settings key is empty"));
+    }
+    let value = store_setting(key, &payload["value"])?;
+    settings_schema::run_hooks([key])?;
+    Ok(json!({ "ok": true, "value": value }))
+}
+
+fn settings_get_all() -> ToolResult {
     let conn = db_conn()?;
     let mut stmt = conn
         .prepare("SELECT key, value FROM user_settings")
@@ -71,139 +159,62 @@ fn settings_get_all() -> Result<Value, String> {
         let (k, v) = r.map_err(|e| e.to_string())?;
         map.insert(k, v);
     }
+    for spec in settings_schema::SETTINGS {
+        map.entry(spec.key.to_string()).or_insert_with(|| spec.default.to_string());
+    }
     Ok(serde_json::to_value(map).map_err(|e| format!("serialize settings failed: {e}"))?)
 }
 
-fn settings_export() -> Result<Value, String> {
-    // Collect all settings
+fn settings_export(payload: &Value) -> ToolResult {
+    let sections = workspace::parse_sections(&payload["sections"])?;
     let conn = db_conn()?;
-    let mut settings_map: HashMap<String, String> = HashMap::new();
-    {
-        let mut stmt = conn
-            .prepare("SELECT key, value FROM user_settings")
-            .map_err(|e| format!("prepare settings query failed: {e}"))?;
-        let rows = stmt
-            .query_map([], |row| {
-                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
-            })
-            .map_err(|e| format!("query settings failed: {e}"))?;
-        for r in rows {
-            let (k, v) = r.map_err(|e| e.to_string())?;
-            settings_map.insert(k, v);
-        }
-    }
-
-    // Collect hosts profiles
-    let mut hosts: Vec<Value> = Vec::new();
-    {
-        let mut stmt = conn
-            .prepare("SELECT name, content, enabled FROM hosts_profiles ORDER BY updated_at DESC")
-            .map_err(|e| format!("prepare hosts query failed: {e}"))?;
-        let rows = stmt
-            .query_map([], |row| {
-                Ok(json!({
-                    "name": row.get::<_, String>(0)?,
-                    "content": row.get::<_, String>(1)?,
-                    "enabled": row.get::<_, i64>(2)?,
-                }))
-            })
-            .map_err(|e| format!("query hosts failed: {e}"))?;
-        for r in rows {
-            hosts.push(r.map_err(|e| e.to_string())?);
-        }
-    }
-
-    let export_data = json!({
-        "version": 2,
-        "exportedAt": chrono::Utc::now().to_rfc3339(),
-        "settings": settings_map,
-        "hosts_profiles": hosts,
-    });
-    Ok(export_data)
+    workspace::export(&conn, &sections)
 }
 
-fn settings_import(payload: &Value) -> Result<Value, String> {
+fn settings_import(payload: &Value) -> ToolResult {
     let data_str = payload["data"].as_str().unwrap_or_default();
-    let mode = payload["mode"].as_str().unwrap_or("merge"); // "merge" or "overwrite"
+    let mode = payload["mode"].as_str().unwrap_or("merge");
     if data_str.is_empty() {
-        return Err("import data is empty".into());
+        return Err(ToolError::invalid_field("data", "import data is empty"));
     }
     let data: Value = serde_json::from_str(data_str)
-        .map_err(|e| format!("parse import data failed: {e}"))?;
-
-    let conn = db_conn()?;
-
-    // Import settings
-    if let Some(settings) = data["settings"].as_object() {
-        if mode == "overwrite" {
-            conn.execute("DELETE FROM user_settings", [])
-                .map_err(|e| format!("clear settings failed: {e}"))?;
-        }
-        for (key, val) in settings {
-            let value_str = match val {
-                Value::String(s) => s.clone(),
-                other => other.to_string(),
-            };
-            conn.execute(
-                "INSERT INTO user_settings(key, value, updated_at) VALUES(?1, ?2, CURRENT_TIMESTAMP)
-                 ON CONFLICT(key) DO UPDATE SET value=excluded.value, updated_at=CURRENT_TIMESTAMP",
-                params![key, value_str],
-            )
-            .map_err(|e| format!("import setting '{key}' failed: {e}"))?;
-        }
+        .map_err(|e| ToolError::json("parse import data failed", &e))?;
+    let sections = workspace::parse_sections(&payload["sections"])?;
+    let mut conn = db_conn()?;
+    let result = workspace::import(&mut conn, &data, mode, &sections)?;
+    if sections.contains(&"settings") {
+        events::emit(EVENT_CHANGED, &json!({ "key": null, "value": null }));
+        settings_schema::run_hooks(settings_schema::SETTINGS.iter().map(|s| s.key))?;
     }
-
-    // Import hosts profiles
-    if let Some(profiles) = data["hosts_profiles"].as_array() {
-        if mode == "overwrite" {
-            conn.execute("DELETE FROM hosts_profiles", [])
-                .map_err(|e| format!("clear hosts_profiles failed: {e}"))?;
-        }
-        for profile in profiles {
-            let name = profile["name"].as_str().unwrap_or_default();
-            let content = profile["content"].as_str().unwrap_or_default();
-            let enabled = profile["enabled"].as_i64().unwrap_or(0);
-            if name.is_empty() {
-                continue;
-            }
-            conn.execute(
-                "INSERT INTO hosts_profiles(name, content, enabled, updated_at) VALUES(?1, ?2, ?3, CURRENT_TIMESTAMP)
-                 ON CONFLICT(name) DO UPDATE SET content=excluded.content, enabled=excluded.enabled, updated_at=CURRENT_TIMESTAMP",
-                params![name, content, enabled],
-            )
-            .map_err(|e| format!("import hosts profile '{name}' failed: {e}"))?;
-        }
-    }
-
-    Ok(json!({ "ok": true }))
+    Ok(result)
 }
 
-fn export_to_file(payload: &Value) -> Result<Value, String> {
+fn export_to_file(payload: &Value) -> ToolResult {
     let path = payload["path"]
         .as_str()
-        .ok_or("path is required")?;
-    let data = settings_export()?;
+        .ok_or_else(|| ToolError::invalid_field("path", "path is required"))?;
+    let data = settings_export(payload)?;
     let json_str = serde_json::to_string_pretty(&data)
         .map_err(|e| format!("serialize export data failed: {e}"))?;
     fs::write(path, json_str)
-        .map_err(|e| format!("write file failed: {e}"))?;
+        .map_err(|e| ToolError::io("write file failed", e))?;
     Ok(json!({ "ok": true }))
 }
 
-fn import_from_file(payload: &Value) -> Result<Value, String> {
+fn import_from_file(payload: &Value) -> ToolResult {
     let path = payload["path"]
         .as_str()
-        .ok_or("path is required")?;
+        .ok_or_else(|| ToolError::invalid_field("path", "path is required"))?;
     let mode = payload["mode"].as_str().unwrap_or("merge");
     let content = fs::read_to_string(path)
-        .map_err(|e| format!("read file failed: {e}"))?;
+        .map_err(|e| ToolError::io("read file failed", e))?;
     // Validate JSON
     serde_json::from_str::<Value>(&content)
-        .map_err(|e| format!("invalid JSON: {e}"))?;
-    settings_import(&json!({ "data": content, "mode": mode }))
+        .map_err(|e| ToolError::json("invalid JSON", &e))?;
+    settings_import(&json!({ "data": content, "mode": mode, "sections": payload["sections"] }))
 }
 
-fn action_get_data_dir() -> Result<Value, String> {
+fn action_get_data_dir() -> ToolResult {
     let data_dir = get_data_dir()?;
     let base_dir = get_base_dir()?;
     let config_path = get_config_path()?;
@@ -216,10 +227,10 @@ fn action_get_data_dir() -> Result<Value, String> {
     }))
 }
 
-fn action_set_data_dir(payload: &Value) -> Result<Value, String> {
+fn action_set_data_dir(payload: &Value) -> ToolResult {
     let target = payload["path"]
         .as_str()
-        .ok_or("path is required")?;
+        .ok_or_else(|| ToolError::invalid_field("path", "path is required"))?;
     let target_path = std::path::PathBuf::from(target);
 
     // 1. Create target dir, test write permission
@@ -233,22 +244,36 @@ fn action_set_data_dir(payload: &Value) -> Result<Value, String> {
     // 2. Check target doesn't already have lazycat.sqlite (avoid overwriting someone else's data)
     let target_db = target_path.join("lazycat.sqlite");
     if target_db.exists() {
-        return Err("target directory already contains lazycat.sqlite, choose an empty directory".into());
+        return Err(ToolError::invalid_field(
+            "path",
+            "target directory already contains lazycat.sqlite, choose an empty directory",
+        ));
     }
 
-    // 3. Copy current data to new directory
+    // 3. Copy current data to new directory. No other connection may commit
+    // until config.json points at the copy, or the commit would stay behind.
+    let exclusive = exclusive_db()?;
     let current_dir = get_data_dir()?;
     let current_db = current_dir.join("lazycat.sqlite");
     if current_db.exists() {
-        fs::copy(&current_db, &target_db)
-            .map_err(|e| format!("copy database failed: {e}"))?;
+        // The backup API reads through the WAL, so nothing is missed even if
+        // it was never checkpointed.
+        let source = super::encryption::open(&current_db)?;
+        let copied = super::encryption::open(&target_db)
+            .map_err(ToolError::from)
+            .and_then(|mut dest| super::backup::copy_database(&source, &mut dest));
+        if let Err(e) = copied {
+            let _ = fs::remove_file(&target_db);
+            return Err(e.context("copy database failed"));
+        }
     }
 
-    // Copy hosts-backups directory
-    let current_backups = current_dir.join("hosts-backups");
-    let target_backups = target_path.join("hosts-backups");
-    if current_backups.is_dir() {
-        copy_dir_recursive(&current_backups, &target_backups)?;
+    // Copy hosts-backups and database backup directories
+    for dir in ["hosts-backups", super::backup::BACKUP_DIR] {
+        let current_backups = current_dir.join(dir);
+        if current_backups.is_dir() {
+            copy_dir_recursive(&current_backups, &target_path.join(dir))?;
+        }
     }
 
     // 4. Update config.json
@@ -258,11 +283,12 @@ fn action_set_data_dir(payload: &Value) -> Result<Value, String> {
         .map_err(|e| format!("serialize config failed: {e}"))?;
     fs::write(&config_path, config_str)
         .map_err(|e| format!("write config.json failed: {e}"))?;
+    drop(exclusive);
 
     Ok(json!({ "ok": true, "restartRequired": true }))
 }
 
-fn action_reset_data_dir() -> Result<Value, String> {
+fn action_reset_data_dir() -> ToolResult {
     let config_path = get_config_path()?;
     if config_path.exists() {
         let content = fs::read_to_string(&config_path).unwrap_or_default();
@@ -277,24 +303,6 @@ fn action_reset_data_dir() -> Result<Value, String> {
                 .map_err(|e| format!("write config.json failed: {e}"))?;
         }
     }
+    close_db();
     Ok(json!({ "ok": true, "restartRequired": true }))
 }
-
-fn copy_dir_recursive(src: &std::path::Path, dst: &std::path::Path) -> Result<(), String> {
-    fs::create_dir_all(dst)
-        .map_err(|e| format!("create dir {:?} failed: {e}", dst))?;
-    let entries = fs::read_dir(src)
-        .map_err(|e| format!("read dir {:?} failed: {e}", src))?;
-    for entry in entries {
-        let entry = entry.map_err(|e| format!("read entry failed: {e}"))?;
-        let file_type = entry.file_type().map_err(|e| format!("get file type failed: {e}"))?;
-        let dst_path = dst.join(entry.file_name());
-        if file_type.is_dir() {
-            copy_dir_recursive(&entry.path(), &dst_path)?;
-        } else {
-            fs::copy(entry.path(), &dst_path)
-                .map_err(|e| format!("copy file {:?} failed: {e}", entry.path()))?;
-        }
-    }
-    Ok(())
-}
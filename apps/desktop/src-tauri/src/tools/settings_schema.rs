@@ -0,0 +1,218 @@
+//! Declared settings: key, type, default and allowed values.
+//!
+//! `settings.set` only accepts keys listed here and stores the value in a
+//! canonical text form, so readers can keep parsing `user_settings` rows as
+//! plain strings. Keys that code writes directly (such as the snippet
+//! workspace marker) are internal and not settable. A setting may name a
+//! hook that applies a change right away, run after it is stored.
+
+use serde_json::{json, Value};
+
+use super::error::{ToolError, ToolResult};
+use super::{api, backup, cron, history};
+
+#[derive(Debug, Clone, Copy)]
+pub enum SettingKind {
+    /// Stored as `true` / `false`.
+    Boolean,
+    Integer { min: i64, max: i64 },
+    Text,
+    Enum(&'static [&'static str]),
+    /// A six-field cron expression, as taken by `cron::schedule`.
+    Cron,
+    /// Any JSON document, stored as JSON text.
+    Json,
+}
+
+impl SettingKind {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Boolean => "boolean",
+            Self::Integer { .. } => "integer",
+            Self::Text => "string",
+            Self::Enum(_) => "enum",
+            Self::Cron => "cron",
+            Self::Json => "json",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SettingSpec {
+    pub key: &'static str,
+    pub kind: SettingKind,
+    /// Canonical text form, as stored in `user_settings`.
+    pub default: &'static str,
+    pub description: &'static str,
+    /// Applies a stored change, e.g. restarts the server configured by it.
+    pub on_change: Option<fn() -> ToolResult>,
+}
+
+const fn setting(key: &'static str, kind: SettingKind, default: &'static str, description: &'static str) -> SettingSpec {
+    SettingSpec { key, kind, default, description, on_change: None }
+}
+
+impl SettingSpec {
+    const fn on_change(self, hook: fn() -> ToolResult) -> Self {
+        SettingSpec { on_change: Some(hook), ..self }
+    }
+}
+
+pub const SETTINGS: &[SettingSpec] = &[
+    setting("theme", SettingKind::Enum(&["system", "dark", "light"]), "system", "Color theme"),
+    setting("hotkey", SettingKind::Text, "", "Global shortcut that shows the window, empty for none"),
+    setting("favorites", SettingKind::Json, "[]", "Favorite tool ids"),
+    setting("tool_clicks", SettingKind::Json, "{}", "Tool click counts by month, ranks the home page"),
+    setting("home_top_limit", SettingKind::Integer { min: 1, max: 50 }, "12", "Tools shown on the home page"),
+    setting("menu_visibility", SettingKind::Json, "[]", "Sidebar items hidden by the user"),
+    setting("calc_draft_history", SettingKind::Json, "[]", "Calculator draft history"),
+    setting("dns_query_history", SettingKind::Json, "[]", "Recent DNS queries"),
+    setting("network_test_history", SettingKind::Json, "[]", "Recent network tests"),
+    setting(history::KEY_ENABLED, SettingKind::Boolean, "true", "Record tool calls in the history"),
+    setting(
+        history::KEY_EXCLUDED,
+        SettingKind::Text,
+        history::DEFAULT_EXCLUDED,
+        "Comma-separated domains that are not recorded",
+    ),
+    setting(
+        history::KEY_LIMIT,
+        SettingKind::Integer { min: 1, max: 100_000 },
+        "1000",
+        "Unpinned history entries kept",
+    ),
+    setting(backup::KEY_ENABLED, SettingKind::Boolean, "true", "Back up the database on a schedule"),
+    setting(backup::KEY_SCHEDULE, SettingKind::Cron, backup::DEFAULT_SCHEDULE, "When scheduled backups run"),
+    setting(
+        backup::KEY_RETENTION,
+        SettingKind::Integer { min: 1, max: 100 },
+        "10",
+        "Backups kept per kind; manual backups are never rotated",
+    ),
+    setting(api::KEY_ENABLED, SettingKind::Boolean, "false", "Serve the local HTTP API").on_change(api::apply_settings),
+    setting(api::KEY_PORT, SettingKind::Integer { min: 1, max: 65535 }, "17800", "Local HTTP API port")
+        .on_change(api::apply_settings),
+];
+
+pub fn find(key: &str) -> Option<&'static SettingSpec> {
+    SETTINGS.iter().find(|s| s.key == key)
+}
+
+/// Runs the change hooks of `keys`, each hook once.
+pub fn run_hooks<'a>(keys: impl IntoIterator<Item = &'a str>) -> ToolResult<()> {
+    let mut done: Vec<usize> = Vec::new();
+    for hook in keys.into_iter().filter_map(|key| find(key)?.on_change) {
+        if !done.contains(&(hook as usize)) {
+            done.push(hook as usize);
+            hook()?;
+        }
+    }
+    Ok(())
+}
+
+/// Checks `value` against `spec` and returns the text to store. Booleans and
+/// integers may be given as JSON values or as strings.
+pub fn validate(spec: &SettingSpec, value: &Value) -> ToolResult<String> {
+    let invalid = |msg: String| ToolError::invalid_field("value", format!("{}: {msg}", spec.key));
+    let text = match value {
+        Value::Null => return Err(invalid("value is required".to_string())),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    match spec.kind {
+        SettingKind::Boolean => match text.trim() {
+            "true" | "1" => Ok("true".to_string()),
+            "false" | "0" => Ok("false".to_string()),
+            _ => Err(invalid(format!("expected true or false, got {text:?}"))),
+        },
+        SettingKind::Integer { min, max } => {
+            let n: i64 = text
+                .trim()
+                .parse()
+                .map_err(|_| invalid(format!("expected an integer, got {text:?}")))?;
+            if !(min..=max).contains(&n) {
+                return Err(invalid(format!("must be between {min} and {max}")));
+            }
+            Ok(n.to_string())
+        }
+        SettingKind::Text => match value {
+            Value::String(_) => Ok(text),
+            _ => Err(invalid("expected a string".to_string())),
+        },
+        SettingKind::Enum(values) => {
+            if values.contains(&text.as_str()) {
+                Ok(text)
+            } else {
+                Err(invalid(format!("expected one of {}", values.join(", "))))
+            }
+        }
+        SettingKind::Cron => {
+            cron::schedule(text.trim()).map_err(invalid)?;
+            Ok(text.trim().to_string())
+        }
+        SettingKind::Json => match value {
+            Value::String(_) => {
+                serde_json::from_str::<Value>(&text).map_err(|e| invalid(format!("invalid JSON: {e}")))?;
+                Ok(text)
+            }
+            _ => Ok(text),
+        },
+    }
+}
+
+/// The schema as returned by `settings.schema`.
+pub fn describe() -> Value {
+    let items: Vec<Value> = SETTINGS
+        .iter()
+        .map(|s| {
+            let mut item = json!({
+                "key": s.key,
+                "type": s.kind.name(),
+                "default": s.default,
+                "description": s.description,
+            });
+            match s.kind {
+                SettingKind::Integer { min, max } => {
+                    item["min"] = json!(min);
+                    item["max"] = json!(max);
+                }
+                SettingKind::Enum(values) => item["values"] = json!(values),
+                _ => {}
+            }
+            item
+        })
+        .collect();
+    Value::Array(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_are_valid_and_match_module_constants() {
+        for spec in SETTINGS {
+            let stored = validate(spec, &json!(spec.default)).unwrap_or_else(|e| panic!("{}: {}", spec.key, e.message()));
+            assert_eq!(stored, spec.default, "{} default is not canonical", spec.key);
+        }
+        assert_eq!(find(history::KEY_LIMIT).unwrap().default, history::DEFAULT_LIMIT.to_string());
+        assert_eq!(find(backup::KEY_RETENTION).unwrap().default, backup::DEFAULT_RETENTION.to_string());
+        assert_eq!(find(api::KEY_PORT).unwrap().default, api::DEFAULT_PORT.to_string());
+    }
+
+    #[test]
+    fn validates_and_canonicalizes_values() {
+        let spec = |key| find(key).unwrap();
+        assert_eq!(validate(spec("api.enabled"), &json!(true)).unwrap(), "true");
+        assert_eq!(validate(spec("api.enabled"), &json!("0")).unwrap(), "false");
+        assert_eq!(validate(spec("backup.retention"), &json!(" 7 ")).unwrap(), "7");
+        assert!(validate(spec("backup.retention"), &json!(0)).is_err());
+        assert!(validate(spec("api.port"), &json!("http")).is_err());
+        assert!(validate(spec("theme"), &json!("blue")).is_err());
+        assert!(validate(spec("backup.schedule"), &json!("every day")).is_err());
+        assert!(validate(spec("favorites"), &json!("[\"json\"")).is_err());
+        assert_eq!(validate(spec("favorites"), &json!(["json"])).unwrap(), "[\"json\"]");
+        assert!(validate(spec("hotkey"), &Value::Null).is_err());
+        assert!(find("snippet_workspace_v2_initialized").is_none());
+    }
+}
@@ -2,10 +2,120 @@
 use serde_json::{json, Value};
 
 use super::helpers::db_conn;
+use super::error::{ToolError, ToolResult};
+use super::registry::{ActionSpec, DomainSpec, FieldSpec};
 
 const INIT_KEY: &str = "snippet_workspace_v2_initialized";
 
-pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
+const ID: FieldSpec = FieldSpec::required("id", "integer", "entry id");
+
+const FILTERS: &[FieldSpec] = &[
+    FieldSpec::optional("folder_id", "integer", "only entries in this folder"),
+    FieldSpec::optional("tag", "string", "only entries with this tag"),
+    FieldSpec::optional("favorite_only", "boolean", "only favorites"),
+    FieldSpec::optional("untagged_only", "boolean", "only entries without tags"),
+    FieldSpec::optional("recent_days", "integer", "only entries used in the last N days"),
+    FieldSpec::optional("sort_by", "string", "last_used | updated_at | created_at | title"),
+];
+
+const SEARCH: &[FieldSpec] = &[
+    FieldSpec::required("keyword", "string", "full-text query"),
+    FieldSpec::optional("folder_id", "integer", "only entries in this folder"),
+    FieldSpec::optional("tag", "string", "only entries with this tag"),
+    FieldSpec::optional("favorite_only", "boolean", "only favorites"),
+    FieldSpec::optional("untagged_only", "boolean", "only entries without tags"),
+    FieldSpec::optional("recent_days", "integer", "only entries used in the last N days"),
+    FieldSpec::optional("sort_by", "string", "last_used | updated_at | created_at | title"),
+];
+
+const ENTRY_FIELDS: &[FieldSpec] = &[
+    FieldSpec::optional("title", "string", "entry title"),
+    FieldSpec::optional("description", "string", "entry description"),
+    FieldSpec::optional("folderId", "integer", "folder id, null for none"),
+    FieldSpec::optional("isFavorite", "boolean", "favorite flag"),
+    FieldSpec::optional("fragments", "array", "[{ label, language, code }]"),
+    FieldSpec::optional("tags", "array", "tag strings"),
+];
+
+const UPDATE_FIELDS: &[FieldSpec] = &[
+    ID,
+    FieldSpec::optional("title", "string", "entry title"),
+    FieldSpec::optional("description", "string", "entry description"),
+    FieldSpec::optional("folderId", "integer", "folder id, null for none"),
+    FieldSpec::optional("isFavorite", "boolean", "favorite flag"),
+    FieldSpec::optional("fragments", "array", "[{ label, language, code }], replaces all"),
+    FieldSpec::optional("tags", "array", "tag strings, replaces all"),
+];
+
+const IDS: FieldSpec = FieldSpec::required("ids", "array", "entry ids");
+
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "snippets",
+    description: "Code snippet workspace",
+    execute,
+    actions: &[
+        ActionSpec::new(
+            "v2_init",
+            "Initialize or reset the workspace schema",
+            &[FieldSpec::optional("confirm", "boolean", "required to reset an initialized workspace")],
+            "object: { initialized, requiresConfirm, message? }",
+        ),
+        ActionSpec::new("v2_list", "List entries", FILTERS, "array: entry summaries").aliases(&["list"]),
+        ActionSpec::new("v2_get", "Get one entry with fragments and tags", &[ID], "object: entry").aliases(&["get"]),
+        ActionSpec::new("v2_create", "Create an entry", ENTRY_FIELDS, "object: entry").aliases(&["create"]),
+        ActionSpec::new("v2_update", "Update an entry", UPDATE_FIELDS, "object: entry").aliases(&["update"]),
+        ActionSpec::new("v2_delete", "Delete an entry", &[ID], "object: { ok }").aliases(&["delete"]),
+        ActionSpec::new("v2_search", "Full-text search entries", SEARCH, "array: entry summaries").aliases(&["search"]),
+        ActionSpec::new("v2_mark_used", "Bump use count and last-used time", &[ID], "object: { ok }"),
+        ActionSpec::new("v2_tag_stats", "Tag usage counts", &[], "array: [{ tag, count }]").aliases(&["tags"]),
+        ActionSpec::new("v2_folder_list", "List folders", &[], "array: folders").aliases(&["folder_list"]),
+        ActionSpec::new(
+            "v2_folder_create",
+            "Create a folder",
+            &[
+                FieldSpec::optional("name", "string", "folder name"),
+                FieldSpec::optional("parentId", "integer", "parent folder id"),
+            ],
+            "object: { id }",
+        )
+        .aliases(&["folder_create"]),
+        ActionSpec::new(
+            "v2_folder_update",
+            "Rename or move a folder",
+            &[
+                FieldSpec::required("id", "integer", "folder id"),
+                FieldSpec::optional("name", "string", "folder name"),
+                FieldSpec::optional("parentId", "integer", "parent folder id"),
+            ],
+            "object: { ok }",
+        )
+        .aliases(&["folder_update"]),
+        ActionSpec::new(
+            "v2_folder_delete",
+            "Delete a folder",
+            &[FieldSpec::required("id", "integer", "folder id")],
+            "object: { ok }",
+        )
+        .aliases(&["folder_delete"]),
+        ActionSpec::new("toggle_favorite", "Flip the favorite flag", &[ID], "object: { id, isFavorite }"),
+        ActionSpec::new("language_stats", "Fragment counts per language", &[], "array: [{ language, count }]"),
+        ActionSpec::new(
+            "batch_update",
+            "Apply favorite, folder and tag changes to several entries",
+            &[
+                IDS,
+                FieldSpec::optional("setFavorite", "boolean", "favorite flag"),
+                FieldSpec::optional("folderId", "integer", "folder id, null to clear"),
+                FieldSpec::optional("addTags", "array", "tags to add"),
+                FieldSpec::optional("removeTags", "array", "tags to remove"),
+            ],
+            "object: { ok, affected }",
+        ),
+        ActionSpec::new("batch_delete", "Delete several entries", &[IDS], "object: { ok, affected }"),
+    ],
+};
+
+pub fn execute(action: &str, payload: &Value) -> ToolResult {
     match action {
         "v2_init" => v2_init(payload),
         "v2_list" | "list" => v2_list(payload),
@@ -24,14 +134,14 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
         "language_stats" => language_stats(),
         "batch_update" => batch_update(payload),
         "batch_delete" => batch_delete(payload),
-        _ => Err(format!("unsupported snippets action: {action}")),
+        _ => Err(ToolError::unsupported("snippets", action)),
     }
 }
 
-fn parse_ids(payload: &Value) -> Result<Vec<i64>, String> {
+fn parse_ids(payload: &Value) -> ToolResult<Vec<i64>> {
     let ids = payload["ids"]
         .as_array()
-        .ok_or("ids is required and must be an array")?;
+        .ok_or_else(|| ToolError::invalid_field("ids", "ids is required and must be an array"))?;
     let mut out = Vec::new();
     for v in ids {
         if let Some(id) = v.as_i64() {
@@ -41,7 +151,7 @@ fn parse_ids(payload: &Value) -> Result<Vec<i64>, String> {
         }
     }
     if out.is_empty() {
-        return Err("ids is empty".to_string());
+        return Err(ToolError::invalid_field("ids", "ids is empty"));
     }
     Ok(out)
 }
@@ -105,7 +215,8 @@ fn has_fts(conn: &Connection) -> bool {
     .unwrap_or(false)
 }
 
-fn set_initialized(conn: &Connection) -> Result<(), String> {
+/// Marks the v2 workspace as in use so `v2_init` won't reset it.
+pub fn set_initialized(conn: &Connection) -> Result<(), String> {
     conn.execute(
         "INSERT INTO user_settings (key, value, updated_at) VALUES (?1, '1', CURRENT_TIMESTAMP)
          ON CONFLICT(key) DO UPDATE SET value='1', updated_at=CURRENT_TIMESTAMP",
@@ -243,7 +354,29 @@ fn rebuild_fts_for_entry(conn: &Connection, entry_id: i64) -> Result<(), String>
     Ok(())
 }
 
-fn collect_tags(conn: &Connection, entry_id: i64) -> Result<Value, String> {
+/// Re-index every entry, e.g. after a workspace import.
+pub fn rebuild_fts(conn: &Connection) -> Result<(), String> {
+    if !has_fts(conn) {
+        return Ok(());
+    }
+    conn.execute("DELETE FROM snippet_fts", [])
+        .map_err(|e| format!("clear fts failed: {e}"))?;
+    let ids: Vec<i64> = {
+        let mut stmt = conn
+            .prepare("SELECT id FROM snippet_entries")
+            .map_err(|e| format!("prepare entry ids failed: {e}"))?;
+        let rows = stmt
+            .query_map([], |r| r.get(0))
+            .map_err(|e| format!("query entry ids failed: {e}"))?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+    };
+    for id in ids {
+        rebuild_fts_for_entry(conn, id)?;
+    }
+    Ok(())
+}
+
+fn collect_tags(conn: &Connection, entry_id: i64) -> ToolResult {
     let mut stmt = conn
         .prepare("SELECT tag FROM snippet_entry_tags WHERE entry_id = ?1 ORDER BY tag ASC")
         .map_err(|e| format!("prepare tags failed: {e}"))?;
@@ -258,7 +391,7 @@ fn collect_tags(conn: &Connection, entry_id: i64) -> Result<Value, String> {
     Ok(Value::Array(tags))
 }
 
-fn collect_fragments(conn: &Connection, entry_id: i64) -> Result<Value, String> {
+fn collect_fragments(conn: &Connection, entry_id: i64) -> ToolResult {
     let mut stmt = conn
         .prepare(
             "SELECT id, label, language, code, sort_order
@@ -317,7 +450,7 @@ fn row_with_tags(mut row: Value) -> Value {
     row
 }
 
-fn v2_init(payload: &Value) -> Result<Value, String> {
+fn v2_init(payload: &Value) -> ToolResult {
     let confirm = payload["confirm"].as_bool().unwrap_or(false);
     let conn = db_conn()?;
 
@@ -338,7 +471,7 @@ fn v2_init(payload: &Value) -> Result<Value, String> {
     Ok(json!({ "initialized": true, "requiresConfirm": false }))
 }
 
-fn v2_list(payload: &Value) -> Result<Value, String> {
+fn v2_list(payload: &Value) -> ToolResult {
     let conn = db_conn()?;
     let folder_id = payload["folder_id"].as_i64();
     let tag = payload["tag"].as_str().unwrap_or_default();
@@ -416,8 +549,8 @@ fn v2_list(payload: &Value) -> Result<Value, String> {
     Ok(Value::Array(out))
 }
 
-fn v2_get(payload: &Value) -> Result<Value, String> {
-    let entry_id = payload["id"].as_i64().ok_or("id is required")?;
+fn v2_get(payload: &Value) -> ToolResult {
+    let entry_id = payload["id"].as_i64().ok_or_else(|| ToolError::invalid_field("id", "id is required"))?;
     let conn = db_conn()?;
 
     let mut snippet = conn
@@ -447,7 +580,7 @@ fn v2_get(payload: &Value) -> Result<Value, String> {
     Ok(snippet)
 }
 
-fn v2_create(payload: &Value) -> Result<Value, String> {
+fn v2_create(payload: &Value) -> ToolResult {
     let conn = db_conn()?;
     let title = payload["title"].as_str().unwrap_or_default();
     let description = payload["description"].as_str().unwrap_or_default();
@@ -497,8 +630,8 @@ fn v2_create(payload: &Value) -> Result<Value, String> {
     v2_get(&json!({ "id": entry_id }))
 }
 
-fn v2_update(payload: &Value) -> Result<Value, String> {
-    let entry_id = payload["id"].as_i64().ok_or("id is required")?;
+fn v2_update(payload: &Value) -> ToolResult {
+    let entry_id = payload["id"].as_i64().ok_or_else(|| ToolError::invalid_field("id", "id is required"))?;
     let conn = db_conn()?;
 
     if let Some(title) = payload["title"].as_str() {
@@ -584,8 +717,8 @@ fn v2_update(payload: &Value) -> Result<Value, String> {
     v2_get(&json!({ "id": entry_id }))
 }
 
-fn v2_delete(payload: &Value) -> Result<Value, String> {
-    let entry_id = payload["id"].as_i64().ok_or("id is required")?;
+fn v2_delete(payload: &Value) -> ToolResult {
+    let entry_id = payload["id"].as_i64().ok_or_else(|| ToolError::invalid_field("id", "id is required"))?;
     let conn = db_conn()?;
     conn.execute("DELETE FROM snippet_entries WHERE id = ?1", params![entry_id])
         .map_err(|e| format!("v2_delete failed: {e}"))?;
@@ -594,8 +727,8 @@ fn v2_delete(payload: &Value) -> Result<Value, String> {
     Ok(json!({ "ok": true }))
 }
 
-fn v2_mark_used(payload: &Value) -> Result<Value, String> {
-    let entry_id = payload["id"].as_i64().ok_or("id is required")?;
+fn v2_mark_used(payload: &Value) -> ToolResult {
+    let entry_id = payload["id"].as_i64().ok_or_else(|| ToolError::invalid_field("id", "id is required"))?;
     let conn = db_conn()?;
     conn.execute(
         "UPDATE snippet_entries
@@ -610,7 +743,7 @@ fn v2_mark_used(payload: &Value) -> Result<Value, String> {
     Ok(json!({ "ok": true }))
 }
 
-fn v2_search(payload: &Value) -> Result<Value, String> {
+fn v2_search(payload: &Value) -> ToolResult {
     let keyword = payload["keyword"].as_str().unwrap_or_default().trim();
     if keyword.is_empty() {
         return v2_list(payload);
@@ -711,7 +844,7 @@ fn v2_search(payload: &Value) -> Result<Value, String> {
     Ok(Value::Array(out))
 }
 
-fn v2_tag_stats() -> Result<Value, String> {
+fn v2_tag_stats() -> ToolResult {
     let conn = db_conn()?;
     let mut stmt = conn
         .prepare(
@@ -738,7 +871,7 @@ fn v2_tag_stats() -> Result<Value, String> {
     Ok(Value::Array(out))
 }
 
-fn v2_folder_list() -> Result<Value, String> {
+fn v2_folder_list() -> ToolResult {
     let conn = db_conn()?;
     let mut stmt = conn
         .prepare(
@@ -777,7 +910,7 @@ fn v2_folder_list() -> Result<Value, String> {
     Ok(Value::Array(out))
 }
 
-fn v2_folder_create(payload: &Value) -> Result<Value, String> {
+fn v2_folder_create(payload: &Value) -> ToolResult {
     let conn = db_conn()?;
     let name = payload["name"].as_str().unwrap_or("新建文件夹").trim();
     let parent_id = payload["parentId"].as_i64();
@@ -798,9 +931,9 @@ fn v2_folder_create(payload: &Value) -> Result<Value, String> {
     Ok(json!({ "ok": true, "id": conn.last_insert_rowid() }))
 }
 
-fn v2_folder_update(payload: &Value) -> Result<Value, String> {
+fn v2_folder_update(payload: &Value) -> ToolResult {
     let conn = db_conn()?;
-    let folder_id = payload["id"].as_i64().ok_or("id is required")?;
+    let folder_id = payload["id"].as_i64().ok_or_else(|| ToolError::invalid_field("id", "id is required"))?;
 
     if let Some(name) = payload["name"].as_str() {
         conn.execute(
@@ -821,9 +954,9 @@ fn v2_folder_update(payload: &Value) -> Result<Value, String> {
     Ok(json!({ "ok": true }))
 }
 
-fn v2_folder_delete(payload: &Value) -> Result<Value, String> {
+fn v2_folder_delete(payload: &Value) -> ToolResult {
     let conn = db_conn()?;
-    let folder_id = payload["id"].as_i64().ok_or("id is required")?;
+    let folder_id = payload["id"].as_i64().ok_or_else(|| ToolError::invalid_field("id", "id is required"))?;
 
     conn.execute(
         "UPDATE snippet_entries SET folder_id = NULL WHERE folder_id = ?1",
@@ -840,8 +973,8 @@ fn v2_folder_delete(payload: &Value) -> Result<Value, String> {
     Ok(json!({ "ok": true }))
 }
 
-fn toggle_favorite(payload: &Value) -> Result<Value, String> {
-    let entry_id = payload["id"].as_i64().ok_or("id is required")?;
+fn toggle_favorite(payload: &Value) -> ToolResult {
+    let entry_id = payload["id"].as_i64().ok_or_else(|| ToolError::invalid_field("id", "id is required"))?;
     let conn = db_conn()?;
     conn.execute(
         "UPDATE snippet_entries
@@ -860,7 +993,7 @@ fn toggle_favorite(payload: &Value) -> Result<Value, String> {
     Ok(json!({ "id": entry_id, "isFavorite": favorite == 1 }))
 }
 
-fn language_stats() -> Result<Value, String> {
+fn language_stats() -> ToolResult {
     let conn = db_conn()?;
     let mut stmt = conn
         .prepare(
@@ -887,7 +1020,7 @@ fn language_stats() -> Result<Value, String> {
     Ok(Value::Array(out))
 }
 
-fn batch_update(payload: &Value) -> Result<Value, String> {
+fn batch_update(payload: &Value) -> ToolResult {
     let ids = parse_ids(payload)?;
     let set_favorite = payload.get("setFavorite").and_then(|v| v.as_bool());
     let has_folder = payload.get("folderId").is_some();
@@ -896,7 +1029,7 @@ fn batch_update(payload: &Value) -> Result<Value, String> {
     let remove_tags = parse_tags(payload, "removeTags");
 
     if set_favorite.is_none() && !has_folder && add_tags.is_empty() && remove_tags.is_empty() {
-        return Err("batch_update requires at least one operation".to_string());
+        return Err(ToolError::invalid("batch_update requires at least one operation"));
     }
 
     let mut conn = db_conn()?;
@@ -955,7 +1088,7 @@ fn batch_update(payload: &Value) -> Result<Value, String> {
     Ok(json!({ "ok": true, "affected": ids.len() }))
 }
 
-fn batch_delete(payload: &Value) -> Result<Value, String> {
+fn batch_delete(payload: &Value) -> ToolResult {
     let ids = parse_ids(payload)?;
     let mut conn = db_conn()?;
     let tx = conn
@@ -973,3 +1106,52 @@ fn batch_delete(payload: &Value) -> Result<Value, String> {
         .map_err(|e| format!("batch_delete commit failed: {e}"))?;
     Ok(json!({ "ok": true, "affected": affected }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result_of(action: &str) -> &'static str {
+        SPEC.action(action).expect("declared action").result
+    }
+
+    /// Checks `value` against an `object: { a, b? }` result description.
+    fn assert_declared_shape(action: &str, value: &Value) {
+        let fields = result_of(action)
+            .strip_prefix("object: {")
+            .and_then(|f| f.strip_suffix('}'))
+            .expect("object result with fields");
+        let declared: Vec<(&str, bool)> = fields
+            .split(',')
+            .map(str::trim)
+            .map(|f| f.strip_suffix('?').map_or((f, true), |f| (f, false)))
+            .collect();
+        let object = value.as_object().expect("object");
+        for (key, required) in &declared {
+            assert!(!required || object.contains_key(*key), "{action}: missing {key}");
+        }
+        for key in object.keys() {
+            assert!(declared.iter().any(|(k, _)| k == key), "{action}: undeclared {key}");
+        }
+    }
+
+    #[test]
+    fn declared_results_match_returned_shapes() {
+        crate::tools::helpers::use_test_data_dir();
+        assert_declared_shape("v2_init", &v2_init(&json!({})).expect("init"));
+
+        let created = v2_create(&json!({ "title": "shape check", "tags": ["spec"] })).expect("create");
+        let id = created["id"].as_i64().expect("id");
+        let entry = v2_get(&json!({ "id": id })).expect("get");
+        let keys = |v: &Value| v.as_object().expect("object").keys().cloned().collect::<Vec<_>>();
+        assert_eq!(result_of("v2_create"), result_of("v2_get"));
+        assert_eq!(keys(&created), keys(&entry));
+
+        let updated = v2_update(&json!({ "id": id, "title": "shape check 2" })).expect("update");
+        assert_eq!(result_of("v2_update"), result_of("v2_get"));
+        assert_eq!(keys(&updated), keys(&entry));
+        assert_eq!(updated["title"], "shape check 2");
+
+        assert_declared_shape("v2_delete", &v2_delete(&json!({ "id": id })).expect("delete"));
+    }
+}
@@ -1,7 +1,25 @@
 use serde_json::{json, Value};
 use std::collections::HashSet;
 
-pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
+use super::error::{ToolError, ToolResult};
+use super::registry::{ActionSpec, DomainSpec, FieldSpec};
+
+const LINES: &[FieldSpec] = &[
+    FieldSpec::required("input", "string", "multi-line text"),
+    FieldSpec::optional("caseSensitive", "boolean", "compare lines case-sensitively, default false"),
+];
+
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "text",
+    description: "Line-oriented text utilities",
+    execute,
+    actions: &[
+        ActionSpec::new("unique_lines", "Drop duplicate lines, keeping the first occurrence", LINES, "string: text"),
+        ActionSpec::new("sort_lines", "Sort lines alphabetically", LINES, "string: text"),
+    ],
+};
+
+pub fn execute(action: &str, payload: &Value) -> ToolResult {
     match action {
         "unique_lines" => {
             let input = payload["input"].as_str().unwrap_or_default();
@@ -32,6 +50,6 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
             }
             Ok(json!(lines.join("\n")))
         }
-        _ => Err(format!("unsupported text action: {action}")),
+        _ => Err(ToolError::unsupported("text", action)),
     }
 }
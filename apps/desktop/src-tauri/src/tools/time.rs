@@ -1,7 +1,30 @@
 use chrono::{Local, TimeZone, Utc};
 use serde_json::{json, Value};
 
-pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
+use super::error::{ToolError, ToolResult};
+use super::registry::{ActionSpec, DomainSpec, FieldSpec};
+
+pub const SPEC: DomainSpec = DomainSpec {
+    name: "time",
+    description: "Timestamp and date conversion",
+    execute,
+    actions: &[
+        ActionSpec::new(
+            "timestamp_to_date",
+            "Format a Unix timestamp (seconds or milliseconds) in local time",
+            &[FieldSpec::required("input", "integer", "Unix timestamp")],
+            "string: yyyy-MM-dd HH:mm:ss",
+        ),
+        ActionSpec::new(
+            "date_to_timestamp",
+            "Parse an RFC 3339 or local yyyy-MM-dd HH:mm:ss date",
+            &[FieldSpec::required("input", "string", "date text")],
+            "object: { seconds, milliseconds }",
+        ),
+    ],
+};
+
+pub fn execute(action: &str, payload: &Value) -> ToolResult {
     match action {
         "timestamp_to_date" => {
             let input = payload["input"].as_i64().unwrap_or_default();
@@ -9,7 +32,7 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
             let dt_local = Local
                 .timestamp_millis_opt(ts_ms)
                 .single()
-                .ok_or("invalid timestamp".to_string())?;
+                .ok_or_else(|| ToolError::invalid_field("input", "invalid timestamp"))?;
             Ok(json!(dt_local.format("%Y-%m-%d %H:%M:%S").to_string()))
         }
         "date_to_timestamp" => {
@@ -21,12 +44,12 @@ pub fn execute(action: &str, payload: &Value) -> Result<Value, String> {
                         .datetime_from_str(input, "%Y-%m-%d %H:%M:%S")
                         .map(|d| d.with_timezone(&Utc))
                 })
-                .map_err(|e| format!("invalid datetime: {e}"))?;
+                .map_err(|e| ToolError::parse(format!("invalid datetime: {e}")))?;
             Ok(json!({
                 "seconds": dt.timestamp(),
                 "milliseconds": dt.timestamp_millis()
             }))
         }
-        _ => Err(format!("unsupported time action: {action}")),
+        _ => Err(ToolError::unsupported("time", action)),
     }
 }
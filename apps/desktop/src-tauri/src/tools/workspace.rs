@@ -0,0 +1,572 @@
+//! Full-workspace archives: every user table in one versioned JSON document.
+//!
+//! Rows are stored under their column names. Snippet folder and entry ids are
+//! only references inside the archive: import assigns fresh ids and remaps
+//! `parent_id`, `folder_id` and `entry_id`, so an archive can be merged into a
+//! database that already holds snippets.
+
+use std::collections::{HashMap, HashSet};
+
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use serde_json::{json, Map, Value};
+
+use super::error::{ToolError, ToolResult};
+use super::migrations::SCHEMA_VERSION;
+use super::settings_schema;
+use super::snippets;
+
+/// Version 2 archives held only `settings` and `hosts_profiles`; they still
+/// import, as do version 1 files.
+pub const FORMAT_VERSION: i64 = 3;
+
+pub const SECTIONS: &[&str] = &["settings", "hosts", "snippets", "pipelines", "history"];
+
+const HOSTS_COLUMNS: &[&str] = &[
+    "name",
+    "content",
+    "enabled",
+    "sort_order",
+    "updated_at",
+    "source",
+    "refresh_cron",
+    "last_refreshed_at",
+    "last_attempt_at",
+    "last_error",
+];
+const PIPELINE_COLUMNS: &[&str] = &["name", "description", "steps", "created_at", "updated_at"];
+const HISTORY_COLUMNS: &[&str] = &[
+    "domain",
+    "action",
+    "payload",
+    "ok",
+    "result_summary",
+    "error_code",
+    "duration_ms",
+    "pinned",
+    "created_at",
+];
+// `id` comes first so import can skip it with `[1..]`.
+const FOLDER_COLUMNS: &[&str] = &["id", "name", "parent_id", "sort_order", "created_at"];
+const ENTRY_COLUMNS: &[&str] = &[
+    "id",
+    "title",
+    "description",
+    "folder_id",
+    "is_favorite",
+    "primary_language",
+    "created_at",
+    "updated_at",
+    "last_used_at",
+    "use_count",
+];
+const FRAGMENT_COLUMNS: &[&str] = &["entry_id", "label", "language", "code", "sort_order"];
+const TAG_COLUMNS: &[&str] = &["entry_id", "tag"];
+const SNIPPET_TABLES: &[&str] = &[
+    "snippet_entry_tags",
+    "snippet_fragments_v2",
+    "snippet_entries",
+    "snippet_folders_v2",
+];
+
+/// Sections named by an optional `sections` array; all of them when absent.
+pub fn parse_sections(value: &Value) -> ToolResult<Vec<&'static str>> {
+    if value.is_null() {
+        return Ok(SECTIONS.to_vec());
+    }
+    let items = value
+        .as_array()
+        .ok_or_else(|| ToolError::invalid_field("sections", "sections must be an array"))?;
+    let mut out = Vec::new();
+    for item in items {
+        let name = item.as_str().unwrap_or_default();
+        let known = SECTIONS.iter().find(|s| **s == name).ok_or_else(|| {
+            ToolError::invalid_field(
+                "sections",
+                format!("unknown section '{name}', expected one of: {}", SECTIONS.join(", ")),
+            )
+        })?;
+        if !out.contains(known) {
+            out.push(*known);
+        }
+    }
+    Ok(out)
+}
+
+pub fn export(conn: &Connection, sections: &[&str]) -> ToolResult {
+    let mut doc = Map::new();
+    doc.insert("version".into(), json!(FORMAT_VERSION));
+    doc.insert("exportedAt".into(), json!(chrono::Utc::now().to_rfc3339()));
+    doc.insert("schemaVersion".into(), json!(SCHEMA_VERSION));
+    doc.insert("sections".into(), json!(sections));
+    for section in sections {
+        match *section {
+            "settings" => {
+                let settings: Map<String, Value> = dump(conn, "user_settings", &["key", "value"], "key")?
+                    .into_iter()
+                    .map(|row| (row["key"].as_str().unwrap_or_default().to_string(), row["value"].clone()))
+                    .collect();
+                doc.insert("settings".into(), Value::Object(settings));
+            }
+            "hosts" => {
+                let rows = dump(conn, "hosts_profiles", HOSTS_COLUMNS, "sort_order, id")?;
+                doc.insert("hosts_profiles".into(), Value::Array(rows));
+            }
+            "snippets" => {
+                doc.insert(
+                    "snippets".into(),
+                    json!({
+                        "folders": dump(conn, "snippet_folders_v2", FOLDER_COLUMNS, "id")?,
+                        "entries": dump(conn, "snippet_entries", ENTRY_COLUMNS, "id")?,
+                        "fragments": dump(conn, "snippet_fragments_v2", FRAGMENT_COLUMNS, "entry_id, sort_order, id")?,
+                        "tags": dump(conn, "snippet_entry_tags", TAG_COLUMNS, "entry_id, tag")?,
+                    }),
+                );
+            }
+            "pipelines" => {
+                let rows = dump(conn, "pipelines", PIPELINE_COLUMNS, "name")?;
+                doc.insert("pipelines".into(), Value::Array(rows));
+            }
+            "history" => {
+                let rows = dump(conn, "tool_history", HISTORY_COLUMNS, "id")?;
+                doc.insert("history".into(), Value::Array(rows));
+            }
+            _ => {}
+        }
+    }
+    Ok(Value::Object(doc))
+}
+
+/// Imports the selected sections of `data` in one transaction. Overwrite
+/// clears a section only when the archive actually carries it.
+pub fn import(conn: &mut Connection, data: &Value, mode: &str, sections: &[&str]) -> ToolResult {
+    let version = data["version"].as_i64().unwrap_or(1);
+    if version > FORMAT_VERSION {
+        return Err(ToolError::invalid(format!(
+            "archive format {version} is newer than the supported format {FORMAT_VERSION}"
+        )));
+    }
+    let overwrite = match mode {
+        "merge" => false,
+        "overwrite" => true,
+        other => return Err(ToolError::invalid_field("mode", format!("unknown import mode: {other}"))),
+    };
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("import begin tx failed: {e}"))?;
+    let mut imported = Map::new();
+    let mut skipped = Vec::new();
+    for section in sections {
+        let count = match *section {
+            "settings" => import_settings(&tx, &data["settings"], overwrite, &mut skipped)?,
+            "hosts" => import_named(&tx, &data["hosts_profiles"], overwrite, "hosts_profiles", HOSTS_COLUMNS)?,
+            "snippets" => import_snippets(&tx, &data["snippets"], overwrite)?,
+            "pipelines" => import_named(&tx, &data["pipelines"], overwrite, "pipelines", PIPELINE_COLUMNS)?,
+            "history" => import_history(&tx, &data["history"], overwrite)?,
+            _ => None,
+        };
+        if let Some(n) = count {
+            imported.insert(section.to_string(), json!(n));
+        }
+    }
+    tx.commit().map_err(|e| format!("import commit failed: {e}"))?;
+    Ok(json!({ "ok": true, "version": version, "imported": imported, "skipped": skipped }))
+}
+
+fn dump(conn: &Connection, table: &str, columns: &[&str], order: &str) -> Result<Vec<Value>, String> {
+    let sql = format!("SELECT {} FROM {table} ORDER BY {order}", columns.join(", "));
+    let mut stmt = conn
+        .prepare(&sql)
+        .map_err(|e| format!("prepare {table} export failed: {e}"))?;
+    let rows = stmt
+        .query_map([], |row| {
+            let mut obj = Map::new();
+            for (i, column) in columns.iter().enumerate() {
+                let value = match row.get_ref(i)? {
+                    ValueRef::Null | ValueRef::Blob(_) => Value::Null,
+                    ValueRef::Integer(n) => json!(n),
+                    ValueRef::Real(f) => json!(f),
+                    ValueRef::Text(t) => json!(String::from_utf8_lossy(t)),
+                };
+                obj.insert(column.to_string(), value);
+            }
+            Ok(Value::Object(obj))
+        })
+        .map_err(|e| format!("query {table} failed: {e}"))?;
+    rows.collect::<Result<_, _>>()
+        .map_err(|e| format!("read {table} failed: {e}"))
+}
+
+fn sql_value(value: &Value) -> SqlValue {
+    match value {
+        Value::Null => SqlValue::Null,
+        Value::Bool(b) => SqlValue::Integer(i64::from(*b)),
+        Value::Number(n) => n
+            .as_i64()
+            .map(SqlValue::Integer)
+            .unwrap_or_else(|| SqlValue::Real(n.as_f64().unwrap_or_default())),
+        Value::String(s) => SqlValue::Text(s.clone()),
+        other => SqlValue::Text(other.to_string()),
+    }
+}
+
+/// Inserts the `columns` present in `row`; absent ones keep their defaults,
+/// which is how archives from before a column existed still import. With a
+/// `key`, a row that already exists is updated in place.
+fn insert(conn: &Connection, table: &str, columns: &[&str], row: &Value, key: Option<&str>) -> Result<i64, String> {
+    let present: Vec<&str> = columns.iter().copied().filter(|c| row.get(c).is_some()).collect();
+    let placeholders: Vec<String> = (1..=present.len()).map(|i| format!("?{i}")).collect();
+    let mut sql = format!("INSERT INTO {table}({}) VALUES({})", present.join(", "), placeholders.join(", "));
+    if let Some(key) = key {
+        let updates: Vec<String> = present
+            .iter()
+            .filter(|c| **c != key)
+            .map(|c| format!("{c}=excluded.{c}"))
+            .collect();
+        if updates.is_empty() {
+            sql.push_str(&format!(" ON CONFLICT({key}) DO NOTHING"));
+        } else {
+            sql.push_str(&format!(" ON CONFLICT({key}) DO UPDATE SET {}", updates.join(", ")));
+        }
+    }
+    conn.execute(&sql, params_from_iter(present.iter().map(|c| sql_value(&row[*c]))))
+        .map_err(|e| format!("import into {table} failed: {e}"))?;
+    Ok(conn.last_insert_rowid())
+}
+
+fn clear(conn: &Connection, tables: &[&str]) -> Result<(), String> {
+    for table in tables {
+        conn.execute(&format!("DELETE FROM {table}"), [])
+            .map_err(|e| format!("clear {table} failed: {e}"))?;
+    }
+    Ok(())
+}
+
+fn rows(value: &Value) -> &[Value] {
+    value.as_array().map(Vec::as_slice).unwrap_or_default()
+}
+
+/// The new id for an archive id; null when the referenced row wasn't imported.
+fn remap(ids: &HashMap<i64, i64>, old: &Value) -> Value {
+    old.as_i64()
+        .and_then(|id| ids.get(&id))
+        .map_or(Value::Null, |id| json!(id))
+}
+
+/// Imports declared settings through `settings_schema::validate`, like
+/// `settings.set`; unknown keys and invalid values are skipped and reported
+/// in `skipped`. Overwrite only clears declared keys, so internal markers
+/// (such as the snippet workspace flag) survive.
+fn import_settings(
+    conn: &Connection,
+    value: &Value,
+    overwrite: bool,
+    skipped: &mut Vec<Value>,
+) -> Result<Option<usize>, String> {
+    let Some(settings) = value.as_object() else {
+        return Ok(None);
+    };
+    if overwrite {
+        for spec in settings_schema::SETTINGS {
+            conn.execute("DELETE FROM user_settings WHERE key = ?1", params![spec.key])
+                .map_err(|e| format!("clear setting '{}' failed: {e}", spec.key))?;
+        }
+    }
+    let mut count = 0;
+    for (key, val) in settings {
+        let Some(spec) = settings_schema::find(key) else {
+            skipped.push(json!({ "key": key, "reason": "unknown setting" }));
+            continue;
+        };
+        let value_str = match settings_schema::validate(spec, val) {
+            Ok(v) => v,
+            Err(err) => {
+                skipped.push(json!({ "key": key, "reason": err.message() }));
+                continue;
+            }
+        };
+        conn.execute(
+            "INSERT INTO user_settings(key, value, updated_at) VALUES(?1, ?2, CURRENT_TIMESTAMP)
+             ON CONFLICT(key) DO UPDATE SET value=excluded.value, updated_at=CURRENT_TIMESTAMP",
+            params![key, value_str],
+        )
+        .map_err(|e| format!("import setting '{key}' failed: {e}"))?;
+        count += 1;
+    }
+    Ok(Some(count))
+}
+
+/// Tables with a unique `name`: an imported row replaces the one of the same name.
+fn import_named(
+    conn: &Connection,
+    value: &Value,
+    overwrite: bool,
+    table: &str,
+    columns: &[&str],
+) -> Result<Option<usize>, String> {
+    let Some(items) = value.as_array() else {
+        return Ok(None);
+    };
+    if overwrite {
+        clear(conn, &[table])?;
+    }
+    let mut count = 0;
+    for row in items {
+        if row["name"].as_str().unwrap_or_default().is_empty() {
+            continue;
+        }
+        insert(conn, table, columns, row, Some("name"))?;
+        count += 1;
+    }
+    Ok(Some(count))
+}
+
+fn import_history(conn: &Connection, value: &Value, overwrite: bool) -> Result<Option<usize>, String> {
+    let Some(items) = value.as_array() else {
+        return Ok(None);
+    };
+    if overwrite {
+        clear(conn, &["tool_history"])?;
+    }
+    let mut count = 0;
+    for row in items {
+        if !row["domain"].is_string() || !row["action"].is_string() {
+            continue;
+        }
+        // Importing the same archive twice must not double the history.
+        let exists: bool = conn
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM tool_history
+                 WHERE created_at = ?1 AND domain = ?2 AND action = ?3 AND payload = ?4)",
+                params_from_iter(["created_at", "domain", "action", "payload"].iter().map(|c| sql_value(&row[*c]))),
+                |r| r.get(0),
+            )
+            .map_err(|e| format!("query tool_history failed: {e}"))?;
+        if exists {
+            continue;
+        }
+        insert(conn, "tool_history", HISTORY_COLUMNS, row, None)?;
+        count += 1;
+    }
+    Ok(Some(count))
+}
+
+/// Parents go in before their children. A folder whose parent isn't in the
+/// archive, or that sits in a parent cycle, lands at the top level. Merging
+/// reuses an existing folder with the same name under the same parent.
+fn import_folders(conn: &Connection, items: &[Value], overwrite: bool) -> Result<HashMap<i64, i64>, String> {
+    let mut ids: HashMap<i64, i64> = HashMap::new();
+    let mut pending: Vec<&Value> = items
+        .iter()
+        .filter(|r| r["id"].is_i64() && r["name"].is_string())
+        .collect();
+    while !pending.is_empty() {
+        let waiting: HashSet<i64> = pending.iter().filter_map(|r| r["id"].as_i64()).collect();
+        let (mut ready, mut blocked): (Vec<&Value>, Vec<&Value>) = pending.into_iter().partition(|r| {
+            r["parent_id"]
+                .as_i64()
+                .is_none_or(|parent| ids.contains_key(&parent) || !waiting.contains(&parent))
+        });
+        if ready.is_empty() {
+            ready.push(blocked.remove(0));
+        }
+        for row in ready {
+            let parent = remap(&ids, &row["parent_id"]);
+            let existing: Option<i64> = if overwrite {
+                None
+            } else {
+                conn.query_row(
+                    "SELECT id FROM snippet_folders_v2 WHERE name = ?1 AND parent_id IS ?2",
+                    params![row["name"].as_str(), parent.as_i64()],
+                    |r| r.get(0),
+                )
+                .optional()
+                .map_err(|e| format!("query snippet_folders_v2 failed: {e}"))?
+            };
+            let id = match existing {
+                Some(id) => id,
+                None => {
+                    let mut row = row.clone();
+                    row["parent_id"] = parent;
+                    insert(conn, "snippet_folders_v2", &FOLDER_COLUMNS[1..], &row, None)?
+                }
+            };
+            ids.insert(row["id"].as_i64().unwrap_or_default(), id);
+        }
+        pending = blocked;
+    }
+    Ok(ids)
+}
+
+fn import_snippets(conn: &Connection, value: &Value, overwrite: bool) -> Result<Option<usize>, String> {
+    if !value.is_object() {
+        return Ok(None);
+    }
+    if overwrite {
+        clear(conn, SNIPPET_TABLES)?;
+    }
+    let folders = import_folders(conn, rows(&value["folders"]), overwrite)?;
+
+    let mut entries: HashMap<i64, i64> = HashMap::new();
+    for row in rows(&value["entries"]) {
+        let (Some(old_id), Some(title)) = (row["id"].as_i64(), row["title"].as_str()) else {
+            continue;
+        };
+        // Entries keep their creation time, which makes re-importing an
+        // archive skip what the first import already brought in.
+        if !overwrite {
+            let exists: bool = conn
+                .query_row(
+                    "SELECT EXISTS(SELECT 1 FROM snippet_entries WHERE title = ?1 AND created_at = ?2)",
+                    params![title, row["created_at"].as_str()],
+                    |r| r.get(0),
+                )
+                .map_err(|e| format!("query snippet_entries failed: {e}"))?;
+            if exists {
+                continue;
+            }
+        }
+        let mut row = row.clone();
+        row["folder_id"] = remap(&folders, &row["folder_id"]);
+        let id = insert(conn, "snippet_entries", &ENTRY_COLUMNS[1..], &row, None)?;
+        entries.insert(old_id, id);
+    }
+
+    for row in rows(&value["fragments"]) {
+        let entry_id = remap(&entries, &row["entry_id"]);
+        if entry_id.is_null() {
+            continue;
+        }
+        let mut row = row.clone();
+        row["entry_id"] = entry_id;
+        insert(conn, "snippet_fragments_v2", FRAGMENT_COLUMNS, &row, None)?;
+    }
+    for row in rows(&value["tags"]) {
+        let (Some(entry_id), Some(tag)) = (remap(&entries, &row["entry_id"]).as_i64(), row["tag"].as_str()) else {
+            continue;
+        };
+        conn.execute(
+            "INSERT OR IGNORE INTO snippet_entry_tags(entry_id, tag) VALUES(?1, ?2)",
+            params![entry_id, tag],
+        )
+        .map_err(|e| format!("import snippet tag failed: {e}"))?;
+    }
+
+    snippets::rebuild_fts(conn)?;
+    // The tables are in use now; the first-run reset must not wipe them.
+    snippets::set_initialized(conn)?;
+    Ok(Some(entries.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::migrations::migrate;
+
+    fn open() -> Connection {
+        let conn = Connection::open_in_memory().expect("open");
+        migrate(&conn).expect("migrate");
+        conn
+    }
+
+    fn count(conn: &Connection, sql: &str) -> i64 {
+        conn.query_row(sql, [], |r| r.get(0)).expect("count")
+    }
+
+    fn seed(conn: &Connection) {
+        conn.execute_batch(
+            "INSERT INTO user_settings(key, value) VALUES ('theme', 'dark');
+             INSERT INTO hosts_profiles(name, content, source) VALUES ('dev', '127.0.0.1 dev.test', 'https://example.test/hosts');
+             INSERT INTO pipelines(name, steps) VALUES ('p', '[]');
+             INSERT INTO tool_history(domain, action, payload, created_at) VALUES ('encode', 'base64', '{}', '2024-01-01 00:00:00');
+             INSERT INTO snippet_folders_v2(id, name) VALUES (1, 'root');
+             INSERT INTO snippet_folders_v2(id, name, parent_id) VALUES (2, 'child', 1);
+             INSERT INTO snippet_entries(id, title, folder_id, created_at) VALUES (1, 'curl', 2, '2024-01-01 00:00:00');
+             INSERT INTO snippet_fragments_v2(entry_id, code) VALUES (1, 'curl -v localhost');
+             INSERT INTO snippet_entry_tags(entry_id, tag) VALUES (1, 'http');",
+        )
+        .expect("seed");
+    }
+
+    #[test]
+    fn merges_snippets_with_remapped_ids() {
+        let source = open();
+        seed(&source);
+        let archive = export(&source, SECTIONS).expect("export");
+
+        let mut dest = open();
+        dest.execute_batch(
+            "INSERT INTO snippet_folders_v2(name) VALUES ('a'), ('b'), ('c');
+             INSERT INTO snippet_entries(title) VALUES ('x'), ('y');",
+        )
+        .expect("pre-existing");
+        let result = import(&mut dest, &archive, "merge", SECTIONS).expect("import");
+        assert_eq!(result["imported"]["snippets"], 1);
+
+        let (entry, folder): (i64, i64) = dest
+            .query_row("SELECT id, folder_id FROM snippet_entries WHERE title = 'curl'", [], |r| {
+                Ok((r.get(0)?, r.get(1)?))
+            })
+            .expect("entry");
+        let (child, parent): (String, String) = dest
+            .query_row(
+                "SELECT f.name, p.name FROM snippet_folders_v2 f JOIN snippet_folders_v2 p ON p.id = f.parent_id WHERE f.id = ?1",
+                params![folder],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .expect("folder");
+        assert_eq!((child.as_str(), parent.as_str()), ("child", "root"));
+        let found: i64 = dest
+            .query_row("SELECT entry_id FROM snippet_fts WHERE snippet_fts MATCH 'localhost'", [], |r| r.get(0))
+            .expect("fts");
+        assert_eq!(found, entry);
+        assert_eq!(count(&dest, "SELECT count(*) FROM snippet_entry_tags WHERE tag = 'http'"), 1);
+        assert_eq!(count(&dest, "SELECT count(*) FROM hosts_profiles WHERE source IS NOT NULL"), 1);
+        assert_eq!(count(&dest, "SELECT count(*) FROM pipelines"), 1);
+
+        import(&mut dest, &archive, "merge", SECTIONS).expect("reimport");
+        assert_eq!(count(&dest, "SELECT count(*) FROM snippet_entries"), 3);
+        assert_eq!(count(&dest, "SELECT count(*) FROM snippet_folders_v2"), 5);
+        assert_eq!(count(&dest, "SELECT count(*) FROM tool_history"), 1);
+    }
+
+    #[test]
+    fn overwrites_selected_sections_and_reads_v2() {
+        let mut dest = open();
+        seed(&dest);
+        let legacy = json!({
+            "version": 2,
+            "settings": { "theme": "light" },
+            "hosts_profiles": [{ "name": "prod", "content": "10.0.0.1 prod.test", "enabled": 0 }],
+        });
+        import(&mut dest, &legacy, "overwrite", &["hosts"]).expect("import");
+        assert_eq!(count(&dest, "SELECT count(*) FROM hosts_profiles WHERE name = 'prod'"), 1);
+        assert_eq!(count(&dest, "SELECT count(*) FROM hosts_profiles"), 1);
+        assert_eq!(count(&dest, "SELECT count(*) FROM user_settings WHERE value = 'dark'"), 1);
+        assert_eq!(count(&dest, "SELECT count(*) FROM snippet_entries"), 1);
+
+        assert!(import(&mut dest, &json!({ "version": 99 }), "merge", SECTIONS).is_err());
+        assert!(parse_sections(&json!(["snippets", "bogus"])).is_err());
+        assert_eq!(parse_sections(&json!(["history", "history"])).unwrap(), vec!["history"]);
+    }
+
+    #[test]
+    fn imported_settings_are_validated() {
+        let mut dest = open();
+        seed(&dest);
+        dest.execute_batch("INSERT INTO user_settings(key, value) VALUES ('snippet_workspace_v2_initialized', '1');")
+            .expect("marker");
+        let archive = json!({
+            "version": FORMAT_VERSION,
+            "settings": { "theme": "light", "api.port": "abc", "backup.retention": 5, "bogus": "x" },
+        });
+        let result = import(&mut dest, &archive, "overwrite", &["settings"]).expect("import");
+        assert_eq!(result["imported"]["settings"], 2);
+        let skipped: Vec<&str> = result["skipped"].as_array().unwrap().iter().filter_map(|s| s["key"].as_str()).collect();
+        assert_eq!(skipped, vec!["api.port", "bogus"]);
+        assert_eq!(count(&dest, "SELECT count(*) FROM user_settings WHERE key = 'api.port' OR key = 'bogus'"), 0);
+        assert_eq!(count(&dest, "SELECT count(*) FROM user_settings WHERE key = 'backup.retention' AND value = '5'"), 1);
+        assert_eq!(count(&dest, "SELECT count(*) FROM user_settings WHERE key = 'snippet_workspace_v2_initialized'"), 1);
+    }
+}
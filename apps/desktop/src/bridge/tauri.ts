@@ -1,4 +1,5 @@
 ﻿import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
 
 export interface ToolRequest {
   request_id: string;
@@ -23,6 +24,41 @@ export async function unregisterHotkey(): Promise<void> {
   await invoke("unregister_hotkey");
 }
 
+/** Catalog of backend tool domains/actions; pass a domain to describe only that one. Errors come back in the `ToolResponse` envelope, e.g. `NOT_FOUND` for an unknown domain. */
+export async function describeTools(domain?: string): Promise<ToolResponse> {
+  return invoke<ToolResponse>("tool_describe", { domain: domain ?? null });
+}
+
+/** Subscribe to background job progress (`tool-job-progress`) and completion (`tool-job-finished`). */
+export async function onJobEvent(
+  handler: (event: "progress" | "finished", job: Record<string, unknown>) => void
+): Promise<UnlistenFn> {
+  const unlistenProgress = await listen<Record<string, unknown>>("tool-job-progress", (e) =>
+    handler("progress", e.payload)
+  );
+  const unlistenFinished = await listen<Record<string, unknown>>("tool-job-finished", (e) =>
+    handler("finished", e.payload)
+  );
+  return () => {
+    unlistenProgress();
+    unlistenFinished();
+  };
+}
+
+/** A scheduled or manual refresh changed a hosts subscription (`hosts-subscription-refreshed`). */
+export async function onHostsSubscriptionRefreshed(
+  handler: (event: { name: string; enabled: boolean }) => void
+): Promise<UnlistenFn> {
+  return listen<{ name: string; enabled: boolean }>("hosts-subscription-refreshed", (e) => handler(e.payload));
+}
+
+/** A setting was written (`settings-changed`); a null key means an import replaced settings wholesale. */
+export async function onSettingsChanged(
+  handler: (event: { key: string | null; value: string | null }) => void
+): Promise<UnlistenFn> {
+  return listen<{ key: string | null; value: string | null }>("settings-changed", (e) => handler(e.payload));
+}
+
 const CHANNEL_MAP: Record<string, { domain: string; action: string }> = {
   "tool:encode:base64-encode": { domain: "encode", action: "base64_encode" },
   "tool:encode:base64-decode": { domain: "encode", action: "base64_decode" },
@@ -89,13 +125,26 @@ const CHANNEL_MAP: Record<string, { domain: string; action: string }> = {
   "tool:hosts:list": { domain: "hosts", action: "list" },
   "tool:hosts:delete": { domain: "hosts", action: "delete" },
   "tool:hosts:activate": { domain: "hosts", action: "activate" },
+  "tool:hosts:preview": { domain: "hosts", action: "preview" },
+  "tool:hosts:verify": { domain: "hosts", action: "verify" },
+  "tool:hosts:parse": { domain: "hosts", action: "parse" },
+  "tool:hosts:lint": { domain: "hosts", action: "lint" },
+  "tool:hosts:toggle-entry": { domain: "hosts", action: "toggle_entry" },
   "tool:hosts:reorder": { domain: "hosts", action: "reorder" },
+  "tool:hosts:subscribe": { domain: "hosts", action: "subscribe" },
+  "tool:hosts:refresh": { domain: "hosts", action: "refresh" },
+  "tool:hosts:unsubscribe": { domain: "hosts", action: "unsubscribe" },
   "tool:hosts:read-system": { domain: "hosts", action: "read_system" },
   "tool:hosts:admin-check": { domain: "hosts", action: "admin_check" },
   "tool:hosts:backup-list": { domain: "hosts", action: "backup_list" },
   "tool:hosts:backup-restore": { domain: "hosts", action: "backup_restore" },
   "tool:manuals:list": { domain: "manuals", action: "list" },
+  "tool:manuals:import": { domain: "manuals", action: "import" },
+  "tool:manuals:remove": { domain: "manuals", action: "remove" },
+  "tool:manuals:search": { domain: "manuals", action: "search" },
+  "tool:manuals:index": { domain: "manuals", action: "index" },
   "tool:settings:get": { domain: "settings", action: "get" },
+  "tool:settings:schema": { domain: "settings", action: "schema" },
   "tool:settings:set": { domain: "settings", action: "set" },
   "tool:settings:get-all": { domain: "settings", action: "get_all" },
   "tool:settings:export": { domain: "settings", action: "export" },
@@ -105,6 +154,24 @@ const CHANNEL_MAP: Record<string, { domain: string; action: string }> = {
   "tool:settings:get-data-dir": { domain: "settings", action: "get_data_dir" },
   "tool:settings:set-data-dir": { domain: "settings", action: "set_data_dir" },
   "tool:settings:reset-data-dir": { domain: "settings", action: "reset_data_dir" },
+  "tool:backup:create": { domain: "backup", action: "create" },
+  "tool:backup:list": { domain: "backup", action: "list" },
+  "tool:backup:check": { domain: "backup", action: "check" },
+  "tool:backup:restore": { domain: "backup", action: "restore" },
+  "tool:backup:status": { domain: "backup", action: "status" },
+  "tool:encryption:status": { domain: "encryption", action: "status" },
+  "tool:encryption:unlock": { domain: "encryption", action: "unlock" },
+  "tool:encryption:lock": { domain: "encryption", action: "lock" },
+  "tool:encryption:enable": { domain: "encryption", action: "enable" },
+  "tool:encryption:change-password": { domain: "encryption", action: "change_password" },
+  "tool:encryption:disable": { domain: "encryption", action: "disable" },
+  "tool:migrations:status": { domain: "migrations", action: "status" },
+  "tool:migrations:rollback": { domain: "migrations", action: "rollback" },
+  "tool:maintenance:check": { domain: "maintenance", action: "check" },
+  "tool:maintenance:vacuum": { domain: "maintenance", action: "vacuum" },
+  "tool:maintenance:analyze": { domain: "maintenance", action: "analyze" },
+  "tool:maintenance:rebuild-fts": { domain: "maintenance", action: "rebuild_fts" },
+  "tool:maintenance:stats": { domain: "maintenance", action: "stats" },
   "tool:jwt:decode": { domain: "jwt", action: "decode" },
   "tool:hotkey:check": { domain: "hotkey", action: "check" },
   "tool:hotkey:scan": { domain: "hotkey", action: "scan" },
@@ -143,7 +210,25 @@ const CHANNEL_MAP: Record<string, { domain: string; action: string }> = {
   "tool:snippets:v2:folder-list": { domain: "snippets", action: "v2_folder_list" },
   "tool:snippets:v2:folder-create": { domain: "snippets", action: "v2_folder_create" },
   "tool:snippets:v2:folder-update": { domain: "snippets", action: "v2_folder_update" },
-  "tool:snippets:v2:folder-delete": { domain: "snippets", action: "v2_folder_delete" }
+  "tool:snippets:v2:folder-delete": { domain: "snippets", action: "v2_folder_delete" },
+  "tool:pipeline:run": { domain: "pipeline", action: "run" },
+  "tool:pipeline:save": { domain: "pipeline", action: "save" },
+  "tool:pipeline:list": { domain: "pipeline", action: "list" },
+  "tool:pipeline:get": { domain: "pipeline", action: "get" },
+  "tool:pipeline:delete": { domain: "pipeline", action: "delete" },
+  "tool:history:list": { domain: "history", action: "list" },
+  "tool:history:search": { domain: "history", action: "search" },
+  "tool:history:pin": { domain: "history", action: "pin" },
+  "tool:history:replay": { domain: "history", action: "replay" },
+  "tool:history:purge": { domain: "history", action: "purge" },
+  "tool:jobs:start": { domain: "jobs", action: "start" },
+  "tool:jobs:cancel": { domain: "jobs", action: "cancel" },
+  "tool:jobs:status": { domain: "jobs", action: "status" },
+  "tool:jobs:list": { domain: "jobs", action: "list" },
+  "tool:api:status": { domain: "api", action: "status" },
+  "tool:api:configure": { domain: "api", action: "configure" },
+  "tool:api:token": { domain: "api", action: "token" },
+  "tool:api:rotate-token": { domain: "api", action: "rotate_token" }
 };
 
 export async function invokeToolByChannel(
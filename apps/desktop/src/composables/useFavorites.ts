@@ -87,8 +87,8 @@ export function useFavorites(allTools: ToolDef[], isRealToolId: (id: string) =>
     toolClickHistory.value = pruneClicks(rawClicks);
 
     // Home top limit
-    const rawLimit = getSettingJson<string>("home_top_limit", "12");
-    homeTopLimit.value = rawLimit === "6" ? 6 : 12;
+    const rawLimit = getSettingJson<number | string>("home_top_limit", 12);
+    homeTopLimit.value = Number(rawLimit) === 6 ? 6 : 12;
   }
 
   // Auto-persist to SQLite via useSettings
@@ -100,7 +100,7 @@ export function useFavorites(allTools: ToolDef[], isRealToolId: (id: string) =>
     () => setSettingJson("tool_clicks", pruneClicks(toolClickHistory.value)),
     { deep: true },
   );
-  watch(homeTopLimit, (v) => setSettingJson("home_top_limit", String(v)));
+  watch(homeTopLimit, (v) => setSettingJson("home_top_limit", v));
 
   return {
     favoriteToolIds,
@@ -1,5 +1,5 @@
 import { reactive, ref } from "vue";
-import { invokeToolByChannel } from "../bridge/tauri";
+import { invokeToolByChannel, onSettingsChanged } from "../bridge/tauri";
 
 /** All known localStorage keys used by old versions */
 const LEGACY_KEYS = [
@@ -45,17 +45,20 @@ async function migrateFromLocalStorage(): Promise<void> {
     if (raw === null) continue;
     const settingKey = LEGACY_KEY_MAP[legacyKey];
     if (!settingKey) continue;
-    // Only migrate if SQLite doesn't already have this key
-    if (settings[settingKey] !== undefined) continue;
-    settings[settingKey] = raw;
     try {
+      // Only migrate if SQLite doesn't already have this key (get_all fills in defaults)
+      const stored = (await invokeToolByChannel("tool:settings:get", { key: settingKey })) as {
+        isDefault?: boolean;
+      } | null;
+      if (stored && !stored.isDefault) continue;
+      settings[settingKey] = raw;
       await invokeToolByChannel("tool:settings:set", {
         key: settingKey,
         value: raw,
       });
       migrated = true;
     } catch {
-      // IPC unavailable, keep in-memory only
+      // IPC unavailable or legacy value rejected by the schema, keep in-memory only
     }
   }
   // Clean up old localStorage keys after successful migration
@@ -74,10 +77,20 @@ export function initSettings(): Promise<void> {
   if (loadPromise.value) return loadPromise.value;
   loadPromise.value = (async () => {
     await loadAll();
-    // If SQLite is empty, check for legacy localStorage data
-    if (Object.keys(settings).length === 0) {
+    // Legacy localStorage keys are removed once migrated
+    if (LEGACY_KEYS.some((key) => localStorage.getItem(key) !== null)) {
       await migrateFromLocalStorage();
     }
+    // Keep in sync with writes from other windows and the backend
+    onSettingsChanged(({ key, value }) => {
+      if (key === null) {
+        void loadAll();
+      } else if (value !== null) {
+        settings[key] = value;
+      }
+    }).catch(() => {
+      // Not running under Tauri
+    });
     loaded.value = true;
   })();
   return loadPromise.value;
@@ -111,7 +124,7 @@ export function setSetting(key: string, value: string): void {
   settings[key] = value;
   // Async persist to SQLite, fire-and-forget
   invokeToolByChannel("tool:settings:set", { key, value }).catch(() => {
-    // IPC failed, data remains in memory for current session
+    // IPC failed or the schema rejected the value, data remains in memory for current session
   });
 }
 
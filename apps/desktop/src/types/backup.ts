@@ -0,0 +1,21 @@
+export interface DatabaseBackup {
+  filename: string;
+  kind: "auto" | "manual" | "pre-migration" | "pre-restore";
+  size: number;
+  createdAt: string;
+}
+
+export interface BackupStatus {
+  enabled: boolean;
+  schedule: string;
+  retention: number;
+  dir: string;
+  lastAutoBackup: DatabaseBackup | null;
+}
+
+export interface BackupCheckResult {
+  filename: string;
+  ok: boolean;
+  messages: string[];
+  schemaVersion: number;
+}
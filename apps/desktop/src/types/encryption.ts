@@ -0,0 +1,10 @@
+export interface EncryptionStatus {
+  encrypted: boolean;
+  unlocked: boolean;
+}
+
+export interface EncryptionRekeyResult {
+  ok: boolean;
+  backups: number;
+  skipped: number;
+}
@@ -4,6 +4,11 @@ export interface HostsProfile {
   content: string;
   enabled: boolean;
   updatedAt: string;
+  /** Subscription source (path or URL); null for a local profile. */
+  source: string | null;
+  schedule: string | null;
+  lastRefreshedAt: string | null;
+  lastError: string | null;
 }
 
 export interface HostsBackupEntry {
@@ -11,3 +16,49 @@ export interface HostsBackupEntry {
   size: number;
   modifiedAt: string;
 }
+
+export interface HostsConflict {
+  hostname: string;
+  winner: string;
+  mappings: { profile: string; ip: string }[];
+}
+
+export interface HostsPreview {
+  profiles: string[];
+  added: number;
+  removed: number;
+  conflicts: HostsConflict[];
+  diff: { op: " " | "+" | "-"; line: string }[];
+  content: string;
+}
+
+export interface HostsEntry {
+  line: number;
+  ip: string;
+  hostnames: string[];
+  comment: string | null;
+  enabled: boolean;
+}
+
+export interface HostsLintIssue {
+  line: number;
+  severity: "error" | "warning";
+  code: "malformed" | "invalid_ip" | "invalid_hostname" | "duplicate" | "shadowed";
+  message: string;
+}
+
+export interface HostsVerification {
+  passed: number;
+  failed: number;
+  skipped: number;
+  hosts: { hostname: string; expected: string[]; resolved: string[]; ok: boolean; error: string | null }[];
+}
+
+export interface HostsActivateResult {
+  backupPath: string;
+  digest: string;
+  profiles?: string[];
+  conflicts?: HostsConflict[];
+  dnsFlush?: { ok: boolean; method?: string; error?: string };
+  verification?: HostsVerification;
+}
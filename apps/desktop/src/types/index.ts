@@ -1,5 +1,14 @@
 export type { ToolDef, GroupDef, SidebarItem, ToolClickHistory } from "./tools";
-export type { HostsProfile, HostsBackupEntry } from "./hosts";
+export type {
+  HostsProfile,
+  HostsBackupEntry,
+  HostsConflict,
+  HostsPreview,
+  HostsEntry,
+  HostsLintIssue,
+  HostsVerification,
+  HostsActivateResult,
+} from "./hosts";
 export type {
   PortUsageSummary,
   PortUsageStateRow,
@@ -26,3 +35,6 @@ export type {
   CronPreviewV2Response,
   CronDescribeResponse,
 } from "./cron";
+export type { DatabaseBackup, BackupStatus, BackupCheckResult } from "./backup";
+export type { EncryptionStatus, EncryptionRekeyResult } from "./encryption";
+export type { MaintenanceCheckResult, MaintenanceVacuumResult, DatabaseStats } from "./maintenance";
@@ -0,0 +1,21 @@
+export interface MaintenanceCheckResult {
+  ok: boolean;
+  quick: boolean;
+  messages: string[];
+}
+
+export interface MaintenanceVacuumResult {
+  ok: boolean;
+  sizeBefore: number;
+  sizeAfter: number;
+}
+
+export interface DatabaseStats {
+  path: string;
+  databaseSize: number;
+  walSize: number;
+  pageSize: number;
+  pageCount: number;
+  freePages: number;
+  tables: { name: string; rows: number }[];
+}
@@ -8,8 +8,18 @@ export interface ToolRequest {
   payload: Record<string, unknown>;
 }
 
+export type ToolErrorCode =
+  | "INVALID_PAYLOAD"
+  | "NOT_FOUND"
+  | "PERMISSION_DENIED"
+  | "PARSE_ERROR"
+  | "UNSUPPORTED_ACTION"
+  | "IO_ERROR"
+  | "CANCELLED"
+  | "TOOL_EXECUTION_FAILED";
+
 export interface ToolError {
-  code: string;
+  code: ToolErrorCode;
   message: string;
   details?: unknown;
 }
@@ -26,3 +36,49 @@ export interface ToolResponse {
   error?: ToolError;
   meta?: ToolMeta;
 }
+
+export interface ToolFieldSpec {
+  name: string;
+  type: "string" | "integer" | "number" | "boolean" | "array" | "object";
+  required: boolean;
+  description: string;
+}
+
+export interface ToolActionSpec {
+  name: string;
+  description: string;
+  payload: ToolFieldSpec[];
+  result: string;
+  aliases?: string[];
+}
+
+export interface ToolDomainSpec {
+  name: string;
+  description: string;
+  actions: ToolActionSpec[];
+}
+
+export interface ToolCatalog {
+  domains: ToolDomainSpec[];
+}
+
+export type ToolJobStatus = "running" | "succeeded" | "failed" | "cancelled";
+
+export interface ToolJob {
+  jobId: string;
+  domain: string;
+  action: string;
+  status: ToolJobStatus;
+  progress: Record<string, unknown> | null;
+  result: unknown;
+  error: ToolError | null;
+  startedAt: string;
+  finishedAt: string | null;
+}
+
+export interface ToolJobProgressEvent {
+  jobId: string;
+  domain: string;
+  action: string;
+  progress: Record<string, unknown>;
+}